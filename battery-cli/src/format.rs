@@ -0,0 +1,107 @@
+use std::fmt;
+
+use battery::units::electric_potential::volt;
+use battery::units::energy::{joule, watt_hour};
+use battery::units::power::watt;
+use battery::units::ratio::{percent, ratio};
+use battery::units::thermodynamic_temperature::{degree_celsius, kelvin};
+use battery::units::time::second;
+use battery::units::{Energy, Time};
+use battery::Battery;
+use serde_json::{json, Value};
+
+/// Human-readable battery representation.
+pub struct Human<'a>(pub &'a Battery);
+
+impl<'a> fmt::Display for Human<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let battery = self.0;
+        let readings = battery.readings();
+
+        f.write_str(battery.name().unwrap_or("battery"))?;
+        let model = [battery.vendor(), battery.model()]
+            .iter()
+            .filter_map(|value| *value)
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !model.is_empty() {
+            write!(f, " ({})", model)?;
+        }
+        writeln!(f)?;
+
+        let mut row = |name: &str, value: Option<String>| match value {
+            Some(value) => writeln!(f, "  {:<17} {}", format!("{}:", name), value),
+            None => Ok(()),
+        };
+        row("state", Some(battery.state().to_string()))?;
+        row(
+            "state of charge",
+            Some(format!("{:.1} %", battery.state_of_charge().get::<percent>())),
+        )?;
+        let energy = |value: Option<Energy>| value.map(|value| format!("{:.2} Wh", value.get::<watt_hour>()));
+        row("energy", energy(readings.energy().value()))?;
+        row("energy full", energy(readings.energy_full().value()))?;
+        row("energy design", energy(readings.energy_full_design().value()))?;
+        row(
+            "energy rate",
+            readings.energy_rate().value().map(|value| format!("{:.2} W", value.get::<watt>())),
+        )?;
+        row(
+            "voltage",
+            readings.voltage().value().map(|value| format!("{:.2} V", value.get::<volt>())),
+        )?;
+        row(
+            "state of health",
+            readings.state_of_health().value().map(|value| format!("{:.1} %", value.get::<percent>())),
+        )?;
+        row(
+            "temperature",
+            battery.temperature().map(|value| format!("{:.1} °C", value.get::<degree_celsius>())),
+        )?;
+        row("cycle count", battery.cycle_count().map(|value| value.to_string()))?;
+        row("technology", Some(battery.technology().to_string()))?;
+        row("time to full", battery.time_to_full().map(duration))?;
+        row("time to empty", battery.time_to_empty().map(duration))?;
+
+        Ok(())
+    }
+}
+
+/// JSON representation of the batteries, all values are in the SI units.
+///
+/// Values which are not reported by the device are `null`.
+pub fn json(batteries: &[Battery]) -> Value {
+    Value::Array(batteries.iter().map(battery).collect())
+}
+
+fn battery(battery: &Battery) -> Value {
+    let readings = battery.readings();
+    json!({
+        "name": battery.name(),
+        "vendor": battery.vendor(),
+        "model": battery.model(),
+        "serial_number": battery.serial_number(),
+        "technology": battery.technology().to_string(),
+        "state": battery.state().to_string(),
+        "state_of_charge": battery.state_of_charge().get::<ratio>(),
+        "energy": readings.energy().value().map(|value| value.get::<joule>()),
+        "energy_full": readings.energy_full().value().map(|value| value.get::<joule>()),
+        "energy_full_design": readings.energy_full_design().value().map(|value| value.get::<joule>()),
+        "energy_rate": readings.energy_rate().value().map(|value| value.get::<watt>()),
+        "voltage": readings.voltage().value().map(|value| value.get::<volt>()),
+        "state_of_health": readings.state_of_health().value().map(|value| value.get::<ratio>()),
+        "temperature": battery.temperature().map(|value| value.get::<kelvin>()),
+        "cycle_count": battery.cycle_count(),
+        "time_to_full": battery.time_to_full().map(|value| value.get::<second>()),
+        "time_to_empty": battery.time_to_empty().map(|value| value.get::<second>()),
+    })
+}
+
+/// Formats time as `5h 04m`.
+pub fn duration(time: Time) -> String {
+    let minutes = (time.get::<second>() / 60.0).round() as u64;
+    match minutes / 60 {
+        0 => format!("{}m", minutes),
+        hours => format!("{}h {:02}m", hours, minutes % 60),
+    }
+}
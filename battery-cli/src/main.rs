@@ -0,0 +1,349 @@
+//! Command-line tool showing the notebook batteries state.
+
+#![allow(clippy::legacy_numeric_constants)]  // MSRV is 1.36
+#![allow(clippy::option_as_ref_deref)]  // MSRV is 1.36
+#![allow(clippy::unnecessary_map_or)]  // MSRV is 1.36
+
+use std::io::{self, Write};
+use std::process;
+use std::thread;
+use std::time::Duration;
+use std::u64;
+
+use structopt::StructOpt;
+
+mod format;
+mod status;
+
+use status::{Bar, Template};
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "battery", about = "Shows the notebook batteries state")]
+struct Options {
+    /// Print batteries as JSON
+    #[structopt(short, long, conflicts_with = "bar")]
+    json: bool,
+
+    /// Print batteries for the status bar: i3bar, swaybar, waybar or polybar
+    #[structopt(short, long, value_name = "BAR")]
+    bar: Option<Bar>,
+
+    /// Format batteries with the template, for example, `{icon} {percent}% {time_left}`
+    #[structopt(short, long, value_name = "TEMPLATE", conflicts_with = "json")]
+    template: Option<Template>,
+
+    /// Refresh and print batteries every SECONDS until interrupted
+    #[structopt(short, long, value_name = "SECONDS")]
+    watch: Option<f64>,
+
+    /// Battery name (for example, `BAT0`), position in the list, serial number or model
+    #[structopt(name = "BATTERY")]
+    battery: Option<String>,
+}
+
+fn main() {
+    let options = Options::from_args();
+    if let Err(e) = run(&options) {
+        eprintln!("battery: {}", e);
+        process::exit(1);
+    }
+}
+
+fn run(options: &Options) -> battery::Result<()> {
+    let manager = battery::Manager::new()?;
+    let query = options.battery.as_ref().map(String::as_str);
+    let mut batteries = select(&manager, query)?;
+    let selection = Selection::new(query, &batteries);
+
+    let interval = match options.watch {
+        Some(seconds) if seconds.is_finite() && seconds > 0.0 && seconds < u64::MAX as f64 => {
+            Some(Duration::new(seconds.trunc() as u64, (seconds.fract() * 1e9) as u32))
+        }
+        Some(_) => {
+            let e = io::Error::new(io::ErrorKind::InvalidInput, "watch interval should be a positive number");
+            return Err(e.into());
+        }
+        None => None,
+    };
+
+    let stdout = io::stdout();
+    if options.bar == Some(Bar::I3bar) && interval.is_some() {
+        // Infinite array of the status lines, see the i3bar protocol
+        let mut output = stdout.lock();
+        writeln!(output, "{{\"version\":1}}\n[")?;
+    }
+    loop {
+        let mut output = stdout.lock();
+        print(&mut output, options, &batteries, interval.is_some())?;
+        output.flush()?;
+        drop(output);
+
+        match interval {
+            Some(interval) => {
+                thread::sleep(interval);
+                update(&manager, &mut batteries, &selection)?;
+                if !options.json && options.bar.is_none() && options.template.is_none() {
+                    println!();
+                }
+            }
+            None => return Ok(()),
+        }
+    }
+}
+
+/// Prints `batteries` in the format requested by `options`.
+fn print<W: Write>(output: &mut W, options: &Options, batteries: &[battery::Battery], watch: bool) -> io::Result<()> {
+    let default;
+    let template = match options.template {
+        Some(ref template) => template,
+        None => {
+            default = status::DEFAULT_TEMPLATE.parse::<Template>().expect("valid default template");
+            &default
+        }
+    };
+
+    match options.bar {
+        Some(Bar::I3bar) => {
+            let blocks = batteries.iter().map(|battery| status::i3bar(template, battery)).collect::<Vec<_>>();
+            let separator = if watch { "," } else { "" };
+            writeln!(output, "{}{}", serde_json::Value::Array(blocks), separator)
+        }
+        // Waybar custom module expects exactly one object per line
+        Some(Bar::Waybar) => match batteries.first() {
+            Some(battery) => writeln!(output, "{}", status::waybar(template, battery)),
+            None => writeln!(output, "{{\"text\":\"\"}}"),
+        },
+        Some(Bar::Polybar) => {
+            let texts = batteries.iter().map(|battery| status::polybar(template, battery)).collect::<Vec<_>>();
+            writeln!(output, "{}", texts.join(" "))
+        }
+        None if options.json => {
+            let json = format::json(batteries);
+            if watch {
+                writeln!(output, "{}", json)
+            } else {
+                writeln!(output, "{:#}", json)
+            }
+        }
+        None if options.template.is_some() => {
+            for battery in batteries {
+                writeln!(output, "{}", template.render(battery))?;
+            }
+            Ok(())
+        }
+        None => {
+            for (idx, battery) in batteries.iter().enumerate() {
+                if idx > 0 {
+                    writeln!(output)?;
+                }
+                write!(output, "{}", format::Human(battery))?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Refreshes `batteries`, dropping the removed ones and adding the new ones, if `add` is `true`.
+///
+/// Batteries which failed to refresh are keeping their previous values.
+fn refresh(manager: &battery::Manager, batteries: &mut Vec<battery::Battery>, add: bool) -> battery::Result<()> {
+    let report = manager.refresh_all(batteries)?;
+    for (idx, result) in report.results().iter().enumerate() {
+        if let Err(e) = result {
+            if !report.removed().contains(&idx) {
+                eprintln!("battery: unable to refresh battery: {}", e);
+            }
+        }
+    }
+    for idx in report.removed().iter().rev() {
+        batteries.remove(*idx);
+    }
+    if add {
+        batteries.extend(report.into_added());
+    }
+
+    Ok(())
+}
+
+/// Batteries watched with the `--watch` option.
+#[derive(Debug, Clone, PartialEq)]
+enum Selection {
+    /// All batteries, including the added ones.
+    All,
+    /// Battery selected by the query, found again by its serial number once removed.
+    Serial(String),
+    /// Battery selected by the query, found again by its name once removed, if it has no serial number.
+    Name(String),
+    /// Battery selected by the query, which can't be found again once removed.
+    Unknown,
+}
+
+impl Selection {
+    /// Remembers the battery selected by the `query` from the `batteries`,
+    /// so the query is not resolved into another battery after the removal.
+    fn new(query: Option<&str>, batteries: &[battery::Battery]) -> Selection {
+        let battery = match (query, batteries.first()) {
+            (None, _) => return Selection::All,
+            (Some(_), Some(battery)) => battery,
+            (Some(_), None) => return Selection::Unknown,
+        };
+        match (battery.serial_number(), battery.name()) {
+            (Some(serial_number), _) => Selection::Serial(serial_number.to_string()),
+            (None, Some(name)) => Selection::Name(name.to_string()),
+            (None, None) => Selection::Unknown,
+        }
+    }
+
+    fn matches(&self, battery: &battery::Battery) -> bool {
+        match self {
+            Selection::All => true,
+            Selection::Serial(serial_number) => battery.serial_number() == Some(serial_number.as_str()),
+            Selection::Name(name) => battery.name() == Some(name.as_str()),
+            Selection::Unknown => false,
+        }
+    }
+}
+
+/// Refreshes the watched `batteries`, finding the selected one again once it was removed.
+///
+/// Missing battery is not an error here, it is printed as an empty status until it is plugged back.
+fn update(manager: &battery::Manager, batteries: &mut Vec<battery::Battery>, selection: &Selection) -> battery::Result<()> {
+    refresh(manager, batteries, *selection == Selection::All)?;
+    // Selected battery might be added back under another name
+    if batteries.is_empty() && *selection != Selection::All {
+        *batteries = load(manager)?
+            .into_iter()
+            .map(|(_, battery)| battery)
+            .filter(|battery| selection.matches(battery))
+            .take(1)
+            .collect();
+    }
+
+    Ok(())
+}
+
+/// Loads all batteries or the one matching the `query`, failing if there are none.
+fn select(manager: &battery::Manager, query: Option<&str>) -> battery::Result<Vec<battery::Battery>> {
+    let batteries = find(manager, query)?;
+    if batteries.is_empty() {
+        let description = match query {
+            Some(query) => format!("no battery matching `{}` found", query),
+            None => "no batteries found".to_string(),
+        };
+        return Err(io::Error::new(io::ErrorKind::NotFound, description).into());
+    }
+
+    Ok(batteries)
+}
+
+/// Loads batteries along with their positions in the list, skipping the ones which failed to load.
+fn load(manager: &battery::Manager) -> battery::Result<Vec<(usize, battery::Battery)>> {
+    let mut batteries = Vec::new();
+    for (idx, battery) in manager.batteries()?.enumerate() {
+        match battery {
+            Ok(battery) => batteries.push((idx, battery)),
+            Err(e) => eprintln!("battery: unable to load battery: {}", e),
+        }
+    }
+
+    Ok(batteries)
+}
+
+/// Loads all batteries or the one matching the `query`.
+fn find(manager: &battery::Manager, query: Option<&str>) -> battery::Result<Vec<battery::Battery>> {
+    let batteries = load(manager)?;
+    let batteries = match query {
+        None => batteries.into_iter().map(|(_, battery)| battery).collect(),
+        Some(query) => {
+            let position = query.parse::<usize>().ok();
+            batteries
+                .into_iter()
+                .filter(|(idx, battery)| {
+                    let matches = |value: Option<&str>| value.map_or(false, |value| value.eq_ignore_ascii_case(query));
+                    Some(*idx) == position
+                        || matches(battery.name())
+                        || matches(battery.serial_number())
+                        || matches(battery.model())
+                })
+                .map(|(_, battery)| battery)
+                .take(1)
+                .collect()
+        }
+    };
+
+    Ok(batteries)
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use battery::test_util::{FakePowerSupply, FakeSysfs};
+    use battery::units::electric_potential::volt;
+    use battery::units::energy::watt_hour;
+    use battery::units::{ElectricPotential, Energy};
+    use battery::State;
+    use structopt::StructOpt;
+
+    use super::{Options, Selection};
+
+    fn battery(name: &str) -> FakePowerSupply {
+        FakePowerSupply::battery(name)
+            .status(State::Discharging)
+            .serial_number("1234")
+            .energy_now(Energy::new::<watt_hour>(25.0))
+            .energy_full(Energy::new::<watt_hour>(50.0))
+            .voltage_now(ElectricPotential::new::<volt>(12.0))
+    }
+
+    fn output(options: &Options, batteries: &[battery::Battery]) -> String {
+        let mut output = Vec::new();
+        super::print(&mut output, options, batteries, true).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_watch_removed() {
+        let options = Options::from_iter(&["battery", "--bar", "waybar", "--watch", "1", "1234"]);
+        let query = options.battery.as_ref().map(String::as_str);
+        let mut sysfs = FakeSysfs::new().unwrap();
+        sysfs.add(battery("BAT0")).unwrap();
+        let manager = sysfs.manager().unwrap();
+        let mut batteries = super::select(&manager, query).unwrap();
+        assert_eq!(batteries.len(), 1);
+        let selection = Selection::new(query, &batteries);
+        assert_eq!(selection, Selection::Serial("1234".to_string()));
+
+        sysfs.remove("BAT0").unwrap();
+        super::update(&manager, &mut batteries, &selection).unwrap();
+        assert!(batteries.is_empty());
+        assert_eq!(output(&options, &batteries), "{\"text\":\"\"}\n");
+        super::update(&manager, &mut batteries, &selection).unwrap();
+        assert!(batteries.is_empty());
+        assert!(super::select(&manager, query).is_err());
+
+        // Battery is found by its serial number after being added back under another name
+        sysfs.add(battery("BAT1")).unwrap();
+        super::update(&manager, &mut batteries, &selection).unwrap();
+        assert_eq!(batteries.len(), 1);
+        assert_eq!(batteries[0].name(), Some("BAT1"));
+    }
+
+    #[test]
+    fn test_select() {
+        let mut sysfs = FakeSysfs::new().unwrap();
+        // Broken device does not prevent the working ones from being selected
+        sysfs.add(FakePowerSupply::battery("BAT0").status(State::Discharging)).unwrap();
+        sysfs.add(battery("BAT1").without("serial_number")).unwrap();
+        let manager = sysfs.manager().unwrap();
+        assert_eq!(super::select(&manager, None).unwrap().len(), 1);
+        let mut batteries = super::select(&manager, Some("bat1")).unwrap();
+        assert_eq!(batteries[0].name(), Some("BAT1"));
+
+        // Positional query is not resolved into another battery after the removal
+        let selection = Selection::new(Some("1"), &batteries);
+        assert_eq!(selection, Selection::Name("BAT1".to_string()));
+        sysfs.remove("BAT1").unwrap();
+        sysfs.add(battery("BAT2")).unwrap();
+        super::update(&manager, &mut batteries, &selection).unwrap();
+        assert!(batteries.is_empty());
+    }
+}
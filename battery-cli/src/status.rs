@@ -0,0 +1,349 @@
+//! Status-bar output.
+//!
+//! Batteries are rendered with the [Template] and are wrapped into the i3bar (swaybar) blocks,
+//! waybar custom module JSON or polybar formatting tags.
+
+use std::str::FromStr;
+
+use battery::units::energy::watt_hour;
+use battery::units::power::watt;
+use battery::units::ratio::percent;
+use battery::units::thermodynamic_temperature::degree_celsius;
+use battery::{Battery, State};
+use serde_json::{json, Value};
+
+use crate::format;
+
+/// Template used when none is provided.
+pub const DEFAULT_TEMPLATE: &str = "{percent}% {time_left}";
+
+const COLOR_CHARGING: &str = "#A3BE8C";
+const COLOR_LOW: &str = "#EBCB8B";
+const COLOR_CRITICAL: &str = "#BF616A";
+
+/// Status bar output format.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Bar {
+    I3bar,
+    Waybar,
+    Polybar,
+}
+
+impl FromStr for Bar {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "i3bar" | "swaybar" => Ok(Bar::I3bar),
+            "waybar" => Ok(Bar::Waybar),
+            "polybar" => Ok(Bar::Polybar),
+            _ => Err(format!("unknown status bar `{}`, expected i3bar, swaybar, waybar or polybar", s)),
+        }
+    }
+}
+
+/// Charge level, used for colours and classes.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Level {
+    Critical,
+    Low,
+    Normal,
+}
+
+impl Level {
+    pub fn of(battery: &Battery) -> Level {
+        match battery.state_of_charge().get::<percent>() {
+            value if value <= 10.0 => Level::Critical,
+            value if value <= 20.0 => Level::Low,
+            _ => Level::Normal,
+        }
+    }
+
+    fn class(self) -> &'static str {
+        match self {
+            Level::Critical => "critical",
+            Level::Low => "warning",
+            Level::Normal => "good",
+        }
+    }
+}
+
+/// Colour for the battery state, `None` if the default bar colour should be used.
+pub fn color(battery: &Battery) -> Option<&'static str> {
+    match (battery.state(), Level::of(battery)) {
+        (State::Charging, _) | (State::Full, _) => Some(COLOR_CHARGING),
+        (_, Level::Critical) => Some(COLOR_CRITICAL),
+        (_, Level::Low) => Some(COLOR_LOW),
+        (_, Level::Normal) => None,
+    }
+}
+
+/// Icon name from the [freedesktop icon naming specification](https://specifications.freedesktop.org/icon-naming-spec/latest/).
+pub fn icon_name(battery: &Battery) -> &'static str {
+    let level = battery.state_of_charge().get::<percent>();
+    match battery.state() {
+        State::Full => "battery-full-charged",
+        State::Charging if level < 10.0 => "battery-caution-charging",
+        State::Charging if level < 30.0 => "battery-low-charging",
+        State::Charging if level < 60.0 => "battery-good-charging",
+        State::Charging => "battery-full-charging",
+        State::Empty => "battery-empty",
+        _ if level < 10.0 => "battery-caution",
+        _ if level < 30.0 => "battery-low",
+        _ if level < 60.0 => "battery-good",
+        _ => "battery-full",
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Placeholder(Placeholder),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Placeholder {
+    Name,
+    Vendor,
+    Model,
+    State,
+    Icon,
+    Percent,
+    TimeLeft,
+    Power,
+    Energy,
+    Health,
+    Temperature,
+}
+
+impl FromStr for Placeholder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let placeholder = match s {
+            "name" => Placeholder::Name,
+            "vendor" => Placeholder::Vendor,
+            "model" => Placeholder::Model,
+            "state" => Placeholder::State,
+            "icon" => Placeholder::Icon,
+            "percent" => Placeholder::Percent,
+            "time_left" => Placeholder::TimeLeft,
+            "power" => Placeholder::Power,
+            "energy" => Placeholder::Energy,
+            "health" => Placeholder::Health,
+            "temperature" => Placeholder::Temperature,
+            _ => return Err(format!("unknown template placeholder `{{{}}}`", s)),
+        };
+
+        Ok(placeholder)
+    }
+}
+
+/// Status text template.
+///
+/// Placeholders are enclosed into braces, `{{` and `}}` are producing the literal braces:
+///
+///  * `{name}`, `{vendor}`, `{model}` and `{state}`
+///  * `{icon}`: freedesktop icon name, for example, `battery-low-charging`
+///  * `{percent}`: state of charge, rounded to the whole percent
+///  * `{time_left}`: time to full while charging or time to empty while discharging, `5h 04m`
+///  * `{power}`, `{energy}` and `{temperature}`: values in `W`, `Wh` and `°C` with units
+///  * `{health}`: state of health, rounded to the whole percent
+///
+/// Placeholders for the values which are not available are replaced with an empty string.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = s.chars().peekable();
+        while let Some(chr) = chars.next() {
+            match chr {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(chr) => name.push(chr),
+                            None => return Err("unclosed `{` in template".to_string()),
+                        }
+                    }
+                    if !text.is_empty() {
+                        parts.push(Part::Text(text.split_off(0)));
+                    }
+                    parts.push(Part::Placeholder(name.parse()?));
+                }
+                '}' => return Err("unmatched `}` in template".to_string()),
+                chr => text.push(chr),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+
+        Ok(Template {
+            parts,
+        })
+    }
+}
+
+impl Template {
+    /// Renders template for the `battery`, trimming the whitespaces left by the empty placeholders.
+    pub fn render(&self, battery: &Battery) -> String {
+        let mut output = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => output.push_str(text),
+                Part::Placeholder(placeholder) => output.push_str(&Self::value(*placeholder, battery)),
+            }
+        }
+
+        output.trim().to_string()
+    }
+
+    fn value(placeholder: Placeholder, battery: &Battery) -> String {
+        let readings = battery.readings();
+        let value = match placeholder {
+            Placeholder::Name => battery.name().map(ToString::to_string),
+            Placeholder::Vendor => battery.vendor().map(ToString::to_string),
+            Placeholder::Model => battery.model().map(ToString::to_string),
+            Placeholder::State => Some(battery.state().to_string()),
+            Placeholder::Icon => Some(icon_name(battery).to_string()),
+            Placeholder::Percent => Some(format!("{:.0}", battery.state_of_charge().get::<percent>())),
+            Placeholder::TimeLeft => match battery.state() {
+                State::Charging => battery.time_to_full().map(format::duration),
+                State::Discharging => battery.time_to_empty().map(format::duration),
+                _ => None,
+            },
+            Placeholder::Power => {
+                let power = readings.energy_rate().value();
+                power.map(|value| format!("{:.1} W", value.get::<watt>()))
+            }
+            Placeholder::Energy => {
+                let energy = readings.energy().value();
+                energy.map(|value| format!("{:.1} Wh", value.get::<watt_hour>()))
+            }
+            Placeholder::Health => {
+                let health = readings.state_of_health().value();
+                health.map(|value| format!("{:.0}%", value.get::<percent>()))
+            }
+            Placeholder::Temperature => {
+                let temperature = battery.temperature();
+                temperature.map(|value| format!("{:.0} °C", value.get::<degree_celsius>()))
+            }
+        };
+
+        value.unwrap_or_default()
+    }
+}
+
+/// i3bar (and swaybar) protocol block.
+pub fn i3bar(template: &Template, battery: &Battery) -> Value {
+    let mut block = json!({
+        "name": "battery",
+        "instance": battery.name(),
+        "full_text": template.render(battery),
+        "short_text": format!("{:.0}%", battery.state_of_charge().get::<percent>()),
+        "urgent": battery.state() == State::Discharging && Level::of(battery) == Level::Critical,
+    });
+    if let Some(color) = color(battery) {
+        block["color"] = Value::from(color);
+    }
+
+    block
+}
+
+/// Waybar custom module output.
+///
+/// `alt` holds the icon name, so it can be mapped into glyphs with the waybar `format-icons` option.
+pub fn waybar(template: &Template, battery: &Battery) -> Value {
+    let tooltip = format::Human(battery).to_string();
+    json!({
+        "text": template.render(battery),
+        "alt": icon_name(battery),
+        "tooltip": tooltip.trim_end(),
+        "class": [Level::of(battery).class(), battery.state().to_string()],
+        "percentage": battery.state_of_charge().get::<percent>().round() as u32,
+    })
+}
+
+/// Polybar script module output with the colour formatting tags.
+pub fn polybar(template: &Template, battery: &Battery) -> String {
+    let text = template.render(battery).replace('%', "%%");
+    match color(battery) {
+        Some(color) => format!("%{{F{}}}{}%{{F-}}", color, text),
+        None => text,
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use battery::test_util::{FakePowerSupply, FakeSysfs};
+    use battery::units::electric_potential::volt;
+    use battery::units::energy::watt_hour;
+    use battery::units::power::watt;
+    use battery::units::{ElectricPotential, Energy, Power};
+    use battery::State;
+
+    use super::Template;
+
+    fn load(state: State, energy: f32) -> battery::Battery {
+        let mut sysfs = FakeSysfs::new().unwrap();
+        let battery = FakePowerSupply::battery("BAT0")
+            .status(state)
+            .energy_now(Energy::new::<watt_hour>(energy))
+            .energy_full(Energy::new::<watt_hour>(50.0))
+            .power_now(Power::new::<watt>(10.0))
+            .voltage_now(ElectricPotential::new::<volt>(12.0));
+        sysfs.add(battery).unwrap();
+
+        let manager = sysfs.manager().unwrap();
+        manager.batteries().unwrap().next().unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_template() {
+        let battery = load(State::Discharging, 25.0);
+        let template = "{icon} {percent}% {time_left} {{{temperature}}}".parse::<Template>().unwrap();
+        assert_eq!(template.render(&battery), "battery-good 50% 2h 30m {}");
+
+        assert!("{unknown}".parse::<Template>().is_err());
+        assert!("50 }".parse::<Template>().is_err());
+        assert_eq!("{percent".parse::<Template>().unwrap_err(), "unclosed `{` in template");
+        assert_eq!("{".parse::<Template>().unwrap_err(), "unclosed `{` in template");
+    }
+
+    #[test]
+    fn test_bars() {
+        let template = super::DEFAULT_TEMPLATE.parse::<Template>().unwrap();
+
+        let battery = load(State::Discharging, 4.0);
+        let block = super::i3bar(&template, &battery);
+        assert_eq!(block["full_text"], "8% 24m");
+        assert_eq!(block["color"], super::COLOR_CRITICAL);
+        assert_eq!(block["urgent"], true);
+        assert_eq!(super::polybar(&template, &battery), "%{F#BF616A}8%% 24m%{F-}");
+
+        let battery = load(State::Charging, 40.0);
+        let waybar = super::waybar(&template, &battery);
+        assert_eq!(waybar["text"], "80% 1h 00m");
+        assert_eq!(waybar["alt"], "battery-full-charging");
+        assert_eq!(waybar["class"], serde_json::json!(["good", "charging"]));
+        assert_eq!(waybar["percentage"], 80);
+        assert!(super::i3bar(&template, &battery).get("urgent") == Some(&serde_json::Value::from(false)));
+    }
+}
@@ -0,0 +1,186 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+use std::u64;
+
+use serde::Deserialize;
+
+use crate::Action;
+
+/// Action fired when the state of charge drops to the level.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Level {
+    /// State of charge, in percents.
+    pub percent: f32,
+    pub action: Action,
+}
+
+/// Commands executed for the built-in actions.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct Commands {
+    pub notify: Vec<String>,
+    pub warn: Vec<String>,
+    pub suspend: Vec<String>,
+    pub hibernate: Vec<String>,
+}
+
+impl Default for Commands {
+    fn default() -> Commands {
+        let command = |args: &[&str]| args.iter().map(ToString::to_string).collect();
+        Commands {
+            notify: command(&["notify-send", "--urgency=normal", "--icon=battery-low", "Battery low"]),
+            warn: command(&["notify-send", "--urgency=critical", "--icon=battery-caution", "Battery critically low"]),
+            suspend: command(&["systemctl", "suspend"]),
+            hibernate: command(&["systemctl", "hibernate"]),
+        }
+    }
+}
+
+/// Daemon configuration.
+///
+/// See the [crate documentation](index.html) for the file format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// Batteries refresh interval.
+    pub interval: Duration,
+    /// Percents the state of charge should rise above the level before it can fire again.
+    pub hysteresis: f32,
+    /// Levels, sorted from the highest to the lowest one.
+    pub levels: Vec<Level>,
+    pub on_plug: Option<Vec<String>>,
+    pub on_unplug: Option<Vec<String>>,
+    pub commands: Commands,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            interval: Duration::from_secs(30),
+            hysteresis: 2.0,
+            levels: vec![
+                Level {
+                    percent: 20.0,
+                    action: Action::Notify,
+                },
+                Level {
+                    percent: 10.0,
+                    action: Action::Warn,
+                },
+                Level {
+                    percent: 5.0,
+                    action: Action::Suspend,
+                },
+            ],
+            on_plug: None,
+            on_unplug: None,
+            commands: Commands::default(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct File {
+    interval: Option<f64>,
+    hysteresis: Option<f32>,
+    level: Option<Vec<LevelEntry>>,
+    on_plug: Option<Vec<String>>,
+    on_unplug: Option<Vec<String>>,
+    #[serde(default)]
+    commands: Commands,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LevelEntry {
+    percent: f32,
+    action: ActionKind,
+    command: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ActionKind {
+    Notify,
+    Warn,
+    Suspend,
+    Hibernate,
+    Command,
+}
+
+impl Config {
+    /// Parses configuration from the TOML document, missing values are taken from the default configuration.
+    pub fn from_toml(document: &str) -> io::Result<Config> {
+        let file: File = toml::from_str(document).map_err(|e| invalid(format!("invalid configuration: {}", e)))?;
+        let default = Config::default();
+
+        let interval = match file.interval {
+            Some(seconds) if seconds.is_finite() && seconds > 0.0 && seconds < u64::MAX as f64 => {
+                Duration::new(seconds.trunc() as u64, (seconds.fract() * 1e9) as u32)
+            }
+            Some(_) => return Err(invalid("interval should be a positive number")),
+            None => default.interval,
+        };
+        let hysteresis = match file.hysteresis {
+            Some(value) if value.is_finite() && value >= 0.0 => value,
+            Some(_) => return Err(invalid("hysteresis should be a non-negative number")),
+            None => default.hysteresis,
+        };
+        let mut levels = match file.level {
+            Some(entries) => entries.into_iter().map(Level::from_entry).collect::<io::Result<Vec<_>>>()?,
+            None => default.levels,
+        };
+        levels.sort_by(|a, b| b.percent.partial_cmp(&a.percent).expect("levels are finite"));
+
+        for command in [&file.on_plug, &file.on_unplug].iter().filter_map(|command| command.as_ref()) {
+            if command.is_empty() {
+                return Err(invalid("on_plug and on_unplug commands should not be empty"));
+            }
+        }
+
+        Ok(Config {
+            interval,
+            hysteresis,
+            levels,
+            on_plug: file.on_plug,
+            on_unplug: file.on_unplug,
+            commands: file.commands,
+        })
+    }
+
+    /// Reads configuration from the TOML file.
+    pub fn from_toml_file<T: AsRef<Path>>(path: T) -> io::Result<Config> {
+        let path = path.as_ref();
+        let document = fs::read_to_string(path)?;
+
+        Self::from_toml(&document).map_err(|e| invalid(format!("{}: {}", path.display(), e)))
+    }
+}
+
+impl Level {
+    fn from_entry(entry: LevelEntry) -> io::Result<Level> {
+        if !(0.0..=100.0).contains(&entry.percent) {
+            return Err(invalid(format!("level {}% is out of the 0..100 range", entry.percent)));
+        }
+        let action = match (entry.action, entry.command) {
+            (ActionKind::Command, Some(command)) if !command.is_empty() => Action::Command(command),
+            (ActionKind::Command, _) => return Err(invalid("command action requires non-empty `command` list")),
+            (_, Some(_)) => return Err(invalid("`command` list is allowed for the command action only")),
+            (ActionKind::Notify, None) => Action::Notify,
+            (ActionKind::Warn, None) => Action::Warn,
+            (ActionKind::Suspend, None) => Action::Suspend,
+            (ActionKind::Hibernate, None) => Action::Hibernate,
+        };
+
+        Ok(Level {
+            percent: entry.percent,
+            action,
+        })
+    }
+}
+
+fn invalid<T: Into<String>>(description: T) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, description.into())
+}
@@ -0,0 +1,522 @@
+//! Daemon running actions when the notebook batteries are running low or the power source changes.
+//!
+//! [Daemon] refreshes batteries with [Manager::refresh](https://docs.rs/battery/*/battery/struct.Manager.html#method.refresh)
+//! and runs the level actions while discharging, once per level: level fires again only after the state of charge
+//! rises above it by the `hysteresis` percents. If a few levels are crossed at once, only the lowest one fires.
+//! Batteries are combined into the one [Status] with their total energy.
+//!
+//! Actions are executed by the [Runner]; [CommandRunner] starts the configured commands,
+//! while the tests and the embedding applications can substitute their own runner.
+//! Failed battery refreshes and actions do not stop the daemon, they are returned as [Failure] values instead.
+//!
+//! Configuration is read from the TOML file, all keys are optional:
+//!
+//! ```toml
+//! # Batteries refresh interval, in seconds
+//! interval = 30
+//! # Percents the state of charge should rise above the level before it can fire again
+//! hysteresis = 2
+//! # Commands executed when the power source is plugged or unplugged
+//! on_plug = ["brightnessctl", "set", "100%"]
+//! on_unplug = ["brightnessctl", "set", "50%"]
+//!
+//! # Levels are replacing the default ones: notify at 20%, warn at 10% and suspend at 5%
+//! [[level]]
+//! percent = 20
+//! action = "notify"
+//!
+//! [[level]]
+//! percent = 10
+//! action = "warn"
+//!
+//! [[level]]
+//! percent = 5
+//! action = "hibernate"
+//!
+//! [[level]]
+//! percent = 15
+//! action = "command"
+//! command = ["powerprofilesctl", "set", "power-saver"]
+//!
+//! # Commands for the built-in actions
+//! [commands]
+//! notify = ["notify-send", "--urgency=normal", "--icon=battery-low", "Battery low"]
+//! warn = ["notify-send", "--urgency=critical", "--icon=battery-caution", "Battery critically low"]
+//! suspend = ["systemctl", "suspend"]
+//! hibernate = ["systemctl", "hibernate"]
+//! ```
+//!
+//! Notification commands receive the `N% remaining` message as the last argument;
+//! all commands are started with the `BATTERY_PERCENT` and `BATTERY_PLUGGED` (`1` or `0`) environment variables.
+//!
+//! [Daemon]: struct.Daemon.html
+//! [Status]: struct.Status.html
+//! [Runner]: trait.Runner.html
+//! [CommandRunner]: struct.CommandRunner.html
+//! [Failure]: enum.Failure.html
+
+#![allow(clippy::legacy_numeric_constants)]  // MSRV is 1.36
+#![allow(clippy::io_other_error)]  // MSRV is 1.36
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+use std::process;
+use std::thread;
+
+use battery::units::energy::joule;
+use battery::units::ratio::percent;
+use battery::{Battery, Manager, State};
+
+mod config;
+
+pub use self::config::{Commands, Config, Level};
+
+/// Action executed by the [Runner](trait.Runner.html).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// Low battery notification.
+    Notify,
+    /// Critically low battery notification.
+    Warn,
+    Suspend,
+    Hibernate,
+    /// Arbitrary command with its arguments.
+    Command(Vec<String>),
+}
+
+/// Combined state of all batteries.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Status {
+    /// State of charge, in percents.
+    pub percent: f32,
+    /// `false` if any battery is discharging.
+    pub plugged: bool,
+}
+
+impl Status {
+    /// Combines `batteries` state, `None` if there are no batteries.
+    ///
+    /// State of charge is the total energy to the total full energy ratio,
+    /// or the average state of charge, if energy is not reported by some battery.
+    pub fn of(batteries: &[Battery]) -> Option<Status> {
+        if batteries.is_empty() {
+            return None;
+        }
+
+        let energy = batteries
+            .iter()
+            .map(|battery| {
+                let readings = battery.readings();
+                match (readings.energy().value(), readings.energy_full().value()) {
+                    (Some(energy), Some(full)) => Some((energy.get::<joule>(), full.get::<joule>())),
+                    _ => None,
+                }
+            })
+            .collect::<Option<Vec<_>>>();
+        let level = match energy {
+            Some(ref energy) if energy.iter().map(|(_, full)| full).sum::<f32>() > 0.0 => {
+                let (now, full) = energy.iter().fold((0.0, 0.0), |acc, (now, full)| (acc.0 + now, acc.1 + full));
+                (now / full * 100.0).min(100.0)
+            }
+            _ => {
+                let total = batteries.iter().map(|battery| battery.state_of_charge().get::<percent>()).sum::<f32>();
+                total / batteries.len() as f32
+            }
+        };
+
+        Some(Status {
+            percent: level,
+            plugged: !batteries.iter().any(|battery| battery.state() == State::Discharging),
+        })
+    }
+}
+
+/// Problem which happened during the [Daemon::poll](struct.Daemon.html#method.poll) call,
+/// but did not stop the daemon.
+#[derive(Debug)]
+pub enum Failure {
+    /// Batteries failed to enumerate, known ones are kept until the next poll.
+    Enumerate(battery::Error),
+    /// Known battery failed to refresh and keeps its previous values.
+    Refresh(battery::Error),
+    /// Battery failed to load and is skipped until the next enumeration.
+    Load(battery::Error),
+    /// Action failed to run.
+    Action(Action, io::Error),
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Failure::Enumerate(e) => write!(f, "unable to enumerate batteries: {}", e),
+            Failure::Refresh(e) => write!(f, "unable to refresh battery: {}", e),
+            Failure::Load(e) => write!(f, "unable to load battery: {}", e),
+            Failure::Action(action, e) => write!(f, "unable to run {:?} action: {}", action, e),
+        }
+    }
+}
+
+impl StdError for Failure {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Failure::Enumerate(e) | Failure::Refresh(e) | Failure::Load(e) => Some(e),
+            Failure::Action(_, e) => Some(e),
+        }
+    }
+}
+
+/// Loads all batteries, returning the ones which failed to load as failures.
+pub fn load(manager: &Manager) -> battery::Result<(Vec<Battery>, Vec<Failure>)> {
+    let mut batteries = Vec::new();
+    let mut failures = Vec::new();
+    for battery in manager.batteries()? {
+        match battery {
+            Ok(battery) => batteries.push(battery),
+            Err(e) => failures.push(Failure::Load(e)),
+        }
+    }
+
+    Ok((batteries, failures))
+}
+
+/// Executes actions.
+///
+/// Implemented for the `FnMut(&Action, &Status) -> io::Result<()>` closures,
+/// so the actions can be stubbed without starting any real commands.
+pub trait Runner {
+    fn run(&mut self, action: &Action, status: &Status) -> io::Result<()>;
+}
+
+impl<F> Runner for F
+where
+    F: FnMut(&Action, &Status) -> io::Result<()>,
+{
+    fn run(&mut self, action: &Action, status: &Status) -> io::Result<()> {
+        self(action, status)
+    }
+}
+
+/// Runner starting the configured commands and waiting for them to finish.
+#[derive(Debug, Clone, Default)]
+pub struct CommandRunner {
+    commands: Commands,
+}
+
+impl CommandRunner {
+    pub fn new(commands: Commands) -> CommandRunner {
+        CommandRunner {
+            commands,
+        }
+    }
+}
+
+impl Runner for CommandRunner {
+    fn run(&mut self, action: &Action, status: &Status) -> io::Result<()> {
+        let mut args = match action {
+            Action::Notify => self.commands.notify.clone(),
+            Action::Warn => self.commands.warn.clone(),
+            Action::Suspend => self.commands.suspend.clone(),
+            Action::Hibernate => self.commands.hibernate.clone(),
+            Action::Command(command) => command.clone(),
+        };
+        // Empty command disables the action
+        if args.is_empty() {
+            return Ok(());
+        }
+        if let Action::Notify | Action::Warn = action {
+            args.push(format!("{:.0}% remaining", status.percent));
+        }
+
+        let exit_status = process::Command::new(&args[0])
+            .args(&args[1..])
+            .env("BATTERY_PERCENT", format!("{:.0}", status.percent))
+            .env("BATTERY_PLUGGED", if status.plugged { "1" } else { "0" })
+            .status()?;
+        if exit_status.success() {
+            Ok(())
+        } else {
+            let description = format!("`{}` exited with {}", args.join(" "), exit_status);
+            Err(io::Error::new(io::ErrorKind::Other, description))
+        }
+    }
+}
+
+/// Batteries watching daemon.
+pub struct Daemon<R> {
+    manager: Manager,
+    config: Config,
+    runner: R,
+    batteries: Vec<Battery>,
+    fired: Vec<bool>,
+    plugged: Option<bool>,
+}
+
+impl<R: Runner> Daemon<R> {
+    pub fn new(manager: Manager, config: Config, runner: R) -> Daemon<R> {
+        let fired = vec![false; config.levels.len()];
+        Daemon {
+            manager,
+            config,
+            runner,
+            batteries: Vec::new(),
+            fired,
+            plugged: None,
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn runner(&self) -> &R {
+        &self.runner
+    }
+
+    /// Refreshes batteries and runs the actions for the new status.
+    ///
+    /// Batteries are enumerated again when there are none or some of them were removed.
+    /// Errors of the individual batteries and actions do not stop the daemon,
+    /// they are returned along with the status instead.
+    pub fn poll(&mut self) -> battery::Result<(Option<Status>, Vec<Failure>)> {
+        let mut failures = Vec::new();
+        let mut removed = false;
+        for battery in self.batteries.iter_mut() {
+            match self.manager.refresh(battery) {
+                Ok(()) => {}
+                Err(e) if e.is_removed() => removed = true,
+                Err(e) => failures.push(Failure::Refresh(e)),
+            }
+        }
+        if removed || self.batteries.is_empty() {
+            let (batteries, load_failures) = load(&self.manager)?;
+            self.batteries = batteries;
+            failures.extend(load_failures);
+        }
+
+        let status = Status::of(&self.batteries);
+        if let Some(ref status) = status {
+            failures.extend(self.update(status));
+        }
+
+        Ok((status, failures))
+    }
+
+    /// Polls batteries every configured interval, never returns.
+    ///
+    /// Failures of each poll, including the failed enumeration, are passed to the `on_failure` callback
+    /// and the daemon keeps polling, so the actions are not lost because of a transient error.
+    pub fn run<F>(&mut self, mut on_failure: F) -> !
+    where
+        F: FnMut(Failure),
+    {
+        loop {
+            match self.poll() {
+                Ok((_, failures)) => failures.into_iter().for_each(&mut on_failure),
+                Err(e) => on_failure(Failure::Enumerate(e)),
+            }
+            thread::sleep(self.config.interval);
+        }
+    }
+
+    /// Runs the actions for the `status` change, returning the failed ones.
+    pub fn update(&mut self, status: &Status) -> Vec<Failure> {
+        let mut failures = Vec::new();
+        if let Some(plugged) = self.plugged {
+            let command = match (plugged, status.plugged) {
+                (false, true) => self.config.on_plug.clone(),
+                (true, false) => self.config.on_unplug.clone(),
+                _ => None,
+            };
+            if let Some(command) = command {
+                failures.extend(self.execute(Action::Command(command), status));
+            }
+        }
+        self.plugged = Some(status.plugged);
+
+        let hysteresis = self.config.hysteresis;
+        for (level, fired) in self.config.levels.iter().zip(self.fired.iter_mut()) {
+            if status.percent >= level.percent + hysteresis {
+                *fired = false;
+            }
+        }
+        if status.plugged {
+            return failures;
+        }
+
+        // Levels are sorted from the highest one, so the last crossed level is the lowest
+        let crossed = self.config.levels.iter().take_while(|level| status.percent <= level.percent).count();
+        if crossed > 0 && !self.fired[crossed - 1] {
+            let action = self.config.levels[crossed - 1].action.clone();
+            failures.extend(self.execute(action, status));
+        }
+        for fired in self.fired.iter_mut().take(crossed) {
+            *fired = true;
+        }
+
+        failures
+    }
+
+    fn execute(&mut self, action: Action, status: &Status) -> Option<Failure> {
+        self.runner.run(&action, status).err().map(|e| Failure::Action(action, e))
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use std::cell::RefCell;
+    use std::io;
+    use std::rc::Rc;
+
+    use battery::test_util::{FakePowerSupply, FakeSysfs};
+    use battery::units::electric_potential::volt;
+    use battery::units::energy::watt_hour;
+    use battery::units::{ElectricPotential, Energy};
+    use battery::State;
+
+    use super::{Action, CommandRunner, Commands, Config, Daemon, Failure, Runner, Status};
+
+    fn battery(state: State, energy: f32) -> FakePowerSupply {
+        FakePowerSupply::battery("BAT0")
+            .status(state)
+            .energy_now(Energy::new::<watt_hour>(energy))
+            .energy_full(Energy::new::<watt_hour>(50.0))
+            .voltage_now(ElectricPotential::new::<volt>(12.0))
+    }
+
+    #[test]
+    fn test_levels() {
+        let mut sysfs = FakeSysfs::new().unwrap();
+        sysfs.add(battery(State::Discharging, 25.0)).unwrap();
+
+        let config = Config::from_toml("on_plug = [\"plugged\"]\non_unplug = [\"unplugged\"]").unwrap();
+        let fired = Rc::new(RefCell::new(Vec::new()));
+        let runner = {
+            let fired = fired.clone();
+            move |action: &Action, status: &Status| -> io::Result<()> {
+                let action = match action {
+                    Action::Command(command) => command[0].clone(),
+                    action => format!("{:?}", action),
+                };
+                fired.borrow_mut().push(format!("{} {:.0}", action, status.percent));
+                Ok(())
+            }
+        };
+        let mut daemon = Daemon::new(sysfs.manager().unwrap(), config, runner);
+
+        let mut step = |state: State, energy: f32| {
+            sysfs.add(battery(state, energy)).unwrap();
+            let (status, failures) = daemon.poll().unwrap();
+            assert!(failures.is_empty());
+            status.unwrap()
+        };
+        assert_eq!(step(State::Discharging, 25.0), Status {
+            percent: 50.0,
+            plugged: false,
+        });
+        step(State::Discharging, 9.5);
+        step(State::Discharging, 9.0);
+        // 10% and 5% levels are crossed at once, only the lowest one fires
+        step(State::Discharging, 2.0);
+        step(State::Charging, 2.2);
+        step(State::Discharging, 2.1);
+        // Not high enough to re-arm the 10% level
+        step(State::Charging, 5.5);
+        step(State::Discharging, 4.9);
+        step(State::Charging, 6.5);
+        step(State::Discharging, 4.9);
+
+        assert_eq!(*fired.borrow(), vec![
+            "Notify 19",
+            "Suspend 4",
+            "plugged 4",
+            "unplugged 4",
+            "plugged 11",
+            "unplugged 10",
+            "plugged 13",
+            "unplugged 10",
+            "Warn 10",
+        ]);
+    }
+
+    #[test]
+    fn test_failures() {
+        let mut sysfs = FakeSysfs::new().unwrap();
+        sysfs.add(battery(State::Discharging, 5.0)).unwrap();
+        let runner = |_: &Action, _: &Status| -> io::Result<()> { Err(io::Error::new(io::ErrorKind::Other, "failed")) };
+        let mut daemon = Daemon::new(sysfs.manager().unwrap(), Config::default(), runner);
+
+        let (status, failures) = daemon.poll().unwrap();
+        assert!(status.is_some());
+        assert_eq!(failures.len(), 1);
+        match failures[0] {
+            Failure::Action(Action::Warn, ref e) => assert_eq!(e.to_string(), "failed"),
+            ref failure => panic!("unexpected failure {:?}", failure),
+        }
+        assert_eq!(failures[0].to_string(), "unable to run Warn action: failed");
+
+        sysfs.update("BAT0", |battery| battery.attribute("energy_now", "garbage")).unwrap();
+        let (_, failures) = daemon.poll().unwrap();
+        match failures.as_slice() {
+            [Failure::Refresh(_)] => {}
+            failures => panic!("unexpected failures {:?}", failures),
+        }
+
+        sysfs.add(battery(State::Discharging, 5.0)).unwrap();
+        sysfs.add(FakePowerSupply::battery("BAT1")).unwrap();
+        let (batteries, failures) = super::load(&daemon.manager).unwrap();
+        assert_eq!(batteries.len(), 1);
+        match failures.as_slice() {
+            [Failure::Load(e)] => assert_eq!(e.device(), Some("BAT1")),
+            failures => panic!("unexpected failures {:?}", failures),
+        }
+    }
+
+    #[test]
+    fn test_command_runner() {
+        let commands = Commands {
+            suspend: vec!["sh".into(), "-c".into(), "test \"$BATTERY_PERCENT$BATTERY_PLUGGED\" = 40".into()],
+            notify: vec!["sh".into(), "-c".into(), "test \"$1\" = \"4% remaining\"".into(), "notify".into()],
+            hibernate: Vec::new(),
+            ..Commands::default()
+        };
+        let mut runner = CommandRunner::new(commands);
+        let status = Status {
+            percent: 4.2,
+            plugged: false,
+        };
+
+        runner.run(&Action::Suspend, &status).unwrap();
+        runner.run(&Action::Notify, &status).unwrap();
+        runner.run(&Action::Hibernate, &status).unwrap();
+        assert!(runner.run(&Action::Command(vec!["false".into()]), &status).is_err());
+    }
+
+    #[test]
+    fn test_config() {
+        let config = Config::from_toml(
+            r#"
+            interval = 5
+            [[level]]
+            percent = 3
+            action = "hibernate"
+            [[level]]
+            percent = 15
+            action = "command"
+            command = ["true"]
+            [commands]
+            hibernate = ["stub"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.interval.as_secs(), 5);
+        assert_eq!(config.levels[0].action, Action::Command(vec!["true".to_string()]));
+        assert_eq!(config.levels[1].action, Action::Hibernate);
+        assert_eq!(config.commands.hibernate, vec!["stub".to_string()]);
+        assert_eq!(config.commands.suspend, vec!["systemctl".to_string(), "suspend".to_string()]);
+
+        assert!(Config::from_toml("[[level]]\npercent = 5\naction = \"command\"").is_err());
+        assert!(Config::from_toml("[[level]]\npercent = 120\naction = \"notify\"").is_err());
+        assert!(Config::from_toml("unknown = 1").is_err());
+    }
+}
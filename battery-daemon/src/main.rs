@@ -0,0 +1,58 @@
+//! Daemon running actions when the notebook batteries are running low or the power source changes.
+
+use std::path::PathBuf;
+use std::process;
+
+use battery_daemon::{CommandRunner, Config, Daemon};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "battery-daemon", about = "Runs actions on the battery level and power source changes")]
+struct Options {
+    /// Configuration file, default levels are used if omitted
+    #[structopt(short, long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Check configuration and batteries once, without running any actions
+    #[structopt(long)]
+    check: bool,
+}
+
+fn main() {
+    let options = Options::from_args();
+    if let Err(e) = run(&options) {
+        eprintln!("battery-daemon: {}", e);
+        process::exit(1);
+    }
+}
+
+fn run(options: &Options) -> battery::Result<()> {
+    let config = match options.config {
+        Some(ref path) => Config::from_toml_file(path)?,
+        None => Config::default(),
+    };
+    let manager = battery::Manager::new()?;
+
+    if options.check {
+        let (batteries, failures) = battery_daemon::load(&manager)?;
+        for failure in failures {
+            eprintln!("battery-daemon: {}", failure);
+        }
+        match battery_daemon::Status::of(&batteries) {
+            Some(status) => println!(
+                "{} batteries, {:.0}%, {}",
+                batteries.len(),
+                status.percent,
+                if status.plugged { "plugged" } else { "unplugged" }
+            ),
+            None => println!("no batteries found"),
+        }
+        for level in &config.levels {
+            println!("{:>5.1}%: {:?}", level.percent, level.action);
+        }
+        return Ok(());
+    }
+
+    let runner = CommandRunner::new(config.commands.clone());
+    Daemon::new(manager, config, runner).run(|failure| eprintln!("battery-daemon: {}", failure))
+}
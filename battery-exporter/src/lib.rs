@@ -0,0 +1,216 @@
+//! Prometheus metrics for the notebook batteries.
+//!
+//! [render] function formats batteries in the Prometheus
+//! [text exposition format](https://prometheus.io/docs/instrumenting/exposition_formats/),
+//! which is served by the `battery-exporter` binary and can be written into the
+//! node_exporter [textfile collector](https://github.com/prometheus/node_exporter#textfile-collector)
+//! directory with the [write_textfile] function.
+//!
+//! Each battery is labeled with the `battery` label, which holds the battery
+//! [name](https://docs.rs/battery/*/battery/struct.Battery.html#method.name) or its position in the list,
+//! if the platform does not provide names. Values which are not reported by the device are omitted.
+//!
+//! Exported metrics:
+//!
+//!  * `battery_info` with the `vendor`, `model`, `serial_number` and `technology` labels, always `1`
+//!  * `battery_state` with the `state` label, `1` for the current state and `0` for the others
+//!  * `battery_state_of_charge_ratio` and `battery_state_of_health_ratio`
+//!  * `battery_energy_joules`, `battery_energy_full_joules` and `battery_energy_full_design_joules`
+//!  * `battery_energy_rate_watts`
+//!  * `battery_voltage_volts`
+//!  * `battery_temperature_celsius`
+//!  * `battery_cycles`
+//!  * `battery_load_failures`, number of batteries which failed to load and are missing from the metrics,
+//!    without the `battery` label
+//!
+//! [render]: fn.render.html
+//! [write_textfile]: fn.write_textfile.html
+
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use battery::units::electric_potential::volt;
+use battery::units::energy::joule;
+use battery::units::power::watt;
+use battery::units::ratio::ratio;
+use battery::units::thermodynamic_temperature::degree_celsius;
+use battery::{Battery, State};
+
+static STATES: &[State] = &[State::Unknown, State::Charging, State::Discharging, State::Empty, State::Full];
+
+/// Metric description.
+struct Metric {
+    name: &'static str,
+    help: &'static str,
+    value: fn(&Battery) -> Option<f32>,
+}
+
+static METRICS: &[Metric] = &[
+    Metric {
+        name: "battery_state_of_charge_ratio",
+        help: "Battery state of charge",
+        value: |battery| battery.readings().state_of_charge().value().map(|value| value.get::<ratio>()),
+    },
+    Metric {
+        name: "battery_state_of_health_ratio",
+        help: "Battery state of health, full energy to the design energy ratio",
+        value: |battery| battery.readings().state_of_health().value().map(|value| value.get::<ratio>()),
+    },
+    Metric {
+        name: "battery_energy_joules",
+        help: "Energy stored in the battery",
+        value: |battery| battery.readings().energy().value().map(|value| value.get::<joule>()),
+    },
+    Metric {
+        name: "battery_energy_full_joules",
+        help: "Energy stored in the fully charged battery",
+        value: |battery| battery.readings().energy_full().value().map(|value| value.get::<joule>()),
+    },
+    Metric {
+        name: "battery_energy_full_design_joules",
+        help: "Energy stored in the fully charged battery by design",
+        value: |battery| battery.readings().energy_full_design().value().map(|value| value.get::<joule>()),
+    },
+    Metric {
+        name: "battery_energy_rate_watts",
+        help: "Power going in or out of the battery",
+        value: |battery| battery.readings().energy_rate().value().map(|value| value.get::<watt>()),
+    },
+    Metric {
+        name: "battery_voltage_volts",
+        help: "Battery voltage",
+        value: |battery| battery.readings().voltage().value().map(|value| value.get::<volt>()),
+    },
+    Metric {
+        name: "battery_temperature_celsius",
+        help: "Battery temperature",
+        value: |battery| battery.temperature().map(|value| value.get::<degree_celsius>()),
+    },
+    Metric {
+        name: "battery_cycles",
+        help: "Number of the battery charge cycles",
+        value: |battery| battery.readings().cycle_count().value().map(|value| value as f32),
+    },
+];
+
+/// Formats `batteries` in the Prometheus text exposition format.
+///
+/// `failed` is the number of batteries which failed to load and are not included into `batteries`.
+pub fn render(batteries: &[Battery], failed: usize) -> String {
+    let mut output = String::new();
+    // Writing into `String` can't fail
+    let _ = write_metrics(&mut output, batteries, failed);
+
+    output
+}
+
+/// Writes `batteries` metrics into the node_exporter textfile collector file.
+///
+/// File is written atomically: metrics are written into the temporary file
+/// in the same directory first, which is renamed into `path` then,
+/// so the collector never reads the partially written file.
+pub fn write_textfile<T: AsRef<Path>>(path: T, batteries: &[Battery], failed: usize) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+
+    fs::write(&temporary, render(batteries, failed))?;
+    fs::rename(&temporary, path)
+}
+
+fn write_metrics(output: &mut String, batteries: &[Battery], failed: usize) -> fmt::Result {
+    let labels = batteries
+        .iter()
+        .enumerate()
+        .map(|(idx, battery)| match battery.name() {
+            Some(name) => label_value(name),
+            None => idx.to_string(),
+        })
+        .collect::<Vec<_>>();
+
+    header(output, "battery_info", "Battery information")?;
+    for (battery, label) in batteries.iter().zip(&labels) {
+        writeln!(
+            output,
+            "battery_info{{battery=\"{}\",vendor=\"{}\",model=\"{}\",serial_number=\"{}\",technology=\"{}\"}} 1",
+            label,
+            label_value(battery.vendor().unwrap_or_default()),
+            label_value(battery.model().unwrap_or_default()),
+            label_value(battery.serial_number().unwrap_or_default()),
+            battery.technology(),
+        )?;
+    }
+
+    header(output, "battery_state", "Battery state, 1 for the current one")?;
+    for (battery, label) in batteries.iter().zip(&labels) {
+        for state in STATES {
+            let value = if battery.state() == *state { 1 } else { 0 };
+            writeln!(output, "battery_state{{battery=\"{}\",state=\"{}\"}} {}", label, state, value)?;
+        }
+    }
+
+    for metric in METRICS {
+        header(output, metric.name, metric.help)?;
+        for (battery, label) in batteries.iter().zip(&labels) {
+            if let Some(value) = (metric.value)(battery) {
+                writeln!(output, "{}{{battery=\"{}\"}} {}", metric.name, label, value)?;
+            }
+        }
+    }
+
+    header(output, "battery_load_failures", "Number of batteries which failed to load")?;
+    writeln!(output, "battery_load_failures {}", failed)
+}
+
+fn header(output: &mut String, name: &str, help: &str) -> fmt::Result {
+    writeln!(output, "# HELP {} {}", name, help)?;
+    writeln!(output, "# TYPE {} gauge", name)
+}
+
+/// Escapes label value according to the exposition format.
+fn label_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use battery::test_util::{FakePowerSupply, FakeSysfs};
+    use battery::units::electric_potential::volt;
+    use battery::units::energy::watt_hour;
+    use battery::units::{ElectricPotential, Energy};
+    use battery::State;
+
+    #[test]
+    fn test_render() {
+        let mut sysfs = FakeSysfs::new().unwrap();
+        let battery = FakePowerSupply::battery("BAT0")
+            .status(State::Charging)
+            .manufacturer("Quote \"Inc\"")
+            .energy_now(Energy::new::<watt_hour>(25.0))
+            .energy_full(Energy::new::<watt_hour>(50.0))
+            .voltage_now(ElectricPotential::new::<volt>(12.0));
+        sysfs.add(battery).unwrap();
+
+        let manager = sysfs.manager().unwrap();
+        let batteries = manager.batteries().unwrap().collect::<battery::Result<Vec<_>>>().unwrap();
+        let output = super::render(&batteries, 0);
+
+        assert!(output.contains("# TYPE battery_energy_joules gauge\nbattery_energy_joules{battery=\"BAT0\"} 90000\n"));
+        assert!(output.contains("battery_state{battery=\"BAT0\",state=\"charging\"} 1\n"));
+        assert!(output.contains("battery_state{battery=\"BAT0\",state=\"full\"} 0\n"));
+        assert!(output.contains("vendor=\"Quote \\\"Inc\\\"\",model=\"\""));
+        assert!(output.contains("battery_state_of_charge_ratio{battery=\"BAT0\"} 0.5\n"));
+        // Design energy and temperature are not reported
+        assert!(!output.contains("battery_energy_full_design_joules{"));
+        assert!(!output.contains("battery_temperature_celsius{"));
+        assert!(output.ends_with("# TYPE battery_load_failures gauge\nbattery_load_failures 0\n"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("battery.prom");
+        super::write_textfile(&path, &batteries, 0).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), output);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,181 @@
+//! Prometheus exporter for the notebook batteries state.
+//!
+//! Serves metrics over HTTP at the `/metrics` path or writes them
+//! into the node_exporter textfile collector file.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "battery-exporter", about = "Prometheus exporter for the notebook batteries state")]
+struct Options {
+    /// Address to serve metrics on
+    #[structopt(short, long, value_name = "ADDRESS", default_value = "127.0.0.1:9906")]
+    listen: String,
+
+    /// Write metrics into the textfile collector file instead of serving them
+    #[structopt(short, long, value_name = "PATH")]
+    textfile: Option<PathBuf>,
+
+    /// Re-write textfile every SECONDS until interrupted
+    #[structopt(short, long, value_name = "SECONDS", requires = "textfile")]
+    interval: Option<u64>,
+}
+
+fn main() {
+    let options = Options::from_args();
+    let result = match options.textfile {
+        Some(ref path) => textfile(path, options.interval.map(Duration::from_secs)),
+        None => serve(&options.listen),
+    };
+
+    if let Err(e) = result {
+        eprintln!("battery-exporter: {}", e);
+        process::exit(1);
+    }
+}
+
+/// Loads batteries, skipping the ones which failed to load; their number is returned too.
+fn batteries(manager: &battery::Manager) -> battery::Result<(Vec<battery::Battery>, usize)> {
+    let mut batteries = Vec::new();
+    let mut failed = 0;
+    for battery in manager.batteries()? {
+        match battery {
+            Ok(battery) => batteries.push(battery),
+            Err(_) => failed += 1,
+        }
+    }
+
+    Ok((batteries, failed))
+}
+
+fn textfile(path: &Path, interval: Option<Duration>) -> battery::Result<()> {
+    let manager = battery::Manager::new()?;
+    loop {
+        let (batteries, failed) = batteries(&manager)?;
+        battery_exporter::write_textfile(path, &batteries, failed)?;
+        match interval {
+            Some(interval) => thread::sleep(interval),
+            None => return Ok(()),
+        }
+    }
+}
+
+fn serve(address: &str) -> battery::Result<()> {
+    let manager = battery::Manager::new()?;
+    let listener = TcpListener::bind(address)?;
+    eprintln!("battery-exporter: serving metrics at http://{}/metrics", listener.local_addr()?);
+
+    for stream in listener.incoming() {
+        let result = stream.and_then(|stream| respond(&manager, stream));
+        if let Err(e) = result {
+            eprintln!("battery-exporter: unable to serve request: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+fn respond(manager: &battery::Manager, mut stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    stream.set_write_timeout(Some(Duration::from_secs(5)))?;
+
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // Headers are not needed, but they should be read before responding
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+
+    let mut parts = request.split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => match batteries(manager) {
+            Ok((batteries, failed)) => (
+                "200 OK",
+                "text/plain; version=0.0.4",
+                battery_exporter::render(&batteries, failed),
+            ),
+            Err(e) => ("500 Internal Server Error", "text/plain", format!("{}\n", e)),
+        },
+        (Some("GET"), Some("/")) => (
+            "200 OK",
+            "text/html",
+            "<html><body><a href=\"/metrics\">Metrics</a></body></html>\n".to_string(),
+        ),
+        (Some("GET"), _) => ("404 Not Found", "text/plain", "Not found\n".to_string()),
+        _ => ("405 Method Not Allowed", "text/plain", "Method not allowed\n".to_string()),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    use battery::test_util::{FakePowerSupply, FakeSysfs};
+    use battery::units::electric_potential::volt;
+    use battery::units::energy::watt_hour;
+    use battery::units::{ElectricPotential, Energy};
+    use battery::State;
+
+    fn request(manager: &battery::Manager, request: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(request.as_bytes()).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        });
+
+        let (stream, _) = listener.accept().unwrap();
+        super::respond(manager, stream).unwrap();
+        client.join().unwrap()
+    }
+
+    #[test]
+    fn test_respond() {
+        let mut sysfs = FakeSysfs::new().unwrap();
+        let battery = FakePowerSupply::battery("BAT0")
+            .status(State::Discharging)
+            .energy_now(Energy::new::<watt_hour>(25.0))
+            .energy_full(Energy::new::<watt_hour>(50.0))
+            .voltage_now(ElectricPotential::new::<volt>(12.0));
+        sysfs.add(battery).unwrap();
+        // Energy values can't be calculated without the voltage
+        sysfs.add(FakePowerSupply::battery("BAT1").status(State::Discharging)).unwrap();
+        let manager = sysfs.manager().unwrap();
+
+        let response = request(&manager, "GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: text/plain; version=0.0.4\r\n"));
+        assert!(response.contains("battery_energy_joules{battery=\"BAT0\"} 90000\n"));
+        assert!(!response.contains("battery=\"BAT1\""));
+        assert!(response.ends_with("battery_load_failures 1\n"));
+
+        let response = request(&manager, "GET /unknown HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        let response = request(&manager, "POST /metrics HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+    }
+}
@@ -165,10 +165,7 @@ pub unsafe extern "C" fn battery_get_cycle_count(ptr: *const Battery) -> u32 {
     assert!(!ptr.is_null());
     let battery = &*ptr;
 
-    match battery.cycle_count() {
-        None => u32::MAX,
-        Some(value) => value,
-    }
+    battery.cycle_count().unwrap_or(u32::MAX)
 }
 
 /// Returns battery vendor.
@@ -307,7 +304,7 @@ pub unsafe extern "C" fn battery_free(ptr: *mut Battery) {
         return;
     }
 
-    Box::from_raw(ptr);
+    let _ = Box::from_raw(ptr);
 }
 
 /// Frees battery information string value.
@@ -322,5 +319,5 @@ pub unsafe extern "C" fn battery_str_free(ptr: *mut libc::c_char) {
         return;
     }
 
-    CString::from_raw(ptr);
+    let _ = CString::from_raw(ptr);
 }
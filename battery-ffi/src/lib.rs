@@ -42,6 +42,7 @@
 
 #![doc(html_root_url = "https://docs.rs/battery-ffi/0.7.5")]
 #![allow(clippy::missing_safety_doc)]
+#![allow(clippy::legacy_numeric_constants)]  // MSRV is 1.36
 
 // cbindgen==0.8.0 fails to export typedefs for opaque pointers
 // from the battery crate, if this line is missing
@@ -65,9 +65,9 @@ pub unsafe extern "C" fn battery_manager_refresh(manager_ptr: *mut Manager, batt
     let manager = &mut *manager_ptr;
 
     assert!(!battery_ptr.is_null());
-    let mut battery = &mut *battery_ptr;
+    let battery = &mut *battery_ptr;
 
-    match manager.refresh(&mut battery) {
+    match manager.refresh(battery) {
         Ok(_) => 0,
         Err(e) => {
             crate::errors::set_last_error(e);
@@ -83,5 +83,5 @@ pub unsafe extern "C" fn battery_manager_free(ptr: *mut Manager) {
         return;
     }
 
-    Box::from_raw(ptr);
+    let _ = Box::from_raw(ptr);
 }
@@ -0,0 +1,467 @@
+//! Recording and replaying of the `/sys/class/power_supply` tree.
+//!
+//! [Recorder] captures all devices attributes into the [Archive] on each
+//! [Recorder::capture] call, and archive can be saved into one text file,
+//! which is convenient to attach to the bug reports.
+//!
+//! [Replay] writes archived [frames](struct.Frame.html) one by one into a temporary directory,
+//! so the [Manager](../struct.Manager.html) created by [Replay::manager] reads them
+//! in the same way as it reads the real devices.
+//!
+//! Only the device directories contents are captured, so quirks matched by the `device/driver` link
+//! or by the DMI `sys_vendor` value are not applied to the replayed devices.
+//!
+//! This module is available on Linux only.
+//!
+//! # Example
+//!
+//! ```edition2018,no_run
+//! # use std::thread;
+//! # use std::time::Duration;
+//! # use battery::Result;
+//! use battery::archive::{Recorder, Replay};
+//!
+//! # fn main() -> Result<()> {
+//! let mut recorder = Recorder::new();
+//! for _ in 0..10 {
+//!     recorder.capture()?;
+//!     thread::sleep(Duration::from_secs(1));
+//! }
+//! recorder.archive().save("battery.archive")?;
+//!
+//! let mut replay = Replay::load("battery.archive")?;
+//! let manager = replay.manager()?;
+//! let mut battery = manager.batteries()?.next().unwrap()?;
+//! while replay.advance()? {
+//!     manager.refresh(&mut battery)?;
+//!     println!("{:?}", battery.energy());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [Archive]: struct.Archive.html
+//! [Recorder]: struct.Recorder.html
+//! [Recorder::capture]: struct.Recorder.html#method.capture
+//! [Replay]: struct.Replay.html
+//! [Replay::manager]: struct.Replay.html#method.manager
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::{Error, Manager, Result};
+
+#[allow(clippy::redundant_static_lifetimes)]
+static SYSFS_ROOT: &'static str = "/sys/class/power_supply";
+#[allow(clippy::redundant_static_lifetimes)]
+static HEADER: &'static str = "# battery archive v1";
+
+/// Attributes of one device, keyed by the attribute name.
+pub type Attributes = BTreeMap<String, String>;
+
+/// State of all devices at some moment.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Frame {
+    elapsed: Duration,
+    devices: BTreeMap<String, Attributes>,
+}
+
+impl Frame {
+    /// Creates empty frame.
+    pub fn new(elapsed: Duration) -> Frame {
+        Frame {
+            elapsed,
+            devices: BTreeMap::new(),
+        }
+    }
+
+    /// Reads all devices attributes from the `root` directory.
+    ///
+    /// Only regular files are captured; attributes which can't be read
+    /// (for example, because of the `ENODEV` error) are skipped, as they are treated as missing ones anyway.
+    /// Symlinks are skipped too, including the `device/driver` one.
+    pub fn capture<T: AsRef<Path>>(root: T, elapsed: Duration) -> Result<Frame> {
+        let mut frame = Frame::new(elapsed);
+        for entry in fs::read_dir(root)? {
+            let path = entry?.path();
+            let device = match path.file_name() {
+                Some(name) => name.to_string_lossy().into_owned(),
+                None => continue,
+            };
+            if !path.is_dir() {
+                continue;
+            }
+
+            let mut attributes = Attributes::new();
+            for attribute in fs::read_dir(&path)? {
+                let attribute = attribute?;
+                // `device`, `subsystem` and other symlinks are leading outside of the device directory
+                if !attribute.file_type()?.is_file() {
+                    continue;
+                }
+                if let Ok(content) = fs::read(attribute.path()) {
+                    let mut value = String::from_utf8_lossy(&content).into_owned();
+                    if value.ends_with('\n') {
+                        value.truncate(value.len() - 1);
+                    }
+                    attributes.insert(attribute.file_name().to_string_lossy().into_owned(), value);
+                }
+            }
+            frame.devices.insert(device, attributes);
+        }
+
+        Ok(frame)
+    }
+
+    /// Time passed since the first frame.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Devices attributes, keyed by the device name.
+    pub fn devices(&self) -> &BTreeMap<String, Attributes> {
+        &self.devices
+    }
+
+    /// Sets the `device` attribute value.
+    ///
+    /// Returns an error if `device` or `attribute` is not a plain file name,
+    /// as it would point outside of the device directory when the frame is written.
+    pub fn set<D, A, V>(&mut self, device: D, attribute: A, value: V) -> Result<()>
+    where
+        D: Into<String>,
+        A: Into<String>,
+        V: Into<String>,
+    {
+        let device = device.into();
+        let attribute = attribute.into();
+        check_name(&device)?;
+        check_name(&attribute)?;
+
+        let attributes = self.devices.entry(device).or_default();
+        attributes.insert(attribute, value.into());
+
+        Ok(())
+    }
+
+    /// Returns the `device` attribute value.
+    pub fn get(&self, device: &str, attribute: &str) -> Option<&str> {
+        self.devices
+            .get(device)
+            .and_then(|attributes| attributes.get(attribute))
+            .map(AsRef::as_ref)
+    }
+
+    /// Writes frame into the `root` directory, removing devices and attributes
+    /// which are not present in this frame.
+    pub fn write_into<T: AsRef<Path>>(&self, root: T) -> Result<()> {
+        let root = root.as_ref();
+        for entry in fs::read_dir(root)? {
+            let path = entry?.path();
+            let name = path.file_name().map(|name| name.to_string_lossy().into_owned());
+            match name.and_then(|name| self.devices.get(&name)) {
+                Some(attributes) => {
+                    for attribute in fs::read_dir(&path)? {
+                        let attribute = attribute?;
+                        let name = attribute.file_name().to_string_lossy().into_owned();
+                        if !attributes.contains_key(&name) {
+                            fs::remove_file(attribute.path())?;
+                        }
+                    }
+                }
+                None => fs::remove_dir_all(&path)?,
+            }
+        }
+
+        for (device, attributes) in &self.devices {
+            check_name(device)?;
+            let path = root.join(device);
+            fs::create_dir_all(&path)?;
+            for (attribute, value) in attributes {
+                check_name(attribute)?;
+                fs::write(path.join(attribute), format!("{}\n", value))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Time series of the captured [frames](struct.Frame.html).
+///
+/// Archive is stored as a text file: each frame starts with the `frame <milliseconds>` line,
+/// followed by the `<device>/<attribute>=<value>` lines.
+/// Newlines and backslashes in values are escaped as `\n` and `\\`.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Archive {
+    frames: Vec<Frame>,
+}
+
+impl Archive {
+    /// Creates empty archive.
+    pub fn new() -> Archive {
+        Archive::default()
+    }
+
+    /// Loads archive from the file.
+    pub fn load<T: AsRef<Path>>(path: T) -> Result<Archive> {
+        let path = path.as_ref();
+        let file = fs::File::open(path).map_err(|e| Error::from(e).with_attribute(path))?;
+
+        Self::read_from(file).map_err(|e| e.with_attribute(path))
+    }
+
+    /// Saves archive into the file.
+    pub fn save<T: AsRef<Path>>(&self, path: T) -> Result<()> {
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        self.write_to(&mut file)?;
+        file.flush()?;
+
+        Ok(())
+    }
+
+    /// Reads archive in the text format.
+    pub fn read_from<R: Read>(reader: R) -> Result<Archive> {
+        let mut archive = Archive::new();
+        for (idx, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            let invalid = || Error::invalid_data(format!("Malformed archive line {}", idx + 1)).with_raw_value(&*line);
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if line.starts_with("frame ") {
+                let elapsed = line["frame ".len()..].trim().parse::<u64>().map_err(|_| invalid())?;
+                archive.frames.push(Frame::new(Duration::from_millis(elapsed)));
+                continue;
+            }
+
+            let frame = archive.frames.last_mut().ok_or_else(invalid)?;
+            let (name, value) = match line.find('=') {
+                Some(pos) => (&line[..pos], &line[pos + 1..]),
+                None => return Err(invalid()),
+            };
+            let (device, attribute) = match name.find('/') {
+                Some(pos) => (&name[..pos], &name[pos + 1..]),
+                None => return Err(invalid()),
+            };
+            if !is_valid_name(device) || !is_valid_name(attribute) {
+                return Err(invalid());
+            }
+            frame.set(device, attribute, unescape(value))?;
+        }
+
+        Ok(archive)
+    }
+
+    /// Writes archive in the text format.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<()> {
+        writeln!(writer, "{}", HEADER)?;
+        for frame in &self.frames {
+            writeln!(writer)?;
+            writeln!(writer, "frame {}", frame.elapsed.as_millis())?;
+            for (device, attributes) in &frame.devices {
+                for (attribute, value) in attributes {
+                    writeln!(writer, "{}/{}={}", device, attribute, escape(value))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds frame to the end of archive.
+    pub fn push(&mut self, frame: Frame) {
+        self.frames.push(frame);
+    }
+
+    /// Captured frames.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+}
+
+/// Captures the power supply devices state into an [Archive](struct.Archive.html).
+#[derive(Debug)]
+pub struct Recorder {
+    root: PathBuf,
+    started: Option<Instant>,
+    archive: Archive,
+}
+
+impl Recorder {
+    /// Creates recorder for the `/sys/class/power_supply` directory.
+    pub fn new() -> Recorder {
+        Self::with_root(SYSFS_ROOT)
+    }
+
+    /// Creates recorder for the `root` directory with the same layout as `/sys/class/power_supply`.
+    pub fn with_root<T: Into<PathBuf>>(root: T) -> Recorder {
+        Recorder {
+            root: root.into(),
+            started: None,
+            archive: Archive::new(),
+        }
+    }
+
+    /// Captures all devices into the new frame.
+    pub fn capture(&mut self) -> Result<()> {
+        let started = *self.started.get_or_insert_with(Instant::now);
+        let frame = Frame::capture(&self.root, started.elapsed())?;
+        self.archive.push(frame);
+
+        Ok(())
+    }
+
+    /// Frames captured so far.
+    pub fn archive(&self) -> &Archive {
+        &self.archive
+    }
+
+    /// Consumes recorder, returning the captured frames.
+    pub fn into_archive(self) -> Archive {
+        self.archive
+    }
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Replays [archive](struct.Archive.html) frames through the [Manager](../struct.Manager.html).
+///
+/// Frames are written into the temporary directory, which is removed when `Replay` is dropped.
+#[derive(Debug)]
+pub struct Replay {
+    archive: Archive,
+    root: PathBuf,
+    position: usize,
+}
+
+impl Replay {
+    /// Creates replay, positioned at the first archive frame.
+    pub fn new(archive: Archive) -> Result<Replay> {
+        let replay = Replay {
+            archive,
+            root: temp_dir()?,
+            position: 0,
+        };
+        if let Some(frame) = replay.archive.frames.first() {
+            frame.write_into(&replay.root)?;
+        }
+
+        Ok(replay)
+    }
+
+    /// Loads archive from the file and creates replay for it.
+    pub fn load<T: AsRef<Path>>(path: T) -> Result<Replay> {
+        Self::new(Archive::load(path)?)
+    }
+
+    /// Creates manager, which reads devices from the current frame.
+    pub fn manager(&self) -> Result<Manager> {
+        Manager::with_sysfs_root(self.root.clone())
+    }
+
+    /// Directory with the current frame contents.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Index of the current frame.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Current frame, if archive is not empty.
+    pub fn frame(&self) -> Option<&Frame> {
+        self.archive.frames.get(self.position)
+    }
+
+    /// Moves to the next frame, returning `false` if the current frame is the last one.
+    pub fn advance(&mut self) -> Result<bool> {
+        if self.position + 1 >= self.archive.frames.len() {
+            return Ok(false);
+        }
+
+        self.seek(self.position + 1)?;
+
+        Ok(true)
+    }
+
+    /// Moves to the frame with `position` index.
+    pub fn seek(&mut self, position: usize) -> Result<()> {
+        match self.archive.frames.get(position) {
+            Some(frame) => {
+                frame.write_into(&self.root)?;
+                self.position = position;
+                Ok(())
+            }
+            None => Err(Error::not_found(format!("Archive has no frame {}", position))),
+        }
+    }
+}
+
+impl Drop for Replay {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+/// Creates unique directory in the system temporary directory.
+pub(crate) fn temp_dir() -> Result<PathBuf> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    loop {
+        let idx = COUNTER.fetch_add(1, Ordering::SeqCst);
+        let path = env::temp_dir().join(format!("battery-{}-{}", process::id(), idx));
+        match fs::create_dir(&path) {
+            Ok(()) => return Ok(path),
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Checks if `name` is a plain file name, which can't point outside of the directory it is joined to:
+/// it is not empty, is not `.` or `..` and has no `/` (so it is not an absolute path either) or `\0` in it.
+pub(crate) fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains('/') && !name.contains('\0')
+}
+
+fn check_name(name: &str) -> Result<()> {
+    if is_valid_name(name) {
+        Ok(())
+    } else {
+        Err(Error::invalid_data(format!("Invalid device or attribute name `{}`", name)).with_raw_value(name))
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(chr) = chars.next() {
+        match chr {
+            '\\' => match chars.next() {
+                Some('n') => result.push('\n'),
+                Some(other) => result.push(other),
+                None => result.push('\\'),
+            },
+            chr => result.push(chr),
+        }
+    }
+
+    result
+}
@@ -0,0 +1,285 @@
+//! Alternative state of health against the baseline capacity.
+//!
+//! Some firmwares are reporting the design energy equal to the current full energy
+//! or a nonsense value, which makes the [state of health](../struct.Battery.html#method.state_of_health)
+//! meaningless. [Baselines] persists the first full energy seen for each [BatteryId](../struct.BatteryId.html)
+//! and accepts the user-provided nominal capacity, which takes precedence over the first seen one.
+//! [Health] returns the state of health against that baseline alongside the reported one.
+//!
+//! Baselines are written into the `baseline` file, one per [battery directory](../history/index.html#storage).
+//!
+//! This module requires the `history` feature.
+//!
+//! # Example
+//!
+//! ```edition2018,no_run
+//! # use battery::Result;
+//! use battery::baseline::Baselines;
+//! use battery::units::energy::watt_hour;
+//! use battery::units::Energy;
+//!
+//! # fn main() -> Result<()> {
+//! let baselines = Baselines::open("/var/lib/battery/baseline")?;
+//! for battery in battery::Manager::new()?.batteries()? {
+//!     let battery = battery?;
+//!     if battery.model() == Some("5B10W13930") {
+//!         baselines.set_nominal(&battery.id(), Energy::new::<watt_hour>(57.0))?;
+//!     }
+//!     let health = baselines.update(&battery)?;
+//!     println!("{:?} vs {:?}", health.state_of_health(), health.baseline_state_of_health());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [Baselines]: struct.Baselines.html
+//! [Health]: struct.Health.html
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::history::store::{self, Store};
+use crate::history::unix_seconds;
+
+use crate::units::energy::joule;
+use crate::units::ratio::ratio;
+use crate::units::{Energy, Ratio};
+use crate::{Battery, BatteryId, Result};
+
+#[allow(clippy::redundant_static_lifetimes)]
+static HEADER: &'static str = "# battery baseline v1";
+#[allow(clippy::redundant_static_lifetimes)]
+static FILE: &'static str = "baseline";
+
+/// Relative difference between the design and full energy, below which they are considered to be equal.
+const SAME_ENERGY: f32 = 0.001;
+
+/// Where the baseline capacity comes from.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Source {
+    /// First full energy seen for the battery.
+    FirstSeen,
+    /// Capacity provided by the user.
+    Nominal,
+
+    // Awaiting for https://github.com/rust-lang/rust/issues/44109
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+/// Baseline capacity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Baseline {
+    energy: Energy,
+    source: Source,
+    recorded: SystemTime,
+}
+
+impl Baseline {
+    pub fn energy(&self) -> Energy {
+        self.energy
+    }
+
+    pub fn source(&self) -> Source {
+        self.source
+    }
+
+    /// Moment when the baseline was recorded.
+    pub fn recorded(&self) -> SystemTime {
+        self.recorded
+    }
+
+    fn to_line(&self) -> String {
+        let source = match self.source {
+            Source::Nominal => "nominal",
+            _ => "first_seen",
+        };
+
+        format!("{} {} {}", source, unix_seconds(self.recorded), self.energy.get::<joule>())
+    }
+
+    fn from_line(line: &str) -> Option<Baseline> {
+        let mut parts = line.split_whitespace();
+        let source = match parts.next()? {
+            "first_seen" => Source::FirstSeen,
+            "nominal" => Source::Nominal,
+            _ => return None,
+        };
+        let recorded = parts.next()?.parse::<u64>().ok()?;
+        let energy = parts.next()?.parse::<f32>().ok()?;
+        if parts.next().is_some() {
+            return None;
+        }
+
+        Some(Baseline {
+            energy: Energy::new::<joule>(energy),
+            source,
+            recorded: UNIX_EPOCH + Duration::from_secs(recorded),
+        })
+    }
+}
+
+/// Reported and baseline state of health.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Health {
+    state_of_health: Option<Ratio>,
+    baseline: Option<Baseline>,
+    baseline_state_of_health: Option<Ratio>,
+    is_design_suspicious: bool,
+}
+
+impl Health {
+    /// State of health against the design energy, as reported by the battery.
+    pub fn state_of_health(&self) -> Option<Ratio> {
+        self.state_of_health
+    }
+
+    /// Baseline used for the alternative state of health.
+    pub fn baseline(&self) -> Option<&Baseline> {
+        self.baseline.as_ref()
+    }
+
+    /// Full energy to the baseline energy ratio.
+    pub fn baseline_state_of_health(&self) -> Option<Ratio> {
+        self.baseline_state_of_health
+    }
+
+    /// Returns `true` if the design energy is missing, equal to the full energy,
+    /// or the full energy exceeds it by more than a quarter.
+    pub fn is_design_suspicious(&self) -> bool {
+        self.is_design_suspicious
+    }
+}
+
+/// Persistent baseline capacities.
+///
+/// See the [module documentation](index.html) for details.
+#[derive(Debug, Clone)]
+pub struct Baselines {
+    store: Store,
+}
+
+impl Baselines {
+    /// Opens baselines saved under the `dir` root directory.
+    pub fn open<T: Into<PathBuf>>(dir: T) -> Result<Baselines> {
+        Ok(Baselines {
+            store: Store::open(dir.into())?,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        self.store.root()
+    }
+
+    /// Sets the nominal capacity for the battery `id`, replacing the previous one.
+    pub fn set_nominal(&self, id: &BatteryId, energy: Energy) -> Result<()> {
+        let mut baselines = self.load(id)?;
+        baselines.retain(|baseline| baseline.source != Source::Nominal);
+        baselines.push(Baseline {
+            energy,
+            source: Source::Nominal,
+            recorded: SystemTime::now(),
+        });
+
+        self.save(id, &baselines)
+    }
+
+    /// Returns the battery `id` baseline, nominal capacity is preferred over the first seen one.
+    pub fn baseline(&self, id: &BatteryId) -> Result<Option<Baseline>> {
+        let mut baselines = self.load(id)?;
+        baselines.sort_by_key(|baseline| baseline.source != Source::Nominal);
+
+        Ok(baselines.into_iter().next())
+    }
+
+    /// Records the `battery` full energy, if it is seen for the first time, and returns its health.
+    pub fn update(&self, battery: &Battery) -> Result<Health> {
+        let id = battery.id();
+        let energy_full = battery.readings().energy_full().value();
+        let mut baselines = self.load(&id)?;
+        let has_first_seen = baselines.iter().any(|baseline| baseline.source == Source::FirstSeen);
+        if let (Some(energy_full), false) = (energy_full, has_first_seen) {
+            if energy_full.get::<joule>() > 0.0 {
+                baselines.push(Baseline {
+                    energy: energy_full,
+                    source: Source::FirstSeen,
+                    recorded: SystemTime::now(),
+                });
+                self.save(&id, &baselines)?;
+            }
+        }
+
+        self.get(battery)
+    }
+
+    /// Returns the `battery` health without recording anything.
+    pub fn get(&self, battery: &Battery) -> Result<Health> {
+        let readings = battery.readings();
+        let energy_full = readings.energy_full().value();
+        let baseline = self.baseline(&battery.id())?;
+        let baseline_state_of_health = match (energy_full, &baseline) {
+            (Some(energy_full), Some(baseline)) if baseline.energy.get::<joule>() > 0.0 => {
+                Some(Ratio::new::<ratio>((energy_full / baseline.energy).get::<ratio>()))
+            }
+            _ => None,
+        };
+        let is_design_suspicious = match (readings.energy_full_design().value(), energy_full) {
+            (Some(design), Some(full)) if design.get::<joule>() > 0.0 => {
+                let relative = (full / design).get::<ratio>();
+                (relative - 1.0).abs() < SAME_ENERGY || relative > 1.25
+            }
+            _ => true,
+        };
+
+        Ok(Health {
+            state_of_health: readings.state_of_health().value(),
+            baseline,
+            baseline_state_of_health,
+            is_design_suspicious,
+        })
+    }
+
+    fn load(&self, id: &BatteryId) -> Result<Vec<Baseline>> {
+        match self.store.find_file(id, FILE)? {
+            Some(path) => store::read_records(&path, "baseline", Baseline::from_line),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn save(&self, id: &BatteryId, baselines: &[Baseline]) -> Result<()> {
+        let lines = baselines.iter().map(Baseline::to_line);
+
+        store::write_records(&self.store.file(id, FILE)?, HEADER, lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{Baselines, Source};
+    use crate::units::energy::watt_hour;
+    use crate::units::Energy;
+    use crate::BatteryId;
+
+    #[test]
+    fn test_set_nominal() {
+        let root = tempfile::tempdir().unwrap();
+        let baselines = Baselines::open(root.path()).unwrap();
+        let id = BatteryId::new(None, Some("5B10W13930"), Some("1234"));
+
+        assert_eq!(baselines.baseline(&id).unwrap(), None);
+        baselines.set_nominal(&id, Energy::new::<watt_hour>(60.0)).unwrap();
+        baselines.set_nominal(&id, Energy::new::<watt_hour>(57.0)).unwrap();
+
+        let baseline = baselines.baseline(&id).unwrap().unwrap();
+        assert_eq!(baseline.source(), Source::Nominal);
+        assert_eq!(baseline.energy(), Energy::new::<watt_hour>(57.0));
+        let path = root.path().join(id.to_string()).join("baseline");
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().filter(|line| line.starts_with("nominal ")).count(), 1);
+
+        fs::write(&path, "first_seen now\n").unwrap();
+        assert!(baselines.baseline(&id).is_err());
+    }
+}
@@ -0,0 +1,245 @@
+//! Charge cycles estimation.
+//!
+//! Many drivers are not reporting the cycle count, so [CycleCounter] accumulates the energy
+//! discharged from each battery across refreshes and persists it per [BatteryId](../struct.BatteryId.html).
+//! Equivalent full cycles are estimated as the discharged energy to the design energy ratio
+//! and are reported by the [CycleCount] next to the driver value.
+//!
+//! Counter state is persisted in the [battery directory](../history/index.html#storage) `cycles` file.
+//!
+//! This module requires the `history` feature.
+//!
+//! Only the energy decrease between two consecutive updates is counted, so the counter should be updated
+//! often enough to see the battery discharging; energy lost while the counter was not running
+//! (for example, while the system was suspended) is counted on the next update too.
+//!
+//! # Example
+//!
+//! ```edition2018,no_run
+//! # use std::thread;
+//! # use std::time::Duration;
+//! # use battery::Result;
+//! use battery::cycles::CycleCounter;
+//!
+//! # fn main() -> Result<()> {
+//! let counter = CycleCounter::open("/var/lib/battery/cycles")?;
+//! let manager = battery::Manager::new()?;
+//! let mut battery = manager.batteries()?.next().unwrap()?;
+//! loop {
+//!     manager.refresh(&mut battery)?;
+//!     println!("cycles: {}", counter.update(&battery)?);
+//!     thread::sleep(Duration::from_secs(60));
+//! }
+//! # }
+//! ```
+//!
+//! [CycleCounter]: struct.CycleCounter.html
+//! [CycleCount]: struct.CycleCount.html
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::history::store::{self, Store};
+
+use crate::units::energy::joule;
+use crate::units::ratio::ratio;
+use crate::units::Energy;
+use crate::{Battery, BatteryId, Result, State};
+
+#[allow(clippy::redundant_static_lifetimes)]
+static HEADER: &'static str = "# battery cycles v1";
+#[allow(clippy::redundant_static_lifetimes)]
+static FILE: &'static str = "cycles";
+
+/// Cycle count reported by the driver and the estimated one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CycleCount {
+    reported: Option<u32>,
+    estimated: Option<f32>,
+    discharged: Energy,
+}
+
+impl CycleCount {
+    /// Cycle count reported by the driver.
+    pub fn reported(&self) -> Option<u32> {
+        self.reported
+    }
+
+    /// Equivalent full cycles estimated from the discharged energy,
+    /// `None` if the design energy is not available.
+    pub fn estimated(&self) -> Option<f32> {
+        self.estimated
+    }
+
+    /// Total energy discharged since the counter started tracking the battery.
+    pub fn discharged(&self) -> Energy {
+        self.discharged
+    }
+}
+
+impl fmt::Display for CycleCount {
+    /// Formats reported cycle count, followed by the estimated one,
+    /// for example, `120` or `87.4 (estimated)`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.reported, self.estimated) {
+            (Some(reported), Some(estimated)) => write!(f, "{} ({:.1} estimated)", reported, estimated),
+            (Some(reported), None) => write!(f, "{}", reported),
+            (None, Some(estimated)) => write!(f, "{:.1} (estimated)", estimated),
+            (None, None) => f.write_str("unknown"),
+        }
+    }
+}
+
+/// Persistent energy throughput accumulator.
+///
+/// See the [module documentation](index.html) for details.
+#[derive(Debug, Clone)]
+pub struct CycleCounter {
+    store: Store,
+}
+
+impl CycleCounter {
+    /// Opens counter with the `dir` root directory.
+    pub fn open<T: Into<PathBuf>>(dir: T) -> Result<CycleCounter> {
+        Ok(CycleCounter {
+            store: Store::open(dir.into())?,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        self.store.root()
+    }
+
+    /// Accounts the energy discharged from the `battery` since the previous update.
+    pub fn update(&self, battery: &Battery) -> Result<CycleCount> {
+        let readings = battery.readings();
+        let discharged = match readings.energy().value() {
+            Some(energy) => self.record(&battery.id(), battery.state(), energy)?,
+            None => self.discharged(&battery.id())?,
+        };
+
+        Ok(Self::count(battery, discharged))
+    }
+
+    /// Returns the `battery` cycle count without updating the counter.
+    pub fn get(&self, battery: &Battery) -> Result<CycleCount> {
+        let discharged = self.discharged(&battery.id())?;
+
+        Ok(Self::count(battery, discharged))
+    }
+
+    /// Accounts the battery `id` energy change and returns the total discharged energy.
+    ///
+    /// Energy decrease is counted unless the battery is charging or full,
+    /// as the energy might fluctuate while it is kept at the full charge.
+    pub fn record(&self, id: &BatteryId, state: State, energy: Energy) -> Result<Energy> {
+        let (mut discharged, last) = self.load(id)?;
+        if let Some(last) = last {
+            let is_discharging = state != State::Charging && state != State::Full;
+            if is_discharging && energy < last {
+                discharged += last - energy;
+            }
+        }
+        self.save(id, discharged, energy)?;
+
+        Ok(discharged)
+    }
+
+    /// Returns the battery `id` total discharged energy.
+    pub fn discharged(&self, id: &BatteryId) -> Result<Energy> {
+        Ok(self.load(id)?.0)
+    }
+
+    fn count(battery: &Battery, discharged: Energy) -> CycleCount {
+        let readings = battery.readings();
+        let design = readings
+            .energy_full_design()
+            .value()
+            .filter(|design| design.get::<joule>() > 0.0);
+
+        CycleCount {
+            reported: readings.cycle_count().value(),
+            estimated: design.map(|design| (discharged / design).get::<ratio>()),
+            discharged,
+        }
+    }
+
+    fn load(&self, id: &BatteryId) -> Result<(Energy, Option<Energy>)> {
+        let records = match self.store.find_file(id, FILE)? {
+            Some(path) => store::read_records(&path, "cycles", parse_state)?,
+            None => Vec::new(),
+        };
+
+        match records.last() {
+            Some((discharged, last)) => Ok((Energy::new::<joule>(*discharged), last.map(Energy::new::<joule>))),
+            None => Ok((Energy::new::<joule>(0.0), None)),
+        }
+    }
+
+    fn save(&self, id: &BatteryId, discharged: Energy, last: Energy) -> Result<()> {
+        let state = format!("{} {}", discharged.get::<joule>(), last.get::<joule>());
+
+        store::write_records(&self.store.file(id, FILE)?, HEADER, Some(state))
+    }
+}
+
+/// Parses the discharged energy and the last energy, which is `-` if it is unknown.
+fn parse_state(line: &str) -> Option<(f32, Option<f32>)> {
+    let mut parts = line.split_whitespace();
+    let discharged = parts.next()?.parse::<f32>().ok()?;
+    let last = match parts.next() {
+        Some("-") | None => None,
+        Some(part) => Some(part.parse::<f32>().ok()?),
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some((discharged, last))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use approx::assert_abs_diff_eq;
+
+    use super::CycleCounter;
+    use crate::history::tests::id;
+    use crate::units::energy::watt_hour;
+    use crate::units::Energy;
+    use crate::{BatteryId, State};
+
+    #[test]
+    fn test_record() {
+        let root = tempfile::tempdir().unwrap();
+        let counter = CycleCounter::open(root.path()).unwrap();
+        let id = id();
+        let wh = Energy::new::<watt_hour>;
+
+        let steps = [
+            (State::Discharging, 40.0),
+            (State::Discharging, 30.0),
+            (State::Charging, 45.0),
+            // Fluctuation at the full charge is not counted
+            (State::Full, 44.0),
+            (State::Unknown, 40.0),
+        ];
+        for (state, energy) in steps.iter() {
+            counter.record(&id, *state, wh(*energy)).unwrap();
+        }
+        assert_abs_diff_eq!(counter.discharged(&id).unwrap().get::<watt_hour>(), 14.0, epsilon = 0.001);
+
+        // Energy discharged while the counter was not running is accounted on the next start
+        let counter = CycleCounter::open(root.path()).unwrap();
+        let discharged = counter.record(&id, State::Discharging, wh(35.0)).unwrap();
+        assert_abs_diff_eq!(discharged.get::<watt_hour>(), 19.0, epsilon = 0.001);
+
+        let other = BatteryId::new(None, Some("Other"), None);
+        assert_eq!(counter.discharged(&other).unwrap(), wh(0.0));
+
+        counter.record(&other, State::Discharging, wh(10.0)).unwrap();
+        fs::write(root.path().join(other.to_string()).join("cycles"), "nonsense\n").unwrap();
+        assert!(counter.discharged(&other).is_err());
+    }
+}
@@ -0,0 +1,408 @@
+//! Parsers for the textual battery dumps.
+//!
+//! Bug reports are usually coming with the `/sys/class/power_supply/<name>/uevent` file contents,
+//! `upower -i <device>` (or `upower -d`) output or `acpi -V` output attached.
+//! Functions from this module are converting these dumps into the sysfs [Frame],
+//! which can be turned into the [Battery] values with the [batteries] function.
+//!
+//! Values are calculated by the same code, which handles the real devices on Linux,
+//! so it is possible to see what this crate would report for the reporter machine.
+//!
+//! This module is available on Linux only.
+//!
+//! # Example
+//!
+//! ```edition2018
+//! # use battery::Result;
+//! use battery::dump;
+//!
+//! # fn main() -> Result<()> {
+//! let frame = dump::uevent(
+//!     "POWER_SUPPLY_NAME=BAT0
+//! POWER_SUPPLY_STATUS=Discharging
+//! POWER_SUPPLY_ENERGY_NOW=25000000
+//! POWER_SUPPLY_ENERGY_FULL=50000000
+//! POWER_SUPPLY_VOLTAGE_NOW=12000000",
+//! )?;
+//!
+//! let battery = dump::batteries(&frame)?.remove(0);
+//! assert_eq!(battery.state_of_charge().value, 0.5);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [Frame]: ../archive/struct.Frame.html
+//! [Battery]: ../struct.Battery.html
+//! [batteries]: fn.batteries.html
+
+use std::mem;
+use std::time::Duration;
+
+use crate::archive::{Archive, Frame, Replay};
+use crate::{Battery, Error, Result};
+
+/// Parses `uevent` file contents.
+///
+/// Multiple devices can be concatenated, each one should start with the `POWER_SUPPLY_NAME` line.
+/// Devices without name are called `BAT0`, `BAT1` and so on.
+pub fn uevent(text: &str) -> Result<Frame> {
+    let mut frame = Frame::new(Duration::default());
+    let mut device: Option<String> = None;
+    let mut unnamed = 0;
+    for line in text.lines() {
+        let line = line.trim();
+        let (key, value) = match line.find('=') {
+            Some(pos) => (&line[..pos], line[pos + 1..].trim()),
+            None => continue,
+        };
+        if !key.starts_with("POWER_SUPPLY_") {
+            continue;
+        }
+        let attribute = key["POWER_SUPPLY_".len()..].to_ascii_lowercase();
+
+        if attribute == "name" {
+            device = Some(value.to_string());
+            frame.set(value, "type", "Battery")?;
+            continue;
+        }
+        let name = match device {
+            Some(ref name) => name.clone(),
+            None => {
+                let name = format!("BAT{}", unnamed);
+                unnamed += 1;
+                frame.set(name.as_str(), "type", "Battery")?;
+                device = Some(name.clone());
+                name
+            }
+        };
+        frame.set(name, attribute, value)?;
+    }
+
+    non_empty(frame, "uevent")
+}
+
+/// Parses `upower -i <device>` or `upower -d` output.
+///
+/// Only devices with the `battery` section are parsed; batteries of the peripheral devices
+/// (`power supply: no`) are kept, but they are not loaded by the [batteries](fn.batteries.html) function.
+pub fn upower(text: &str) -> Result<Frame> {
+    let mut frame = Frame::new(Duration::default());
+    let mut devices: Vec<Vec<(String, String)>> = Vec::new();
+    let mut current: Vec<(String, String)> = Vec::new();
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("Device:") || trimmed.starts_with("Daemon:") {
+            devices.push(mem::replace(&mut current, Vec::new()));
+            continue;
+        }
+        if trimmed == "battery" {
+            current.push(("battery".to_string(), String::new()));
+            continue;
+        }
+        if let Some(pos) = trimmed.find(':') {
+            let key = trimmed[..pos].trim();
+            if key == "native-path" && current.iter().any(|(key, _)| key == "native-path") {
+                devices.push(mem::replace(&mut current, Vec::new()));
+            }
+            current.push((key.to_string(), trimmed[pos + 1..].trim().to_string()));
+        }
+    }
+    devices.push(current);
+
+    let mut unnamed = 0;
+    for properties in devices {
+        if !properties.iter().any(|(key, _)| key == "battery") {
+            continue;
+        }
+        let name = properties
+            .iter()
+            .find(|(key, _)| key == "native-path")
+            .and_then(|(_, value)| value.rsplit('/').next())
+            .filter(|name| !name.is_empty() && *name != "(null)")
+            .map(ToString::to_string)
+            .unwrap_or_else(|| {
+                unnamed += 1;
+                format!("BAT{}", unnamed - 1)
+            });
+
+        frame.set(name.as_str(), "type", "Battery")?;
+        for (key, value) in &properties {
+            let (attribute, value) = match key.as_str() {
+                "vendor" => ("manufacturer", value.clone()),
+                "model" => ("model_name", value.clone()),
+                "serial" => ("serial_number", value.clone()),
+                "power supply" if value == "no" => ("scope", "Device".to_string()),
+                "state" => ("status", upower_state(value).to_string()),
+                "technology" => ("technology", upower_technology(value).to_string()),
+                // `Wh`, `W` and `V` values are stored in `µWh`, `µW` and `µV`
+                "energy" => ("energy_now", scaled(value, 1_000_000.0)),
+                "energy-full" => ("energy_full", scaled(value, 1_000_000.0)),
+                "energy-full-design" => ("energy_full_design", scaled(value, 1_000_000.0)),
+                "energy-rate" => ("power_now", scaled(value, 1_000_000.0)),
+                "voltage" => ("voltage_now", scaled(value, 1_000_000.0)),
+                "percentage" => ("capacity", scaled(value, 1.0)),
+                // Tenths of degree Celsius
+                "temperature" => ("temp", scaled(value, 10.0)),
+                // `upower` prints zero or negative values if cycle count is unknown
+                "charge-cycles" if number(value).map_or(false, |value| value > 0.0) => {
+                    ("cycle_count", scaled(value, 1.0))
+                }
+                _ => continue,
+            };
+            if !value.is_empty() {
+                frame.set(name.as_str(), attribute, value)?;
+            }
+        }
+    }
+
+    non_empty(frame, "upower")
+}
+
+/// Parses `acpi -V` (or `acpi -b -i`) output.
+///
+/// `acpi` does not print the device names, so batteries are called `BAT0`, `BAT1` and so on,
+/// according to their numbers in the output.
+pub fn acpi(text: &str) -> Result<Frame> {
+    let mut frame = Frame::new(Duration::default());
+    for line in text.lines() {
+        let line = line.trim();
+        if !line.starts_with("Battery ") {
+            continue;
+        }
+        let rest = &line["Battery ".len()..];
+        let (number, info) = match rest.find(':') {
+            Some(pos) => (rest[..pos].trim(), rest[pos + 1..].trim()),
+            None => continue,
+        };
+        let name = format!("BAT{}", number);
+        frame.set(name.as_str(), "type", "Battery")?;
+
+        if info.starts_with("design capacity ") {
+            let capacities = &info["design capacity ".len()..];
+            // design capacity 5700 mAh, last full capacity 5010 mAh = 87%
+            let mut parts = capacities.splitn(2, ", last full capacity ");
+            let design = parts.next().unwrap_or_default();
+            let full = parts.next().unwrap_or_default().split('=').next().unwrap_or_default();
+            for (value, suffix) in &[(design, "_full_design"), (full, "_full")] {
+                let prefix = if value.trim().ends_with("mWh") { "energy" } else { "charge" };
+                // `mAh` and `mWh` values are stored in `µAh` and `µWh`
+                let value = scaled(value, 1000.0);
+                if !value.is_empty() {
+                    frame.set(name.as_str(), format!("{}{}", prefix, suffix), value)?;
+                }
+            }
+            continue;
+        }
+
+        // Discharging, 82%, 02:31:12 remaining
+        let mut parts = info.split(',').map(str::trim);
+        let status = parts.next().unwrap_or_default();
+        if status.is_empty() || status.starts_with(char::is_numeric) {
+            continue;
+        }
+        frame.set(name.as_str(), "status", status)?;
+        if let Some(capacity) = parts.next() {
+            frame.set(name.as_str(), "capacity", scaled(capacity, 1.0))?;
+        }
+    }
+
+    non_empty(frame, "acpi")
+}
+
+/// Loads batteries from the parsed dump.
+///
+/// Dumps are usually incomplete, so batteries are loaded in the lenient mode,
+/// see [Manager::batteries_lenient](../struct.Manager.html#method.batteries_lenient).
+pub fn batteries(frame: &Frame) -> Result<Vec<Battery>> {
+    let mut archive = Archive::new();
+    archive.push(frame.clone());
+    let replay = Replay::new(archive)?;
+    let manager = replay.manager()?;
+
+    manager.batteries_lenient()?.collect()
+}
+
+fn non_empty(frame: Frame, format: &str) -> Result<Frame> {
+    if frame.devices().is_empty() {
+        Err(Error::invalid_data(format!("No batteries found in the {} output", format)))
+    } else {
+        Ok(frame)
+    }
+}
+
+/// Leading number of the `41,26 Wh`-like value.
+fn number(value: &str) -> Option<f64> {
+    let value = value.trim().replace(',', ".");
+    let end = value
+        .find(|chr: char| !(chr.is_ascii_digit() || chr == '.' || chr == '-'))
+        .unwrap_or(value.len());
+
+    value[..end].parse().ok()
+}
+
+/// Multiplies the leading number of `value` by `factor` and formats it as an integer sysfs value.
+///
+/// Returns an empty string if `value` is not a number.
+fn scaled(value: &str, factor: f64) -> String {
+    number(value)
+        .map(|value| format!("{}", (value * factor).round()))
+        .unwrap_or_default()
+}
+
+fn upower_state(value: &str) -> &'static str {
+    match value {
+        "charging" => "Charging",
+        "discharging" => "Discharging",
+        "fully-charged" => "Full",
+        "empty" => "Empty",
+        _ => "Unknown",
+    }
+}
+
+fn upower_technology(value: &str) -> &'static str {
+    match value {
+        "lithium-ion" => "Li-ion",
+        "lithium-polymer" => "Li-poly",
+        "lithium-iron-phosphate" => "LiFe",
+        "lead-acid" => "Pb",
+        "nickel-cadmium" => "NiCd",
+        "nickel-metal-hydride" => "NiMH",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use crate::dump;
+    use crate::{State, Technology};
+
+    #[test]
+    fn test_uevent() {
+        let frame = dump::uevent(
+            "DEVTYPE=power_supply
+POWER_SUPPLY_NAME=BAT0
+POWER_SUPPLY_TYPE=Battery
+POWER_SUPPLY_STATUS=Charging
+POWER_SUPPLY_PRESENT=1
+POWER_SUPPLY_TECHNOLOGY=Li-ion
+POWER_SUPPLY_CYCLE_COUNT=0
+POWER_SUPPLY_VOLTAGE_MIN_DESIGN=11100000
+POWER_SUPPLY_VOLTAGE_NOW=12300000
+POWER_SUPPLY_CURRENT_NOW=1000000
+POWER_SUPPLY_CHARGE_FULL_DESIGN=5000000
+POWER_SUPPLY_CHARGE_FULL=4000000
+POWER_SUPPLY_CHARGE_NOW=2000000
+POWER_SUPPLY_MODEL_NAME=5B10W13930
+POWER_SUPPLY_MANUFACTURER=SMP
+POWER_SUPPLY_NAME=AC
+POWER_SUPPLY_TYPE=Mains
+POWER_SUPPLY_ONLINE=1",
+        )
+        .unwrap();
+        assert_eq!(frame.get("BAT0", "charge_now"), Some("2000000"));
+        assert_eq!(frame.get("AC", "type"), Some("Mains"));
+
+        let batteries = dump::batteries(&frame).unwrap();
+        assert_eq!(batteries.len(), 1);
+        let battery = &batteries[0];
+        assert_eq!(battery.state(), State::Charging);
+        assert_eq!(battery.technology(), Technology::LithiumIon);
+        assert_eq!(battery.vendor(), Some("SMP"));
+        assert_eq!(battery.cycle_count(), None);
+        // Charge values are converted with the design voltage
+        assert_abs_diff_eq!(battery.energy().value, 2.0 * 11.1 * 3600.0, epsilon = 0.1);
+        assert_abs_diff_eq!(battery.energy_rate().value, 11.1, epsilon = 0.01);
+        assert_abs_diff_eq!(battery.state_of_charge().value, 0.5);
+
+        assert!(dump::uevent("DEVTYPE=power_supply").is_err());
+    }
+
+    #[test]
+    fn test_upower() {
+        let frame = dump::upower(
+            "Device: /org/freedesktop/UPower/devices/line_power_AC
+  native-path:          AC
+  power supply:         yes
+  line-power
+    online:              yes
+
+Device: /org/freedesktop/UPower/devices/battery_BAT0
+  native-path:          BAT0
+  vendor:               SMP
+  model:                5B10W13930
+  serial:               1234
+  power supply:         yes
+  has history:          yes
+  battery
+    present:             yes
+    rechargeable:        yes
+    state:               discharging
+    warning-level:       none
+    energy:              41,26 Wh
+    energy-empty:        0 Wh
+    energy-full:         50.1 Wh
+    energy-full-design:  57.02 Wh
+    energy-rate:         8.123 W
+    voltage:             12.1 V
+    charge-cycles:       N/A
+    time to empty:       5.1 hours
+    percentage:          82%
+    capacity:            87.86%
+    technology:          lithium-polymer
+    temperature:         30.5 degrees C
+    icon-name:          'battery-full-symbolic'
+
+Device: /org/freedesktop/UPower/devices/mouse_hidpp_battery_0
+  native-path:          hidpp_battery_0
+  model:                MX Master 3
+  power supply:         no
+  battery
+    state:               discharging
+    percentage:          55%
+",
+        )
+        .unwrap();
+        assert_eq!(frame.devices().len(), 2);
+        assert_eq!(frame.get("BAT0", "energy_now"), Some("41260000"));
+        assert_eq!(frame.get("BAT0", "cycle_count"), None);
+        assert_eq!(frame.get("hidpp_battery_0", "scope"), Some("Device"));
+
+        let batteries = dump::batteries(&frame).unwrap();
+        assert_eq!(batteries.len(), 1);
+        let battery = &batteries[0];
+        assert_eq!(battery.state(), State::Discharging);
+        assert_eq!(battery.technology(), Technology::LithiumPolymer);
+        assert_eq!(battery.serial_number(), Some("1234"));
+        assert_abs_diff_eq!(battery.energy().value, 41.26 * 3600.0, epsilon = 0.1);
+        assert_abs_diff_eq!(battery.energy_rate().value, 8.123, epsilon = 0.001);
+        assert_abs_diff_eq!(battery.voltage().value, 12.1, epsilon = 0.001);
+        assert_abs_diff_eq!(battery.temperature().unwrap().value, 303.65, epsilon = 0.01);
+    }
+
+    #[test]
+    fn test_acpi() {
+        let frame = dump::acpi(
+            "Battery 0: Discharging, 82%, 02:31:12 remaining
+Battery 0: design capacity 57020 mWh, last full capacity 50100 mWh = 87%
+Adapter 0: off-line
+Thermal 0: ok, 45.0 degrees C
+Thermal 0: trip point 0 switches to mode critical at temperature 98.0 degrees C",
+        )
+        .unwrap();
+        assert_eq!(frame.get("BAT0", "status"), Some("Discharging"));
+        assert_eq!(frame.get("BAT0", "energy_full_design"), Some("57020000"));
+        assert_eq!(frame.get("BAT0", "energy_full"), Some("50100000"));
+
+        let batteries = dump::batteries(&frame).unwrap();
+        let battery = &batteries[0];
+        assert_eq!(battery.state(), State::Discharging);
+        assert_abs_diff_eq!(battery.state_of_charge().value, 0.82, epsilon = 0.001);
+        // Energy is derived from the capacity, voltage is not reported by `acpi` at all
+        assert_abs_diff_eq!(battery.energy().value, 0.82 * 50.1 * 3600.0, epsilon = 0.1);
+        assert!(battery.readings().voltage().value().is_none());
+
+        assert!(dump::acpi("Adapter 0: on-line").is_err());
+    }
+}
@@ -4,42 +4,91 @@ use std::borrow::Cow;
 use std::error::Error as StdError;
 use std::fmt;
 use std::io;
+use std::path::{Path, PathBuf};
 use std::result;
 
 pub type Result<T> = result::Result<T, Error>;
 
+/// Category of the [Error](struct.Error.html).
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+pub enum ErrorKind {
+    /// Battery was removed from the system.
+    DeviceGone,
+    /// Device does not provide the required value.
+    AttributeMissing,
+    /// Device provided a value which can't be used.
+    InvalidValue,
+    /// Access to the device was denied by the operating system.
+    PermissionDenied,
+    /// Operation is not supported by the device or platform.
+    Unsupported,
+    /// Device had not responded in time.
+    Timeout,
+    /// Any other I/O error, see the `Error::source` for details.
+    Io,
+
+    // Awaiting for https://github.com/rust-lang/rust/issues/44109
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+impl From<io::ErrorKind> for ErrorKind {
+    fn from(kind: io::ErrorKind) -> ErrorKind {
+        match kind {
+            io::ErrorKind::NotFound => ErrorKind::AttributeMissing,
+            io::ErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
+            io::ErrorKind::InvalidData => ErrorKind::InvalidValue,
+            io::ErrorKind::TimedOut => ErrorKind::Timeout,
+            _ => ErrorKind::Io,
+        }
+    }
+}
+
 /// Battery routines error.
 ///
-/// Since all operations are basically I/O of some kind,
-/// this is a thin wrapper around `::std::io::Error` with option
-/// to store custom description for debugging purposes.
+/// Error [kind](#method.kind) tells the failure reasons apart, while the [device](#method.device) name,
+/// [attribute](#method.attribute) and its [raw value](#method.raw_value) point to the failed value,
+/// where they are known. Underlying `::std::io::Error` is available as the error [source](#method.source).
 #[derive(Debug)]
 pub struct Error {
+    kind: ErrorKind,
     source: io::Error,
     description: Option<Cow<'static, str>>,
+    device: Option<String>,
+    attribute: Option<PathBuf>,
+    raw_value: Option<String>,
 }
 
 impl Error {
-    #[allow(unused)]
-    pub(crate) fn new<T>(e: io::Error, description: T) -> Error
+    fn with_kind<T>(kind: ErrorKind, source: io::Error, description: T) -> Error
     where
         T: Into<Cow<'static, str>>,
     {
         Error {
-            source: e,
+            kind,
+            source,
             description: Some(description.into()),
+            device: None,
+            attribute: None,
+            raw_value: None,
         }
     }
 
+    #[allow(unused)]
+    pub(crate) fn new<T>(e: io::Error, description: T) -> Error
+    where
+        T: Into<Cow<'static, str>>,
+    {
+        Self::with_kind(e.kind().into(), e, description)
+    }
+
     #[allow(unused)]
     pub(crate) fn not_found<T>(description: T) -> Error
     where
         T: Into<Cow<'static, str>>,
     {
-        Error {
-            source: io::Error::from(io::ErrorKind::NotFound),
-            description: Some(description.into()),
-        }
+        let source = io::Error::from(io::ErrorKind::NotFound);
+        Self::with_kind(ErrorKind::AttributeMissing, source, description)
     }
 
     #[allow(unused)]
@@ -47,10 +96,79 @@ impl Error {
     where
         T: Into<Cow<'static, str>>,
     {
-        Error {
-            source: io::Error::from(io::ErrorKind::InvalidData),
-            description: Some(description.into()),
-        }
+        let source = io::Error::from(io::ErrorKind::InvalidData);
+        Self::with_kind(ErrorKind::InvalidValue, source, description)
+    }
+
+    #[allow(unused)]
+    pub(crate) fn unsupported<T>(description: T) -> Error
+    where
+        T: Into<Cow<'static, str>>,
+    {
+        let source = io::Error::from(io::ErrorKind::Other);
+        Self::with_kind(ErrorKind::Unsupported, source, description)
+    }
+
+    #[cfg_attr(not(target_os = "linux"), allow(unused))]
+    pub(crate) fn removed<T>(description: T) -> Error
+    where
+        T: Into<Cow<'static, str>>,
+    {
+        let source = io::Error::from(io::ErrorKind::NotFound);
+        Self::with_kind(ErrorKind::DeviceGone, source, description)
+    }
+
+    #[allow(unused)]
+    pub(crate) fn with_device<T: Into<String>>(mut self, device: T) -> Error {
+        self.device = Some(device.into());
+        self
+    }
+
+    #[allow(unused)]
+    pub(crate) fn with_attribute<T: Into<PathBuf>>(mut self, attribute: T) -> Error {
+        self.attribute = Some(attribute.into());
+        self
+    }
+
+    #[allow(unused)]
+    pub(crate) fn with_raw_value<T: Into<String>>(mut self, raw_value: T) -> Error {
+        self.raw_value = Some(raw_value.into());
+        self
+    }
+
+    /// Returns the error category.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Name of the device this error is related to, if known.
+    ///
+    /// For Linux it is a `/sys/class/power_supply/` directory name, for example, `BAT0`.
+    pub fn device(&self) -> Option<&str> {
+        self.device.as_ref().map(AsRef::as_ref)
+    }
+
+    /// Path of the device attribute this error is related to, if known.
+    ///
+    /// For Linux it is a sysfs file path; other platforms are using the property name.
+    pub fn attribute(&self) -> Option<&Path> {
+        self.attribute.as_ref().map(AsRef::as_ref)
+    }
+
+    /// Raw attribute value which caused this error, if any.
+    pub fn raw_value(&self) -> Option<&str> {
+        self.raw_value.as_ref().map(AsRef::as_ref)
+    }
+
+    /// Returns `true` if error happened because the battery was removed from system.
+    ///
+    /// Battery is considered to be removed if it is not possible to find it anymore,
+    /// even by its [identity](struct.BatteryId.html).
+    /// Information stored in such battery is stale and it should be dropped.
+    ///
+    /// Same as checking for the [ErrorKind::DeviceGone](enum.ErrorKind.html#variant.DeviceGone) kind.
+    pub fn is_removed(&self) -> bool {
+        self.kind == ErrorKind::DeviceGone
     }
 }
 
@@ -63,17 +181,33 @@ impl StdError for Error {
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match &self.description {
-            Some(desc) => write!(f, "{}", desc),
-            None => self.source.fmt(f),
+            Some(desc) => write!(f, "{}", desc)?,
+            None => self.source.fmt(f)?,
+        }
+        if let Some(device) = &self.device {
+            write!(f, " (device `{}`)", device)?;
         }
+        if let Some(attribute) = &self.attribute {
+            write!(f, " (attribute `{}`", attribute.display())?;
+            if let Some(raw_value) = &self.raw_value {
+                write!(f, " = {:?}", raw_value)?;
+            }
+            f.write_str(")")?;
+        }
+
+        Ok(())
     }
 }
 
 impl From<io::Error> for Error {
     fn from(e: io::Error) -> Self {
         Error {
+            kind: e.kind().into(),
             source: e,
             description: None,
+            device: None,
+            attribute: None,
+            raw_value: None,
         }
     }
 }
@@ -82,27 +216,21 @@ impl From<io::Error> for Error {
 mod nix_impl {
     use std::io;
 
-    use super::Error;
+    use super::{Error, ErrorKind};
 
     impl From<nix::Error> for Error {
         fn from(e: nix::Error) -> Self {
             match e {
-                nix::Error::Sys(errno) => Error {
-                    source: io::Error::from_raw_os_error(errno as i32),
-                    description: Some(errno.desc().into()),
-                },
-                nix::Error::InvalidPath => Error {
-                    source: io::Error::new(io::ErrorKind::InvalidInput, e),
-                    description: Some("Invalid path".into()),
-                },
-                nix::Error::InvalidUtf8 => Error {
-                    source: io::Error::new(io::ErrorKind::InvalidData, e),
-                    description: Some("Invalid UTF-8 string".into()),
-                },
-                nix::Error::UnsupportedOperation => Error {
-                    source: io::Error::new(io::ErrorKind::Other, e),
-                    description: Some("Unsupported operation".into()),
-                },
+                nix::Error::Sys(errno) => Error::new(io::Error::from_raw_os_error(errno as i32), errno.desc()),
+                nix::Error::InvalidPath => Error::new(io::Error::new(io::ErrorKind::InvalidInput, e), "Invalid path"),
+                nix::Error::InvalidUtf8 => {
+                    Error::new(io::Error::new(io::ErrorKind::InvalidData, e), "Invalid UTF-8 string")
+                }
+                nix::Error::UnsupportedOperation => Error::with_kind(
+                    ErrorKind::Unsupported,
+                    io::Error::new(io::ErrorKind::Other, e),
+                    "Unsupported operation",
+                ),
             }
         }
     }
@@ -0,0 +1,534 @@
+//! On-disk time series of the batteries state.
+//!
+//! [History] appends timestamped [samples](struct.Sample.html) into the directory
+//! per each [BatteryId](../struct.BatteryId.html), so the data recorded with the different
+//! devices (or the same device in the different slots) is never mixed.
+//! Batteries which report neither vendor, model nor serial number can't be told apart,
+//! so they are rejected with the [ErrorKind::Unsupported](../enum.ErrorKind.html#variant.Unsupported) error.
+//! Each battery directory is split into the segment files covering a fixed time period
+//! (one week by default); segments which are older than the retention period (two years by default)
+//! are removed when the new segment is started.
+//!
+//! Recorded samples can be loaded back for any time range with [History::query]
+//! and reduced with the [downsample] function, for example, to draw a graph.
+//!
+//! This module and the [wear](../wear/index.html), [cycles](../cycles/index.html),
+//! [baseline](../baseline/index.html), [sessions](../sessions/index.html) and [suspend](../suspend/index.html)
+//! modules built on top of it require the `history` feature.
+//!
+//! # Example
+//!
+//! ```edition2018,no_run
+//! # use std::thread;
+//! # use std::time::{Duration, SystemTime};
+//! # use battery::Result;
+//! use battery::history::{self, History};
+//!
+//! # fn main() -> Result<()> {
+//! let history = History::open("/var/lib/battery/history")?;
+//! let manager = battery::Manager::new()?;
+//! let mut battery = manager.batteries()?.next().unwrap()?;
+//! for _ in 0..60 {
+//!     manager.refresh(&mut battery)?;
+//!     history.record_battery(&battery)?;
+//!     thread::sleep(Duration::from_secs(60));
+//! }
+//!
+//! let day_ago = SystemTime::now() - Duration::from_secs(24 * 60 * 60);
+//! let samples = history.query(&battery.id(), day_ago..)?;
+//! for sample in history::downsample(&samples, Duration::from_secs(15 * 60)) {
+//!     println!("{:?} {:?}", sample.time(), sample.state_of_charge());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Storage
+//!
+//! Root directory holds one subdirectory per battery, named after its identity (for example,
+//! `SMP-5B10W13930-1234`) and containing the `id` file with the full identity; if two identities
+//! are formatted into the same name, the numeric suffix is added to the later one.
+//! Battery directory holds the history segments along with the files of the other per-battery stores:
+//! `wear` for the [WearTracker](../wear/struct.WearTracker.html), `cycles` for the
+//! [CycleCounter](../cycles/struct.CycleCounter.html), `baseline` for the [Baselines](../baseline/struct.Baselines.html)
+//! and `suspend` for the [SuspendTracker](../suspend/struct.SuspendTracker.html),
+//! so all of them can share one root directory.
+//!
+//! # Format
+//!
+//! Segments are text files named after their start time as `<unix seconds>.history`,
+//! with one sample per line:
+//!
+//! ```text
+//! # battery history v1
+//! 1700000000000 discharging 0.82 148536 180360 8.12 12.1 303.65 120 - 18292
+//! ```
+//!
+//! Values are the time in milliseconds since the Unix epoch, state, state of charge ratio,
+//! energy and full energy in joules, energy rate in watts, voltage in volts, temperature in kelvins,
+//! cycle count, time to full and time to empty in seconds. Values which were not available are written as `-`.
+//!
+//! [upower] submodule converts samples from and to the UPower history files.
+//!
+//! [History]: struct.History.html
+//! [History::query]: struct.History.html#method.query
+//! [downsample]: fn.downsample.html
+//! [upower]: upower/index.html
+
+use std::fmt;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::ops::{Bound, RangeBounds};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::units::electric_potential::volt;
+use crate::units::energy::joule;
+use crate::units::power::watt;
+use crate::units::ratio::ratio;
+use crate::units::thermodynamic_temperature::kelvin;
+use crate::units::time::second;
+use crate::units::{ElectricPotential, Energy, Power, Ratio, ThermodynamicTemperature, Time};
+use crate::{Battery, BatteryId, Error, Result, State};
+
+pub(crate) mod store;
+#[cfg(test)]
+pub(crate) mod tests;
+pub mod upower;
+
+use self::store::Store;
+
+#[allow(clippy::redundant_static_lifetimes)]
+static HEADER: &'static str = "# battery history v1";
+#[allow(clippy::redundant_static_lifetimes)]
+static EXTENSION: &'static str = "history";
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// Battery state at some moment.
+///
+/// All values except the time and state are optional,
+/// as the device or the imported data might not provide them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    time: SystemTime,
+    state: State,
+    state_of_charge: Option<Ratio>,
+    energy: Option<Energy>,
+    energy_full: Option<Energy>,
+    energy_rate: Option<Power>,
+    voltage: Option<ElectricPotential>,
+    temperature: Option<ThermodynamicTemperature>,
+    cycle_count: Option<u32>,
+    time_to_full: Option<Time>,
+    time_to_empty: Option<Time>,
+}
+
+impl Sample {
+    /// Creates sample without any values.
+    pub fn new(time: SystemTime, state: State) -> Sample {
+        Sample {
+            time,
+            state,
+            state_of_charge: None,
+            energy: None,
+            energy_full: None,
+            energy_rate: None,
+            voltage: None,
+            temperature: None,
+            cycle_count: None,
+            time_to_full: None,
+            time_to_empty: None,
+        }
+    }
+
+    /// Creates sample from the available `battery` [readings](../struct.Battery.html#method.readings).
+    pub fn from_battery(battery: &Battery, time: SystemTime) -> Sample {
+        let readings = battery.readings();
+        Sample {
+            time,
+            state: battery.state(),
+            state_of_charge: readings.state_of_charge().value(),
+            energy: readings.energy().value(),
+            energy_full: readings.energy_full().value(),
+            energy_rate: readings.energy_rate().value(),
+            voltage: readings.voltage().value(),
+            temperature: readings.temperature().value(),
+            cycle_count: readings.cycle_count().value(),
+            time_to_full: readings.time_to_full().value(),
+            time_to_empty: readings.time_to_empty().value(),
+        }
+    }
+
+    pub fn with_state_of_charge(mut self, value: Ratio) -> Sample {
+        self.state_of_charge = Some(value);
+        self
+    }
+
+    pub fn with_energy(mut self, value: Energy) -> Sample {
+        self.energy = Some(value);
+        self
+    }
+
+    pub fn with_energy_full(mut self, value: Energy) -> Sample {
+        self.energy_full = Some(value);
+        self
+    }
+
+    pub fn with_energy_rate(mut self, value: Power) -> Sample {
+        self.energy_rate = Some(value);
+        self
+    }
+
+    pub fn with_voltage(mut self, value: ElectricPotential) -> Sample {
+        self.voltage = Some(value);
+        self
+    }
+
+    pub fn with_temperature(mut self, value: ThermodynamicTemperature) -> Sample {
+        self.temperature = Some(value);
+        self
+    }
+
+    pub fn with_cycle_count(mut self, value: u32) -> Sample {
+        self.cycle_count = Some(value);
+        self
+    }
+
+    pub fn with_time_to_full(mut self, value: Time) -> Sample {
+        self.time_to_full = Some(value);
+        self
+    }
+
+    pub fn with_time_to_empty(mut self, value: Time) -> Sample {
+        self.time_to_empty = Some(value);
+        self
+    }
+
+    pub fn time(&self) -> SystemTime {
+        self.time
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn state_of_charge(&self) -> Option<Ratio> {
+        self.state_of_charge
+    }
+
+    pub fn energy(&self) -> Option<Energy> {
+        self.energy
+    }
+
+    pub fn energy_full(&self) -> Option<Energy> {
+        self.energy_full
+    }
+
+    pub fn energy_rate(&self) -> Option<Power> {
+        self.energy_rate
+    }
+
+    pub fn voltage(&self) -> Option<ElectricPotential> {
+        self.voltage
+    }
+
+    pub fn temperature(&self) -> Option<ThermodynamicTemperature> {
+        self.temperature
+    }
+
+    pub fn cycle_count(&self) -> Option<u32> {
+        self.cycle_count
+    }
+
+    pub fn time_to_full(&self) -> Option<Time> {
+        self.time_to_full
+    }
+
+    pub fn time_to_empty(&self) -> Option<Time> {
+        self.time_to_empty
+    }
+}
+
+impl fmt::Display for Sample {
+    /// Formats sample as the history file line.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn value<T: fmt::Display>(f: &mut fmt::Formatter, value: Option<T>) -> fmt::Result {
+            match value {
+                Some(value) => write!(f, " {}", value),
+                None => f.write_str(" -"),
+            }
+        }
+
+        write!(f, "{} {}", unix_millis(self.time), self.state)?;
+        value(f, self.state_of_charge.map(|value| value.get::<ratio>()))?;
+        value(f, self.energy.map(|value| value.get::<joule>()))?;
+        value(f, self.energy_full.map(|value| value.get::<joule>()))?;
+        value(f, self.energy_rate.map(|value| value.get::<watt>()))?;
+        value(f, self.voltage.map(|value| value.get::<volt>()))?;
+        value(f, self.temperature.map(|value| value.get::<kelvin>()))?;
+        value(f, self.cycle_count)?;
+        value(f, self.time_to_full.map(|value| value.get::<second>()))?;
+        value(f, self.time_to_empty.map(|value| value.get::<second>()))
+    }
+}
+
+impl FromStr for Sample {
+    type Err = Error;
+
+    /// Parses the history file line.
+    fn from_str(s: &str) -> Result<Sample> {
+        fn value<T: FromStr>(part: Option<&str>) -> Option<Option<T>> {
+            match part? {
+                "-" => Some(None),
+                part => part.parse().ok().map(Some),
+            }
+        }
+
+        let invalid = || Error::invalid_data("Malformed history sample").with_raw_value(s);
+        let mut parts = s.split_whitespace();
+        let time = parts.next().and_then(|part| part.parse::<u64>().ok()).ok_or_else(invalid)?;
+        let state = parts.next().and_then(|part| part.parse::<State>().ok()).ok_or_else(invalid)?;
+        let state_of_charge = value::<f32>(parts.next()).ok_or_else(invalid)?;
+        let energy = value::<f32>(parts.next()).ok_or_else(invalid)?;
+        let energy_full = value::<f32>(parts.next()).ok_or_else(invalid)?;
+        let energy_rate = value::<f32>(parts.next()).ok_or_else(invalid)?;
+        let voltage = value::<f32>(parts.next()).ok_or_else(invalid)?;
+        let temperature = value::<f32>(parts.next()).ok_or_else(invalid)?;
+        let cycle_count = value::<u32>(parts.next()).ok_or_else(invalid)?;
+        // Time columns are optional, as they were not written by the early versions
+        let time_to_full = match parts.next() {
+            None => None,
+            part => value::<f32>(part).ok_or_else(invalid)?,
+        };
+        let time_to_empty = match parts.next() {
+            None => None,
+            part => value::<f32>(part).ok_or_else(invalid)?,
+        };
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+
+        Ok(Sample {
+            time: UNIX_EPOCH + Duration::from_millis(time),
+            state,
+            state_of_charge: state_of_charge.map(Ratio::new::<ratio>),
+            energy: energy.map(Energy::new::<joule>),
+            energy_full: energy_full.map(Energy::new::<joule>),
+            energy_rate: energy_rate.map(Power::new::<watt>),
+            voltage: voltage.map(ElectricPotential::new::<volt>),
+            temperature: temperature.map(ThermodynamicTemperature::new::<kelvin>),
+            cycle_count,
+            time_to_full: time_to_full.map(Time::new::<second>),
+            time_to_empty: time_to_empty.map(Time::new::<second>),
+        })
+    }
+}
+
+/// Rotating on-disk store of the battery samples.
+///
+/// See the [module documentation](index.html) for details.
+#[derive(Debug, Clone)]
+pub struct History {
+    store: Store,
+    segment: Duration,
+    retention: Duration,
+}
+
+impl History {
+    /// Opens history stored in the `root` directory, creating it if necessary.
+    pub fn open<T: Into<PathBuf>>(root: T) -> Result<History> {
+        Ok(History {
+            store: Store::open(root.into())?,
+            segment: Duration::from_secs(7 * DAY),
+            retention: Duration::from_secs(2 * 365 * DAY),
+        })
+    }
+
+    /// Sets the time period covered by one segment file, rounded to seconds.
+    pub fn with_segment_duration(mut self, duration: Duration) -> History {
+        self.segment = Duration::from_secs(duration.as_secs().max(1));
+        self
+    }
+
+    /// Sets how long the samples are kept.
+    pub fn with_retention(mut self, retention: Duration) -> History {
+        self.retention = retention;
+        self
+    }
+
+    /// History directory.
+    pub fn root(&self) -> &Path {
+        self.store.root()
+    }
+
+    /// Appends the `battery` sample taken right now.
+    pub fn record_battery(&self, battery: &Battery) -> Result<()> {
+        self.record(&battery.id(), &Sample::from_battery(battery, SystemTime::now()))
+    }
+
+    /// Appends `sample` to the battery `id` history.
+    ///
+    /// Segments which are older than the retention period are removed,
+    /// when the sample starts the new segment.
+    pub fn record(&self, id: &BatteryId, sample: &Sample) -> Result<()> {
+        let seconds = unix_seconds(sample.time());
+        let start = seconds - seconds % self.segment.as_secs();
+        let path = self.store.file(id, &format!("{}.{}", start, EXTENSION))?;
+
+        if store::append_record(&path, HEADER, &sample.to_string())? {
+            self.prune(id, sample.time())?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns identities of all batteries with the recorded history.
+    pub fn ids(&self) -> Result<Vec<BatteryId>> {
+        self.store.ids()
+    }
+
+    /// Loads the battery `id` samples recorded in the time `range`, ordered by time.
+    pub fn query<R: RangeBounds<SystemTime>>(&self, id: &BatteryId, range: R) -> Result<Vec<Sample>> {
+        let segments = self.segments(id)?;
+        let mut samples = Vec::new();
+        for (idx, (start, path)) in segments.iter().enumerate() {
+            let end = segments.get(idx + 1).map(|(start, _)| *start);
+            let before = match range.end_bound() {
+                Bound::Included(time) => *start > *time,
+                Bound::Excluded(time) => *start >= *time,
+                Bound::Unbounded => false,
+            };
+            let after = match (range.start_bound(), end) {
+                (Bound::Included(time), Some(end)) | (Bound::Excluded(time), Some(end)) => end <= *time,
+                _ => false,
+            };
+            if before || after {
+                continue;
+            }
+
+            let file = fs::File::open(path).map_err(|e| Error::from(e).with_attribute(path))?;
+            for line in BufReader::new(file).lines() {
+                let line = line.map_err(|e| Error::from(e).with_attribute(path))?;
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let sample = line.parse::<Sample>().map_err(|e| e.with_attribute(path))?;
+                if range.contains(&sample.time()) {
+                    samples.push(sample);
+                }
+            }
+        }
+        samples.sort_by_key(Sample::time);
+
+        Ok(samples)
+    }
+
+    /// Removes the battery `id` segments containing only samples older than the retention period.
+    pub fn prune(&self, id: &BatteryId, now: SystemTime) -> Result<()> {
+        let threshold = match now.checked_sub(self.retention) {
+            Some(threshold) => threshold,
+            None => return Ok(()),
+        };
+        let segments = self.segments(id)?;
+        for pair in segments.windows(2) {
+            let ((_, path), (next_start, _)) = (&pair[0], &pair[1]);
+            if *next_start <= threshold {
+                fs::remove_file(path).map_err(|e| Error::from(e).with_attribute(path))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns battery segments with their start time, ordered by it.
+    fn segments(&self, id: &BatteryId) -> Result<Vec<(SystemTime, PathBuf)>> {
+        let dir = match self.store.find(id)? {
+            Some(dir) => dir,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut segments = Vec::new();
+        for entry in fs::read_dir(&dir).map_err(|e| Error::from(e).with_attribute(&dir))? {
+            let path = entry?.path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some(EXTENSION) {
+                continue;
+            }
+            let start = path.file_stem().and_then(|stem| stem.to_str()).and_then(|stem| stem.parse::<u64>().ok());
+            if let Some(start) = start {
+                segments.push((UNIX_EPOCH + Duration::from_secs(start), path));
+            }
+        }
+        segments.sort();
+
+        Ok(segments)
+    }
+}
+
+/// Reduces time-ordered `samples` to one sample per `interval`.
+///
+/// Samples are grouped into the intervals aligned to the Unix epoch; each group is replaced
+/// with the sample at the interval start, which has the average values of the group,
+/// the last state and the last cycle count.
+pub fn downsample(samples: &[Sample], interval: Duration) -> Vec<Sample> {
+    let interval = interval.as_millis().max(1) as u64;
+    let mut result = Vec::new();
+    let mut start = 0;
+    while start < samples.len() {
+        let bucket = unix_millis(samples[start].time()) / interval;
+        let end = samples[start..]
+            .iter()
+            .position(|sample| unix_millis(sample.time()) / interval != bucket)
+            .map_or(samples.len(), |position| start + position);
+        let group = &samples[start..end];
+
+        let last = &group[group.len() - 1];
+        result.push(Sample {
+            time: UNIX_EPOCH + Duration::from_millis(bucket * interval),
+            state: last.state,
+            state_of_charge: mean(group, Sample::state_of_charge, |value| value.get::<ratio>())
+                .map(Ratio::new::<ratio>),
+            energy: mean(group, Sample::energy, |value| value.get::<joule>()).map(Energy::new::<joule>),
+            energy_full: mean(group, Sample::energy_full, |value| value.get::<joule>()).map(Energy::new::<joule>),
+            energy_rate: mean(group, Sample::energy_rate, |value| value.get::<watt>()).map(Power::new::<watt>),
+            voltage: mean(group, Sample::voltage, |value| value.get::<volt>()).map(ElectricPotential::new::<volt>),
+            temperature: mean(group, Sample::temperature, |value| value.get::<kelvin>())
+                .map(ThermodynamicTemperature::new::<kelvin>),
+            cycle_count: group.iter().rev().find_map(Sample::cycle_count),
+            time_to_full: mean(group, Sample::time_to_full, |value| value.get::<second>()).map(Time::new::<second>),
+            time_to_empty: mean(group, Sample::time_to_empty, |value| value.get::<second>()).map(Time::new::<second>),
+        });
+        start = end;
+    }
+
+    result
+}
+
+/// Average of the `value` in the samples `group`, converted into `f32` with `get`.
+fn mean<T, V, G>(group: &[Sample], value: V, get: G) -> Option<f32>
+where
+    V: Fn(&Sample) -> Option<T>,
+    G: Fn(T) -> f32,
+{
+    let values = group.iter().filter_map(value).map(get).collect::<Vec<_>>();
+    match values.len() {
+        0 => None,
+        len => Some(values.iter().sum::<f32>() / len as f32),
+    }
+}
+
+/// Duration in seconds, including the fractional part.
+pub(crate) fn seconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1e9
+}
+
+/// Whole seconds since the Unix epoch, zero for the earlier times.
+pub(crate) fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or(0)
+}
+
+/// Milliseconds since the Unix epoch, zero for the earlier times.
+pub(crate) fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|duration| duration.as_millis() as u64).unwrap_or(0)
+}
@@ -0,0 +1,276 @@
+//! Per-battery directories of the persistent stores.
+//!
+//! Each battery gets its own directory, named after its [BatteryId](../../struct.BatteryId.html)
+//! and holding the `id` file with the full identity. Identities which are formatted into the same
+//! directory name are checked against the `id` file, so the different batteries never share a directory.
+//! Identities without vendor, model and serial number are rejected, as all such batteries would share one.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::{BatteryId, Error, Result};
+
+#[allow(clippy::redundant_static_lifetimes)]
+static ID_FILE: &'static str = "id";
+
+/// Directory with the per-battery subdirectories.
+#[derive(Debug, Clone)]
+pub(crate) struct Store {
+    root: PathBuf,
+}
+
+impl Store {
+    /// Opens store in the `root` directory, creating it if necessary.
+    pub fn open(root: PathBuf) -> Result<Store> {
+        fs::create_dir_all(&root).map_err(|e| Error::from(e).with_attribute(&root))?;
+
+        Ok(Store {
+            root,
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns identities of all batteries in the store.
+    pub fn ids(&self) -> Result<Vec<BatteryId>> {
+        let mut ids = Vec::new();
+        for entry in fs::read_dir(&self.root).map_err(|e| Error::from(e).with_attribute(&self.root))? {
+            if let Some(id) = read_id(&entry?.path())? {
+                ids.push(id);
+            }
+        }
+        ids.sort();
+
+        Ok(ids)
+    }
+
+    /// Returns the battery `id` directory, `None` if nothing was stored for it yet.
+    pub fn find(&self, id: &BatteryId) -> Result<Option<PathBuf>> {
+        let expected = id_content(id);
+        if expected.is_empty() {
+            return Err(Error::unsupported("Battery without vendor, model and serial number can't be stored"));
+        }
+        for entry in fs::read_dir(&self.root).map_err(|e| Error::from(e).with_attribute(&self.root))? {
+            let dir = entry?.path();
+            let path = dir.join(ID_FILE);
+            if !path.is_file() {
+                continue;
+            }
+            let content = fs::read_to_string(&path).map_err(|e| Error::from(e).with_attribute(&path))?;
+            if content == expected {
+                return Ok(Some(dir));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the battery `id` file `name` path, `None` if nothing was stored for the battery yet.
+    pub fn find_file(&self, id: &BatteryId, name: &str) -> Result<Option<PathBuf>> {
+        Ok(self.find(id)?.map(|dir| dir.join(name)))
+    }
+
+    /// Returns the battery `id` directory, creating it if necessary.
+    ///
+    /// Directory is named after the `id`, with a numeric suffix added
+    /// if the name is already taken by another battery.
+    pub fn dir(&self, id: &BatteryId) -> Result<PathBuf> {
+        if let Some(dir) = self.find(id)? {
+            return Ok(dir);
+        }
+
+        let name = id.to_string();
+        let mut suffix = 0;
+        loop {
+            let dir = match suffix {
+                0 => self.root.join(&name),
+                suffix => self.root.join(format!("{}-{}", name, suffix)),
+            };
+            if !dir.exists() {
+                fs::create_dir_all(&dir).map_err(|e| Error::from(e).with_attribute(&dir))?;
+                write_atomic(&dir.join(ID_FILE), &id_content(id))?;
+                return Ok(dir);
+            }
+            suffix += 1;
+        }
+    }
+
+    /// Returns the battery `id` file `name` path, creating the battery directory if necessary.
+    pub fn file(&self, id: &BatteryId, name: &str) -> Result<PathBuf> {
+        Ok(self.dir(id)?.join(name))
+    }
+}
+
+/// Reads the `path` records, which are the lines other than empty ones and comments, parsed with `parse`.
+///
+/// Missing file has no records; `kind` names the records in the error for the malformed line.
+pub(crate) fn read_records<T, F>(path: &Path, kind: &str, mut parse: F) -> Result<Vec<T>>
+where
+    F: FnMut(&str) -> Option<T>,
+{
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(path).map_err(|e| Error::from(e).with_attribute(path))?;
+    let mut records = Vec::new();
+    for (idx, line) in content.lines().enumerate() {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let record = parse(line).ok_or_else(|| {
+            Error::invalid_data(format!("Malformed {} line {}", kind, idx + 1))
+                .with_attribute(path)
+                .with_raw_value(line)
+        })?;
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+/// Appends the `record` line to the `path`, starting the new file with the `header` line.
+///
+/// Returns `true` if the file was created.
+pub(crate) fn append_record(path: &Path, header: &str, record: &str) -> Result<bool> {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| Error::from(e).with_attribute(path))?;
+    let is_new = file.metadata().map_err(|e| Error::from(e).with_attribute(path))?.len() == 0;
+    if is_new {
+        writeln!(file, "{}", header).map_err(|e| Error::from(e).with_attribute(path))?;
+    }
+    writeln!(file, "{}", record).map_err(|e| Error::from(e).with_attribute(path))?;
+
+    Ok(is_new)
+}
+
+/// Replaces the `path` content with the `header` line followed by the `records` lines.
+pub(crate) fn write_records<I>(path: &Path, header: &str, records: I) -> Result<()>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut content = format!("{}\n", header);
+    for record in records {
+        content.push_str(&record);
+        content.push('\n');
+    }
+
+    write_atomic(path, &content)
+}
+
+/// Writes `content` into the temporary file first and renames it to the `path`,
+/// so the file is never left partially written.
+pub(crate) fn write_atomic(path: &Path, content: &str) -> Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+
+    fs::write(&temporary, content).map_err(|e| Error::from(e).with_attribute(path))?;
+    fs::rename(&temporary, path).map_err(|e| Error::from(e).with_attribute(path))?;
+
+    Ok(())
+}
+
+/// Formats `id` for the `id` file, one `key=value` line per known part.
+///
+/// Blank parts are skipped as the unknown ones, so the content is empty if the whole identity is unknown.
+fn id_content(id: &BatteryId) -> String {
+    let mut content = String::new();
+    let parts = [("vendor", id.vendor()), ("model", id.model()), ("serial_number", id.serial_number())];
+    for (key, value) in parts.iter() {
+        if let Some(value) = value.filter(|value| !value.trim().is_empty()) {
+            content.push_str(&format!("{}={}\n", key, value.replace('\n', " ")));
+        }
+    }
+
+    content
+}
+
+/// Reads identity from the `dir` directory `id` file, `None` if there is no such file.
+fn read_id(dir: &Path) -> Result<Option<BatteryId>> {
+    let path = dir.join(ID_FILE);
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(&path).map_err(|e| Error::from(e).with_attribute(&path))?;
+    let value = |key: &str| {
+        content
+            .lines()
+            .map(|line| line.splitn(2, '='))
+            .filter_map(|mut parts| Some((parts.next()?, parts.next()?)))
+            .find(|(name, _)| *name == key)
+            .map(|(_, value)| value.to_string())
+    };
+
+    Ok(Some(BatteryId::new(value("vendor"), value("model"), value("serial_number"))))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{append_record, read_records, write_records, Store};
+    use crate::{BatteryId, ErrorKind};
+
+    #[test]
+    fn test_same_formatted_ids() {
+        let root = tempfile::tempdir().unwrap();
+        let store = Store::open(root.path().to_path_buf()).unwrap();
+        // Both are formatted as `SMP-5B10W13930-12_34`
+        let first = BatteryId::new(Some("SMP"), Some("5B10W13930"), Some("12/34"));
+        let second = BatteryId::new(Some("SMP"), Some("5B10W13930"), Some("12 34"));
+
+        assert_eq!(store.find(&first).unwrap(), None);
+        let first_dir = store.dir(&first).unwrap();
+        let second_dir = store.dir(&second).unwrap();
+        assert_ne!(first_dir, second_dir);
+        assert_eq!(first_dir, root.path().join("SMP-5B10W13930-12_34"));
+        assert_eq!(second_dir, root.path().join("SMP-5B10W13930-12_34-1"));
+
+        let store = Store::open(root.path().to_path_buf()).unwrap();
+        assert_eq!(store.find(&first).unwrap(), Some(first_dir));
+        assert_eq!(store.dir(&second).unwrap(), second_dir);
+        assert_eq!(store.ids().unwrap(), vec![second, first]);
+    }
+
+    #[test]
+    fn test_unknown_id() {
+        let root = tempfile::tempdir().unwrap();
+        let store = Store::open(root.path().to_path_buf()).unwrap();
+        let unknown = BatteryId::new(None, Some(" "), None);
+
+        assert_eq!(store.find(&unknown).unwrap_err().kind(), ErrorKind::Unsupported);
+        assert_eq!(store.dir(&unknown).unwrap_err().kind(), ErrorKind::Unsupported);
+        assert_eq!(fs::read_dir(root.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_records() {
+        let root = tempfile::tempdir().unwrap();
+        let path = root.path().join("records");
+        let parse = |line: &str| line.parse::<u32>().ok();
+
+        assert!(read_records(&path, "test", parse).unwrap().is_empty());
+        assert!(append_record(&path, "# test v1", "1").unwrap());
+        assert!(!append_record(&path, "# test v1", "2").unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# test v1\n1\n2\n");
+        assert_eq!(read_records(&path, "test", parse).unwrap(), vec![1, 2]);
+
+        write_records(&path, "# test v1", vec!["3".to_string()]).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# test v1\n3\n");
+        assert!(!root.path().join("records.tmp").exists());
+
+        fs::write(&path, "# test v1\n3\nthree\n").unwrap();
+        let e = read_records(&path, "test", parse).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidValue);
+        assert_eq!(e.attribute(), Some(path.as_path()));
+        assert_eq!(e.raw_value(), Some("three"));
+        assert!(e.to_string().contains("Malformed test line 3"));
+    }
+}
@@ -0,0 +1,110 @@
+use std::fs;
+use std::time::{Duration, UNIX_EPOCH};
+
+use approx::assert_abs_diff_eq;
+
+use super::{downsample, History, Sample};
+use crate::units::energy::watt_hour;
+use crate::units::power::watt;
+use crate::units::ratio::percent;
+use crate::units::{Energy, Power, Ratio};
+use crate::{BatteryId, State};
+
+const HOUR: u64 = 60 * 60;
+
+/// Sample of the 50 Wh battery charged for `charge` percents, taken `seconds` after the Unix epoch.
+///
+/// Shared with the tests of the other modules working with samples.
+pub(crate) fn sample(seconds: u64, state: State, charge: f32) -> Sample {
+    Sample::new(UNIX_EPOCH + Duration::from_secs(seconds), state)
+        .with_state_of_charge(Ratio::new::<percent>(charge))
+        .with_energy(Energy::new::<watt_hour>(charge / 2.0))
+}
+
+/// Identity of the battery used by the tests.
+pub(crate) fn id() -> BatteryId {
+    BatteryId::new(Some("SMP"), Some("5B10W13930"), Some("1234"))
+}
+
+#[test]
+fn test_sample_line() {
+    let sample = Sample::new(UNIX_EPOCH + Duration::from_millis(1_700_000_000_500), State::Discharging)
+        .with_state_of_charge(Ratio::new::<percent>(82.0))
+        .with_energy_rate(Power::new::<watt>(8.5))
+        .with_cycle_count(120);
+    let line = sample.to_string();
+    assert_eq!(line, "1700000000500 discharging 0.82 - - 8.5 - - 120 - -");
+    assert_eq!(line.parse::<Sample>().unwrap(), sample);
+    // Time columns are optional
+    assert_eq!("1700000000500 discharging 0.82 - - 8.5 - - 120".parse::<Sample>().unwrap(), sample);
+
+    assert!("1700000000500 discharging 0.82".parse::<Sample>().is_err());
+    assert!("1700000000500 sleeping 0.82 - - 8.5 - - 120".parse::<Sample>().is_err());
+}
+
+#[test]
+fn test_record_and_query() {
+    let root = tempfile::tempdir().unwrap();
+    let history = History::open(root.path()).unwrap().with_segment_duration(Duration::from_secs(HOUR));
+    let other = BatteryId::new(None, Some("Other"), None);
+
+    for minute in 0..180 {
+        history.record(&id(), &sample(minute * 60, State::Discharging, 100.0 - minute as f32 / 2.0)).unwrap();
+    }
+    history.record(&other, &sample(0, State::Charging, 50.0)).unwrap();
+
+    assert_eq!(history.ids().unwrap(), vec![other.clone(), id()]);
+    let segments = fs::read_dir(root.path().join(id().to_string())).unwrap().count();
+    assert_eq!(segments, 4);
+
+    let all = history.query(&id(), ..).unwrap();
+    assert_eq!(all.len(), 180);
+    let range = UNIX_EPOCH + Duration::from_secs(HOUR - 60)..UNIX_EPOCH + Duration::from_secs(2 * HOUR);
+    let samples = history.query(&id(), range).unwrap();
+    assert_eq!(samples.len(), 61);
+    assert_eq!(samples[0], all[59]);
+    assert_eq!(history.query(&other, ..).unwrap().len(), 1);
+    assert!(history.query(&BatteryId::new(None, None, Some("missing")), ..).unwrap().is_empty());
+}
+
+#[test]
+fn test_retention() {
+    let root = tempfile::tempdir().unwrap();
+    let history = History::open(root.path())
+        .unwrap()
+        .with_segment_duration(Duration::from_secs(HOUR))
+        .with_retention(Duration::from_secs(2 * HOUR));
+
+    for hour in 0..5 {
+        history.record(&id(), &sample(hour * HOUR + 10, State::Discharging, 50.0)).unwrap();
+    }
+
+    // Segment starting at 2h still contains samples newer than 4h 10s - 2h
+    let samples = history.query(&id(), ..).unwrap();
+    let times = samples.iter().map(|sample| sample.time()).collect::<Vec<_>>();
+    assert_eq!(times, vec![
+        UNIX_EPOCH + Duration::from_secs(2 * HOUR + 10),
+        UNIX_EPOCH + Duration::from_secs(3 * HOUR + 10),
+        UNIX_EPOCH + Duration::from_secs(4 * HOUR + 10),
+    ]);
+}
+
+#[test]
+fn test_downsample() {
+    let samples = (0..10)
+        .map(|minute| {
+            let state = if minute < 7 { State::Discharging } else { State::Charging };
+            sample(minute * 60, state, 50.0 + minute as f32)
+        })
+        .collect::<Vec<_>>();
+
+    let reduced = downsample(&samples, Duration::from_secs(5 * 60));
+    assert_eq!(reduced.len(), 2);
+    assert_eq!(reduced[0].time(), UNIX_EPOCH);
+    assert_eq!(reduced[0].state(), State::Discharging);
+    assert_abs_diff_eq!(reduced[0].state_of_charge().unwrap().get::<percent>(), 52.0, epsilon = 0.001);
+    assert_eq!(reduced[1].time(), UNIX_EPOCH + Duration::from_secs(5 * 60));
+    assert_eq!(reduced[1].state(), State::Charging);
+    assert_abs_diff_eq!(reduced[1].energy().unwrap().get::<watt_hour>(), 28.5, epsilon = 0.001);
+    assert_eq!(reduced[1].energy_rate(), None);
+}
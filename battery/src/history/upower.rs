@@ -0,0 +1,399 @@
+//! UPower history files.
+//!
+//! UPower daemon keeps the battery history in the `/var/lib/upower` directory,
+//! in the `history-{charge,rate,time-empty,time-full}-<id>.dat` files, where `<id>`
+//! is built from the battery model, design energy in watt-hours and serial number,
+//! for example, `history-charge-5B10W13930-57-1234.dat`. Each line holds the time in seconds
+//! since the Unix epoch, value (percents, watts or seconds) and the UPower state name, separated with tabs.
+//!
+//! [UpowerHistory] loads these files as the [samples](../struct.Sample.html), merging values recorded
+//! at the same moment, and writes samples back in the same format, so the data can be exchanged
+//! with the GNOME power statistics tool.
+//!
+//! Vendor is not a part of the UPower identity, so batteries are matched by the model and serial number only.
+//!
+//! # Example
+//!
+//! ```edition2018,no_run
+//! # use battery::Result;
+//! use battery::history::History;
+//! use battery::history::upower::UpowerHistory;
+//!
+//! # fn main() -> Result<()> {
+//! let upower = UpowerHistory::system();
+//! let history = History::open("/var/lib/battery/history")?;
+//! for battery in battery::Manager::new()?.batteries()? {
+//!     let id = battery?.id();
+//!     for sample in upower.load_battery(&id)? {
+//!         history.record(&id, &sample)?;
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [UpowerHistory]: struct.UpowerHistory.html
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use super::{store, Sample};
+use crate::units::energy::watt_hour;
+use crate::units::power::watt;
+use crate::units::ratio::percent;
+use crate::units::time::second;
+use crate::units::{Energy, Power, Ratio, Time};
+use crate::{Battery, BatteryId, Error, Result, State};
+
+#[allow(clippy::redundant_static_lifetimes)]
+static SYSTEM_DIR: &'static str = "/var/lib/upower";
+
+/// Kinds of the UPower history files.
+static KINDS: &[Kind] = &[Kind::Charge, Kind::Rate, Kind::TimeFull, Kind::TimeEmpty];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Kind {
+    Charge,
+    Rate,
+    TimeFull,
+    TimeEmpty,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Charge => "charge",
+            Kind::Rate => "rate",
+            Kind::TimeFull => "time-full",
+            Kind::TimeEmpty => "time-empty",
+        }
+    }
+
+    fn get(self, sample: &Sample) -> Option<f32> {
+        match self {
+            Kind::Charge => sample.state_of_charge().map(|value| value.get::<percent>()),
+            Kind::Rate => sample.energy_rate().map(|value| value.get::<watt>()),
+            Kind::TimeFull => sample.time_to_full().map(|value| value.get::<second>()),
+            Kind::TimeEmpty => sample.time_to_empty().map(|value| value.get::<second>()),
+        }
+    }
+
+    fn set(self, sample: Sample, value: f32) -> Sample {
+        match self {
+            Kind::Charge => sample.with_state_of_charge(Ratio::new::<percent>(value)),
+            Kind::Rate => sample.with_energy_rate(Power::new::<watt>(value)),
+            Kind::TimeFull => sample.with_time_to_full(Time::new::<second>(value)),
+            Kind::TimeEmpty => sample.with_time_to_empty(Time::new::<second>(value)),
+        }
+    }
+}
+
+/// Builds the UPower device identity.
+///
+/// Returns `None` if both model and serial number are unknown, as UPower does not keep history in that case.
+pub fn device_id(
+    model: Option<&str>,
+    energy_full_design: Option<Energy>,
+    serial_number: Option<&str>,
+) -> Option<String> {
+    if model.is_none() && serial_number.is_none() {
+        return None;
+    }
+
+    let capacity = energy_full_design
+        .map(|value| value.get::<watt_hour>() as u32)
+        .filter(|value| *value > 0)
+        .map(|value| value.to_string());
+    let parts = [model.map(ToString::to_string), capacity, serial_number.map(ToString::to_string)];
+    let id = parts.iter().filter_map(|part| part.as_ref()).cloned().collect::<Vec<_>>().join("-");
+
+    Some(escape(&id))
+}
+
+/// Builds the UPower device identity for the `battery`.
+pub fn battery_device_id(battery: &Battery) -> Option<String> {
+    let energy_full_design = battery.readings().energy_full_design().value();
+    device_id(battery.model(), energy_full_design, battery.serial_number())
+}
+
+/// UPower history files directory.
+#[derive(Debug, Clone)]
+pub struct UpowerHistory {
+    dir: PathBuf,
+}
+
+impl UpowerHistory {
+    /// Uses history files in the `dir` directory.
+    pub fn new<T: Into<PathBuf>>(dir: T) -> UpowerHistory {
+        UpowerHistory {
+            dir: dir.into(),
+        }
+    }
+
+    /// Uses history files of the system UPower daemon at the `/var/lib/upower`.
+    pub fn system() -> UpowerHistory {
+        Self::new(SYSTEM_DIR)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns UPower identities of all devices with the history files.
+    pub fn device_ids(&self) -> Result<Vec<String>> {
+        let mut ids = Vec::new();
+        for entry in fs::read_dir(&self.dir).map_err(|e| Error::from(e).with_attribute(&self.dir))? {
+            let name = entry?.file_name().to_string_lossy().into_owned();
+            let id = KINDS.iter().find_map(|kind| {
+                let prefix = format!("history-{}-", kind.name());
+                if name.starts_with(&prefix) && name.ends_with(".dat") {
+                    Some(name[prefix.len()..name.len() - 4].to_string())
+                } else {
+                    None
+                }
+            });
+            if let Some(id) = id {
+                ids.push(id);
+            }
+        }
+        ids.sort();
+        ids.dedup();
+
+        Ok(ids)
+    }
+
+    /// Returns UPower identities matching the battery `id` model and serial number.
+    ///
+    /// There could be a few of them, if the design energy was reported differently over time.
+    pub fn find(&self, id: &BatteryId) -> Result<Vec<String>> {
+        let model = id.model().map(escape);
+        let serial = id.serial_number().map(escape);
+        if model.is_none() && serial.is_none() {
+            return Ok(Vec::new());
+        }
+
+        let matches = |device: &str| {
+            let rest = match model {
+                Some(ref model) if device == model => "",
+                Some(ref model) if device.starts_with(&format!("{}-", model)) => &device[model.len() + 1..],
+                Some(_) => return false,
+                None => device,
+            };
+            // Remainder is either empty, capacity, serial or `capacity-serial`
+            let rest = match serial {
+                Some(ref serial) if rest == serial => return true,
+                Some(ref serial) if rest.ends_with(&format!("-{}", serial)) => &rest[..rest.len() - serial.len() - 1],
+                Some(_) => return false,
+                None => rest,
+            };
+            rest.is_empty() || rest.bytes().all(|byte| byte.is_ascii_digit())
+        };
+
+        Ok(self.device_ids()?.into_iter().filter(|device| matches(device)).collect())
+    }
+
+    /// Loads all history files of the UPower `device` identity, ordered by time.
+    ///
+    /// Missing files are skipped.
+    pub fn load(&self, device: &str) -> Result<Vec<Sample>> {
+        let mut samples = BTreeMap::<u64, Sample>::new();
+        for kind in KINDS {
+            let path = self.path(*kind, device);
+            let content = match fs::read_to_string(&path) {
+                Ok(content) => content,
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(Error::from(e).with_attribute(&path)),
+            };
+
+            for (idx, line) in content.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let invalid = || {
+                    Error::invalid_data(format!("Malformed UPower history line {}", idx + 1))
+                        .with_attribute(&path)
+                        .with_raw_value(line)
+                };
+                let mut parts = line.split('\t');
+                let time = parts.next().and_then(|part| part.trim().parse::<u64>().ok()).ok_or_else(invalid)?;
+                let value = parts.next().and_then(|part| part.trim().parse::<f32>().ok()).ok_or_else(invalid)?;
+                let state = parts.next().map(|part| state(part.trim())).ok_or_else(invalid)?;
+
+                let sample = samples
+                    .remove(&time)
+                    .unwrap_or_else(|| Sample::new(UNIX_EPOCH + Duration::from_secs(time), state));
+                samples.insert(time, kind.set(sample, value));
+            }
+        }
+
+        Ok(samples.into_iter().map(|(_, sample)| sample).collect())
+    }
+
+    /// Loads history of all UPower devices matching the battery `id`, ordered by time.
+    pub fn load_battery(&self, id: &BatteryId) -> Result<Vec<Sample>> {
+        let mut samples = Vec::new();
+        for device in self.find(id)? {
+            samples.extend(self.load(&device)?);
+        }
+        samples.sort_by_key(Sample::time);
+
+        Ok(samples)
+    }
+
+    /// Writes `samples` into the UPower `device` history files, replacing the existing ones.
+    ///
+    /// Files are written only for the values present in the samples; existing files of the other values
+    /// are removed, so they are not merged back on the next load. Each file is replaced atomically.
+    pub fn save(&self, device: &str, samples: &[Sample]) -> Result<()> {
+        fs::create_dir_all(&self.dir).map_err(|e| Error::from(e).with_attribute(&self.dir))?;
+        for kind in KINDS {
+            let mut content = String::new();
+            for sample in samples {
+                if let Some(value) = kind.get(sample) {
+                    let time = sample.time().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0);
+                    // Writing into `String` can't fail
+                    let _ = writeln!(content, "{}\t{:.3}\t{}", time, value, state_name(sample.state()));
+                }
+            }
+            let path = self.path(*kind, device);
+            if !content.is_empty() {
+                store::write_atomic(&path, &content)?;
+            } else {
+                match fs::remove_file(&path) {
+                    Ok(()) => {}
+                    Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(Error::from(e).with_attribute(&path)),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn path(&self, kind: Kind, device: &str) -> PathBuf {
+        self.dir.join(format!("history-{}-{}.dat", kind.name(), device))
+    }
+}
+
+/// Replaces characters which UPower does not allow in the identity with `_`.
+fn escape(value: &str) -> String {
+    value
+        .chars()
+        .map(|chr| match chr {
+            '\\' | '\t' | '"' | '?' | '\'' | ' ' | '/' | ',' | '.' => '_',
+            chr => chr,
+        })
+        .collect()
+}
+
+fn state(name: &str) -> State {
+    match name {
+        "charging" => State::Charging,
+        "discharging" => State::Discharging,
+        "empty" => State::Empty,
+        "fully-charged" => State::Full,
+        _ => State::Unknown,
+    }
+}
+
+fn state_name(state: State) -> &'static str {
+    match state {
+        State::Charging => "charging",
+        State::Discharging => "discharging",
+        State::Empty => "empty",
+        State::Full => "fully-charged",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use approx::assert_abs_diff_eq;
+
+    use super::{device_id, UpowerHistory};
+    use crate::history::tests::sample;
+    use crate::units::energy::watt_hour;
+    use crate::units::power::watt;
+    use crate::units::ratio::percent;
+    use crate::units::time::second;
+    use crate::units::{Energy, Power};
+    use crate::{BatteryId, State};
+
+    #[test]
+    fn test_upower_device_id() {
+        let energy = Some(Energy::new::<watt_hour>(57.02));
+        assert_eq!(device_id(Some("5B10W13930"), energy, Some("1234")), Some("5B10W13930-57-1234".to_string()));
+        assert_eq!(device_id(Some("DELL 1.0"), None, Some("12/34")), Some("DELL_1_0-12_34".to_string()));
+        assert_eq!(device_id(None, energy, None), None);
+    }
+
+    #[test]
+    fn test_upower_load() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(
+            root.path().join("history-charge-5B10W13930-57-1234.dat"),
+            "1700000000\t82.000\tdischarging\n1700000120\t81.500\tdischarging\n",
+        )
+        .unwrap();
+        fs::write(
+            root.path().join("history-rate-5B10W13930-57-1234.dat"),
+            "1700000000\t8.120\tdischarging\n1700000060\t7.900\tdischarging\n",
+        )
+        .unwrap();
+        let path = root.path().join("history-time-full-5B10W13930-50-1234.dat");
+        fs::write(path, "1690000000\t3600.000\tcharging\n").unwrap();
+        fs::write(root.path().join("history-charge-OTHER-57-1234.dat"), "1700000000\t10.000\tfully-charged\n").unwrap();
+        fs::write(root.path().join("history-charge-5B10W13930-57-12345.dat"), "1700000000\t10.000\tempty\n").unwrap();
+
+        let upower = UpowerHistory::new(root.path());
+        let id = BatteryId::new(Some("Unknown vendor"), Some("5B10W13930"), Some("1234"));
+        assert_eq!(upower.find(&id).unwrap(), vec!["5B10W13930-50-1234", "5B10W13930-57-1234"]);
+
+        let samples = upower.load_battery(&id).unwrap();
+        assert_eq!(samples.len(), 4);
+        assert_eq!(samples[0].state(), State::Charging);
+        assert_abs_diff_eq!(samples[0].time_to_full().unwrap().get::<second>(), 3600.0);
+        assert_eq!(samples[1].time(), UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        assert_abs_diff_eq!(samples[1].state_of_charge().unwrap().get::<percent>(), 82.0, epsilon = 0.001);
+        assert_abs_diff_eq!(samples[1].energy_rate().unwrap().get::<watt>(), 8.12, epsilon = 0.001);
+        assert_eq!(samples[2].state_of_charge(), None);
+        assert_eq!(samples[3].energy_rate(), None);
+
+        assert_eq!(upower.load("OTHER-57-1234").unwrap()[0].state(), State::Full);
+
+        fs::write(root.path().join("history-rate-BROKEN.dat"), "1700000000 8.1\n").unwrap();
+        assert!(upower.load("BROKEN").is_err());
+    }
+
+    #[test]
+    fn test_upower_save() {
+        let root = tempfile::tempdir().unwrap();
+        let upower = UpowerHistory::new(root.path());
+        let stale = root.path().join("history-time-full-MODEL-57-1234.dat");
+        fs::write(&stale, "1690000000\t3600.000\tcharging\n").unwrap();
+        let samples = vec![
+            sample(1_700_000_000, State::Full, 100.0),
+            sample(1_700_000_060, State::Discharging, 99.0).with_energy_rate(Power::new::<watt>(8.0)),
+        ];
+        upower.save("MODEL-57-1234", &samples).unwrap();
+
+        let charge = fs::read_to_string(root.path().join("history-charge-MODEL-57-1234.dat")).unwrap();
+        assert_eq!(charge, "1700000000\t100.000\tfully-charged\n1700000060\t99.000\tdischarging\n");
+        assert!(root.path().join("history-rate-MODEL-57-1234.dat").exists());
+        assert!(!stale.exists());
+        assert_eq!(fs::read_dir(root.path()).unwrap().count(), 2);
+
+        let loaded = upower.load("MODEL-57-1234").unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[1].energy_rate(), Some(Power::new::<watt>(8.0)));
+        // Energy is not a part of the UPower history
+        assert_eq!(loaded[1].energy(), None);
+    }
+}
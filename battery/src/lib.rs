@@ -24,6 +24,14 @@
 #![deny(unstable_features)]
 #![deny(bare_trait_objects)]
 #![allow(clippy::manual_non_exhaustive)]  // MSRV is 1.36
+#![allow(clippy::derivable_impls)]  // MSRV is 1.36
+#![allow(clippy::manual_clamp)]  // MSRV is 1.36
+#![allow(clippy::iter_kv_map)]  // MSRV is 1.36
+#![allow(clippy::match_like_matches_macro)]  // MSRV is 1.36
+#![allow(clippy::unnecessary_map_or)]  // MSRV is 1.36
+#![allow(clippy::legacy_numeric_constants)]  // MSRV is 1.36
+#![allow(clippy::manual_strip)]  // MSRV is 1.36
+#![allow(clippy::mem_replace_with_default)]  // MSRV is 1.36
 #![doc(html_root_url = "https://docs.rs/battery/0.7.8")]
 
 #[macro_use]
@@ -41,7 +49,32 @@ mod types;
 #[macro_use]
 pub mod units;
 pub mod errors;
+pub mod quirks;
+#[cfg(feature = "history")]
+pub mod history;
+#[cfg(feature = "history")]
+pub mod baseline;
+#[cfg(feature = "history")]
+pub mod cycles;
+#[cfg(feature = "history")]
+pub mod wear;
+#[cfg(feature = "history")]
+pub mod sessions;
+#[cfg(feature = "history")]
+pub mod suspend;
+#[cfg(target_os = "linux")]
+pub mod archive;
+#[cfg(target_os = "linux")]
+pub mod dump;
+#[cfg(all(target_os = "linux", feature = "test-util"))]
+pub mod test_util;
+#[cfg(all(target_os = "linux", feature = "simulation"))]
+pub mod simulation;
 mod platform;
 
-pub use self::errors::{Error, Result};
-pub use self::types::{Batteries, Battery, Manager, State, Technology};
+pub use self::errors::{Error, ErrorKind, Result};
+pub use self::types::diagnostics;
+pub use self::types::{
+    Anomaly, Batteries, Battery, BatteryId, Diagnostics, Fields, Manager, Reading, Readings, RefreshReport, State, Technology,
+    Warning,
+};
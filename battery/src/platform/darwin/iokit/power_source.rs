@@ -80,7 +80,7 @@ impl InstantData {
                 value_ref.downcast::<CFBoolean>()
             })
             .map(Into::into)
-            .ok_or_else(|| Error::not_found(raw_key))
+            .ok_or_else(|| Error::not_found("Power source property is missing").with_attribute(raw_key))
     }
 
     fn get_u32(props: &Properties, raw_key: &'static str) -> Result<u32> {
@@ -99,7 +99,7 @@ impl InstantData {
             // but with currently used keys it seems to be impossible
             .and_then(|number| number.to_i32())
             .map(|value| value as u32)
-            .ok_or_else(|| Error::not_found(raw_key))
+            .ok_or_else(|| Error::not_found("Power source property is missing").with_attribute(raw_key))
     }
 
     fn get_i32(props: &Properties, raw_key: &'static str) -> Result<i32> {
@@ -115,7 +115,7 @@ impl InstantData {
                 value_ref.downcast::<CFNumber>()
             })
             .and_then(|number| number.to_i32())
-            .ok_or_else(|| Error::not_found(raw_key))
+            .ok_or_else(|| Error::not_found("Power source property is missing").with_attribute(raw_key))
     }
 
     fn get_string(props: &Properties, raw_key: &'static str) -> Result<String> {
@@ -131,7 +131,7 @@ impl InstantData {
                 value_ref.downcast::<CFString>()
             })
             .map(|cf_string| cf_string.to_string())
-            .ok_or_else(|| Error::not_found(raw_key))
+            .ok_or_else(|| Error::not_found("Power source property is missing").with_attribute(raw_key))
     }
 }
 
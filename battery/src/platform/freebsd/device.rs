@@ -76,6 +76,10 @@ impl IoCtlDevice {
 }
 
 impl BatteryDevice for IoCtlDevice {
+    fn is_same_device(&self, other: &Self) -> bool {
+        self.unit == other.unit
+    }
+
     fn energy(&self) -> Energy {
         self.current_capacity
     }
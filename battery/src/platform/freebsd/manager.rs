@@ -19,10 +19,11 @@ impl BatteryManager for IoCtlManager {
         let bif = self.0.bif(device.unit())?;
         let bst = self.0.bst(device.unit())?;
 
+        let name = format!("battery{}", device.unit());
         match (bif, bst) {
             (Some(bif), Some(bst)) => device.refresh(bif, bst),
-            (None, _) => Err(Error::invalid_data("Returned bif struct is invalid")),
-            (_, None) => Err(Error::invalid_data("Returned bst struct is invalid")),
+            (None, _) => Err(Error::invalid_data("Returned bif struct is invalid").with_device(name)),
+            (_, None) => Err(Error::invalid_data("Returned bst struct is invalid").with_device(name)),
         }
     }
 }
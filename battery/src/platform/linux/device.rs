@@ -1,12 +1,18 @@
 use std::fmt;
 use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
 
 use crate::platform::traits::*;
+use crate::quirks::{Correction, DeviceInfo, Quirks};
 use crate::units::{ElectricPotential, Energy, Power, Ratio, ThermodynamicTemperature};
-use crate::{Error, Result, State, Technology};
+use crate::types::{Reading, Readings};
+use crate::{Diagnostics, Error, Fields, Result, State, Technology, Warning};
 
-use super::sysfs::{fs, DataBuilder, InstantData, Scope, Type};
+use super::sysfs::{fs, DataBuilder, InstantData, Scope, Tracker, Type};
 
 pub struct SysFsDevice {
     root: PathBuf,
@@ -18,6 +24,42 @@ pub struct SysFsDevice {
     model: Option<String>,
     serial_number: Option<String>,
     technology: Technology,
+
+    // Quirks matching this device, their conditions on the attributes values
+    // are checked on each read, as these values are changing
+    quirks: Quirks,
+
+    // Lenient devices are keeping previous values for attributes which failed to load,
+    // instead of failing the whole refresh
+    lenient: bool,
+    missing: Fields,
+    warnings: Vec<Warning>,
+
+    // Read started by `refresh_timeout`, which had not finished in time
+    pending: Option<PendingRead>,
+}
+
+pub type ReadResult = Result<(InstantData, Vec<Warning>)>;
+
+struct PendingRead {
+    device: String,
+    result: Receiver<ReadResult>,
+    tracker: Tracker,
+}
+
+impl PendingRead {
+    fn timeout_error(&self, timeout: Duration) -> Error {
+        let inner = io::Error::from(io::ErrorKind::TimedOut);
+        let attribute = self.tracker.lock().ok().and_then(|current| current.clone());
+        let e = match attribute {
+            Some(path) => {
+                Error::new(inner, format!("Reading timed out after {:?}", timeout)).with_attribute(path)
+            }
+            None => Error::new(inner, format!("Device refresh timed out after {:?}", timeout)),
+        };
+
+        e.with_device(self.device.clone())
+    }
 }
 
 impl SysFsDevice {
@@ -30,14 +72,16 @@ impl SysFsDevice {
         Ok(false)
     }
 
-    pub fn try_from(root: PathBuf) -> Result<SysFsDevice> {
+    pub fn try_from(root: PathBuf, quirks: &Quirks) -> Result<SysFsDevice> {
         let builder = DataBuilder::new(&root);
         let vendor = builder.manufacturer()?;
         let model = builder.model()?;
         let serial_number = builder.serial_number()?;
         let technology = builder.technology()?;
 
-        let source = builder.collect()?;
+        let quirks = Self::candidates(&builder, quirks, &vendor, &model);
+        let corrections = Self::corrections(&builder, &quirks);
+        let source = builder.with_corrections(&corrections).collect()?;
 
         Ok(SysFsDevice {
             root,
@@ -46,50 +90,284 @@ impl SysFsDevice {
             model,
             serial_number,
             technology,
+            quirks,
+            lenient: false,
+            missing: Fields::empty(),
+            warnings: Vec::new(),
+            pending: None,
         })
     }
 
+    /// Loads the device, skipping all values which can't be loaded.
+    ///
+    /// Problems are reported by the `warnings()` method instead,
+    /// and the following refreshes are lenient too.
+    pub fn try_from_lenient(root: PathBuf, quirks: &Quirks) -> SysFsDevice {
+        let mut warnings = Vec::new();
+        let builder = DataBuilder::new(&root);
+        let mut static_value = |value: Result<Option<String>>| {
+            value.unwrap_or_else(|e| {
+                warnings.push(Warning::new(Fields::empty(), e));
+                None
+            })
+        };
+        let vendor = static_value(builder.manufacturer());
+        let model = static_value(builder.model());
+        let serial_number = static_value(builder.serial_number());
+        let technology = builder.technology().unwrap_or_else(|e| {
+            warnings.push(Warning::new(Fields::empty(), e));
+            Technology::Unknown
+        });
+
+        let quirks = Self::candidates(&builder, quirks, &vendor, &model);
+        let corrections = Self::corrections(&builder, &quirks);
+        let mut source = InstantData::empty();
+        let builder = builder.with_corrections(&corrections);
+        warnings.extend(builder.collect_lenient(&mut source, Fields::ALL));
+        let missing = Self::missing_in(&warnings);
+
+        SysFsDevice {
+            root,
+            source,
+            vendor,
+            model,
+            serial_number,
+            technology,
+            quirks,
+            lenient: true,
+            missing,
+            warnings,
+            pending: None,
+        }
+    }
+
+    /// Selects quirks matching the device, except their conditions on the attributes values.
+    fn candidates(builder: &DataBuilder, quirks: &Quirks, vendor: &Option<String>, model: &Option<String>) -> Quirks {
+        if quirks.is_empty() {
+            return Quirks::new();
+        }
+
+        let driver = builder.driver();
+        let sys_vendor = builder.sys_vendor();
+        let attributes = builder.attributes();
+        let info = DeviceInfo {
+            vendor: vendor.as_ref().map(AsRef::as_ref),
+            model: model.as_ref().map(AsRef::as_ref),
+            driver: driver.as_ref().map(AsRef::as_ref),
+            sys_vendor: sys_vendor.as_ref().map(AsRef::as_ref),
+            attributes: Some(&attributes),
+        };
+
+        quirks.candidates_for(&info)
+    }
+
+    /// Collects corrections of the `quirks` matching the current device attributes values.
+    fn corrections(builder: &DataBuilder, quirks: &Quirks) -> Vec<Correction> {
+        quirks.corrections_with(&builder.values(&quirks.compared_attributes()))
+    }
+
+    fn missing_in(warnings: &[Warning]) -> Fields {
+        warnings
+            .iter()
+            .fold(Fields::empty(), |acc, warning| acc | warning.fields())
+    }
+
+    /// Reads `fields` into the copy of the `source`, applying corrections of the matching `quirks`.
+    fn read(
+        builder: DataBuilder,
+        quirks: &Quirks,
+        mut source: InstantData,
+        fields: Fields,
+        lenient: bool,
+    ) -> ReadResult {
+        let corrections = Self::corrections(&builder, quirks);
+        let builder = builder.with_corrections(&corrections);
+        if lenient {
+            let warnings = builder.collect_lenient(&mut source, fields);
+            Ok((source, warnings))
+        } else {
+            builder.collect_into(&mut source, fields)?;
+            Ok((source, Vec::new()))
+        }
+    }
+
+    fn apply(&mut self, fields: Fields, source: InstantData, warnings: Vec<Warning>) {
+        self.source = source;
+        self.missing = (self.missing - fields) | Self::missing_in(&warnings);
+        self.warnings = warnings;
+    }
+
+    fn removed_error(root: &Path) -> Error {
+        let e = Error::removed("Device directory is missing");
+        e.with_device(fs::device_name(root))
+    }
+
+    pub fn root(&self) -> &Path {
+        self.root.as_path()
+    }
+
+    /// Checks if the battery from the `path` directory has the same identity as this one.
+    pub fn is_located_at(&self, path: &Path) -> bool {
+        let builder = DataBuilder::new(path);
+        let matches = |value: Result<Option<String>>, expected: &Option<String>| match value {
+            Ok(value) => value.as_ref().map(|v| v.trim()) == expected.as_ref().map(|v| v.trim()),
+            Err(_) => false,
+        };
+
+        matches(builder.serial_number(), &self.serial_number)
+            && matches(builder.manufacturer(), &self.vendor)
+            && matches(builder.model(), &self.model)
+    }
+
+    /// Points device to the new sysfs directory, used when the same battery was re-plugged.
+    pub fn rebind(&mut self, root: PathBuf) {
+        self.root = root;
+    }
+
     pub fn refresh(&mut self) -> Result<()> {
+        self.refresh_with(Fields::ALL)
+    }
+
+    pub fn refresh_with(&mut self, fields: Fields) -> Result<()> {
+        let fields = fields.with_implied();
         // It is necessary to ensure that `self.root`
         // still exists and accessible.
         // See https://github.com/svartalf/rust-battery/issues/29
         if self.root.is_dir() {
             let builder = DataBuilder::new(&self.root);
-            self.source = builder.collect()?;
+            let (source, warnings) = Self::read(builder, &self.quirks, self.source.clone(), fields, self.lenient)?;
+            self.apply(fields, source, warnings);
 
             Ok(())
         } else {
-            let inner = io::Error::from(io::ErrorKind::NotFound);
-            let e = Error::new(inner, format!("Device directory `{:?}` is missing", self.root));
+            Err(Self::removed_error(&self.root))
+        }
+    }
 
-            Err(e)
+    /// Prepares the read of all values, which does not borrow the device
+    /// and could be executed in another thread.
+    ///
+    /// Read result should be passed to the `complete_refresh` method then.
+    pub fn refresh_job(&self, tracker: Option<Tracker>) -> impl FnOnce() -> ReadResult + Send + 'static {
+        let root = self.root.clone();
+        let source = self.source.clone();
+        let lenient = self.lenient;
+        let quirks = self.quirks.clone();
+
+        move || {
+            if root.is_dir() {
+                let builder = match tracker {
+                    Some(tracker) => DataBuilder::with_tracker(&root, tracker),
+                    None => DataBuilder::new(&root),
+                };
+                Self::read(builder, &quirks, source, Fields::ALL, lenient)
+            } else {
+                Err(Self::removed_error(&root))
+            }
+        }
+    }
+
+    /// Applies the result of the `refresh_job` read.
+    pub fn complete_refresh(&mut self, result: ReadResult) -> Result<()> {
+        let (source, warnings) = result?;
+        self.apply(Fields::ALL, source, warnings);
+
+        Ok(())
+    }
+
+    /// Reads all values in a separate thread, waiting for at most `timeout` for it to finish.
+    ///
+    /// If the read takes longer, previous values are kept and the timeout error is returned.
+    /// Since it is not possible to cancel a blocked read, the hanging thread is left
+    /// to finish on its own, and the following calls are failing immediately until it does.
+    pub fn refresh_timeout(&mut self, timeout: Duration) -> Result<()> {
+        if let Some(pending) = self.pending.take() {
+            match pending.result.try_recv() {
+                // Previous read is finished, it is okay to start a new one
+                Ok(_) | Err(TryRecvError::Disconnected) => {}
+                Err(TryRecvError::Empty) => {
+                    let e = pending.timeout_error(timeout);
+                    self.pending = Some(pending);
+                    return Err(e);
+                }
+            }
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let tracker: Tracker = Arc::new(Mutex::new(None));
+        let job = self.refresh_job(Some(tracker.clone()));
+        thread::Builder::new().name("battery-refresh".into()).spawn(move || {
+            // Receiver might be gone already if caller stopped waiting
+            let _ = sender.send(job());
+        })?;
+
+        match receiver.recv_timeout(timeout) {
+            Ok(result) => self.complete_refresh(result),
+            Err(RecvTimeoutError::Timeout) => {
+                let pending = PendingRead {
+                    device: fs::device_name(&self.root),
+                    result: receiver,
+                    tracker,
+                };
+                let e = pending.timeout_error(timeout);
+                self.pending = Some(pending);
+
+                Err(e)
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                let e = Error::new(io::Error::from(io::ErrorKind::Other), "Device refresh thread had panicked");
+                Err(e.with_device(fs::device_name(&self.root)))
+            }
         }
     }
 }
 
 impl BatteryDevice for SysFsDevice {
+    fn is_same_device(&self, other: &Self) -> bool {
+        self.root == other.root
+    }
+
+    fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    fn missing_fields(&self) -> Fields {
+        self.missing
+    }
+
+    fn diagnostics(&self) -> Diagnostics {
+        self.source.diagnostics.clone()
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.root.file_name().and_then(|name| name.to_str())
+    }
+
     fn state_of_health(&self) -> Ratio {
-        self.source.state_of_health
+        // Same as upower, assuming that battery is healthy if it is unknown
+        self.source.state_of_health.unwrap_or(percent!(100.0))
     }
 
     fn state_of_charge(&self) -> Ratio {
-        self.source.state_of_charge
+        // Same as upower, falling back to 0.0%
+        self.source.state_of_charge.unwrap_or(percent!(0.0))
     }
 
     fn energy(&self) -> Energy {
-        self.source.energy
+        self.source.energy.unwrap_or(microwatt_hour!(0.0))
     }
 
     fn energy_full(&self) -> Energy {
-        self.source.energy_full
+        self.source.energy_full.unwrap_or(microwatt_hour!(0.0))
     }
 
     fn energy_full_design(&self) -> Energy {
-        self.source.energy_full_design
+        // Same as upower, falling back to zero value, see #40
+        self.source.energy_full_design.unwrap_or(microwatt_hour!(0.0))
     }
 
     fn energy_rate(&self) -> Power {
-        self.source.energy_rate
+        self.source.energy_rate.unwrap_or(watt!(0.0))
     }
 
     fn state(&self) -> State {
@@ -97,11 +375,11 @@ impl BatteryDevice for SysFsDevice {
     }
 
     fn voltage(&self) -> ElectricPotential {
-        self.source.voltage
+        self.source.voltage.unwrap_or(volt!(0.0))
     }
 
     fn temperature(&self) -> Option<ThermodynamicTemperature> {
-        self.source.temperature
+        self.source.temperature.value()
     }
 
     fn vendor(&self) -> Option<&str> {
@@ -121,7 +399,24 @@ impl BatteryDevice for SysFsDevice {
     }
 
     fn cycle_count(&self) -> Option<u32> {
-        self.source.cycle_count
+        self.source.cycle_count.value()
+    }
+
+    fn readings(&self) -> Readings {
+        Readings {
+            state_of_charge: self.source.state_of_charge,
+            state_of_health: self.source.state_of_health,
+            energy: self.source.energy,
+            energy_full: self.source.energy_full,
+            energy_full_design: self.source.energy_full_design,
+            energy_rate: self.source.energy_rate,
+            voltage: self.source.voltage,
+            temperature: self.source.temperature,
+            cycle_count: self.source.cycle_count,
+            // Both are calculated from the values above and can't be reported by sysfs
+            time_to_full: self.time_to_full().map_or(Reading::Unavailable, Reading::Derived),
+            time_to_empty: self.time_to_empty().map_or(Reading::Unavailable, Reading::Derived),
+        }
     }
 }
 
@@ -7,9 +7,9 @@ use crate::platform::traits::*;
 use crate::Result;
 
 pub struct SysFsIterator {
-    #[allow(dead_code)]
     manager: Rc<SysFsManager>,
     entries: ReadDir,
+    lenient: bool,
 }
 
 impl BatteryIterator for SysFsIterator {
@@ -22,8 +22,47 @@ impl BatteryIterator for SysFsIterator {
         Ok(SysFsIterator {
             manager,
             entries,
+            lenient: false,
         })
     }
+
+    fn new_lenient(manager: Rc<Self::Manager>) -> Result<Self> {
+        let mut iterator = Self::new(manager)?;
+        iterator.lenient = true;
+
+        Ok(iterator)
+    }
+
+    /// Lists the device directories instead of loading all devices,
+    /// only the new ones and the ones which failed to refresh are read.
+    fn presence(
+        manager: Rc<Self::Manager>,
+        devices: &[&SysFsDevice],
+        results: &[Result<()>],
+    ) -> Result<(Vec<bool>, Vec<SysFsDevice>)> {
+        let mut present = vec![false; devices.len()];
+        let mut added = Vec::new();
+        for entry in fs::read_dir(manager.path())? {
+            let path = entry?.path();
+            match SysFsDevice::is_system_battery(&path) {
+                Ok(true) => {}
+                _ => continue,
+            }
+
+            let known = devices.iter().position(|device| device.root() == path);
+            match known {
+                Some(idx) if results[idx].is_ok() || devices[idx].is_located_at(&path) => present[idx] = true,
+                _ => {
+                    // Devices which are failing to load are not reported as added ones
+                    if let Ok(device) = SysFsDevice::try_from(path, manager.quirks()) {
+                        added.push(device);
+                    }
+                }
+            }
+        }
+
+        Ok((present, added))
+    }
 }
 
 impl Iterator for SysFsIterator {
@@ -38,7 +77,10 @@ impl Iterator for SysFsIterator {
                 Some(Ok(entry)) => {
                     let path = entry.path();
                     match SysFsDevice::is_system_battery(&path) {
-                        Ok(true) => Some(SysFsDevice::try_from(path)),
+                        Ok(true) if self.lenient => {
+                            Some(Ok(SysFsDevice::try_from_lenient(path, self.manager.quirks())))
+                        }
+                        Ok(true) => Some(SysFsDevice::try_from(path, self.manager.quirks())),
                         Ok(false) => continue,
                         Err(e) => Some(Err(e)),
                     }
@@ -58,6 +100,7 @@ impl fmt::Debug for SysFsIterator {
         f.debug_struct("LinuxIterator")
             .field("start", &start)
             .field("end", &end)
+            .field("lenient", &self.lenient)
             .finish()
     }
 }
@@ -1,9 +1,17 @@
+use std::error::Error as StdError;
+use std::fs;
+use std::io;
+use std::panic;
 use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
 
 use super::device::SysFsDevice;
 use super::iterator::SysFsIterator;
+use super::sysfs;
 use crate::platform::traits::*;
-use crate::Result;
+use crate::quirks::Quirks;
+use crate::{Error, Fields, Result};
 
 #[allow(clippy::redundant_static_lifetimes)]
 static SYSFS_ROOT: &'static str = "/sys/class/power_supply";
@@ -11,12 +19,73 @@ static SYSFS_ROOT: &'static str = "/sys/class/power_supply";
 #[derive(Debug)]
 pub struct SysFsManager {
     root: PathBuf,
+    quirks: Quirks,
 }
 
 impl SysFsManager {
     pub fn path(&self) -> &Path {
         self.root.as_path()
     }
+
+    pub fn quirks(&self) -> &Quirks {
+        &self.quirks
+    }
+
+    /// Re-binds `device` after its refresh failed with the `e`rror.
+    ///
+    /// If the device directory is gone or holds another battery now,
+    /// it looks for the battery with the same identity among other devices,
+    /// since hot-swappable and docking station batteries might be removed
+    /// and added back under another name.
+    /// Identity is not checked on successful refreshes, as it requires additional reads.
+    fn rebind(&self, device: &mut SysFsDevice, e: Error) -> Result<()> {
+        if !is_gone(&e) || (device.root().is_dir() && device.is_located_at(device.root())) {
+            return Err(e);
+        }
+
+        // Without serial number there is no way to tell if this is the same battery
+        if device.serial_number().is_some() {
+            for entry in fs::read_dir(&self.root)? {
+                let path = entry?.path();
+                if path == device.root() {
+                    continue;
+                }
+                if let Ok(true) = SysFsDevice::is_system_battery(&path) {
+                    if device.is_located_at(&path) {
+                        device.rebind(path);
+                        return Ok(());
+                    }
+                }
+            }
+        }
+
+        let e = Error::removed(format!(
+            "Battery previously located at `{:?}` is not available anymore",
+            device.root()
+        ));
+        Err(e.with_device(sysfs::fs::device_name(device.root())))
+    }
+
+    /// Executes the `refresh`, repeating it once if the `device` was re-bound after the failure.
+    fn refresh_bound<F>(&self, device: &mut SysFsDevice, refresh: F) -> Result<()>
+    where
+        F: Fn(&mut SysFsDevice) -> Result<()>,
+    {
+        match refresh(device) {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                self.rebind(device, e)?;
+                refresh(device)
+            }
+        }
+    }
+
+    pub fn with_root(root: PathBuf) -> SysFsManager {
+        SysFsManager {
+            root,
+            quirks: Quirks::builtin(),
+        }
+    }
 }
 
 impl BatteryManager for SysFsManager {
@@ -25,10 +94,69 @@ impl BatteryManager for SysFsManager {
     fn new() -> Result<Self> {
         Ok(Self {
             root: PathBuf::from(SYSFS_ROOT),
+            quirks: Quirks::builtin(),
         })
     }
 
+    fn with_quirks(quirks: Quirks) -> Result<Self> {
+        let mut manager = Self::new()?;
+        manager.quirks.extend(quirks);
+
+        Ok(manager)
+    }
+
     fn refresh(&self, device: &mut SysFsDevice) -> Result<()> {
-        device.refresh()
+        self.refresh_bound(device, SysFsDevice::refresh)
+    }
+
+    fn refresh_with(&self, device: &mut SysFsDevice, fields: Fields) -> Result<()> {
+        self.refresh_bound(device, |device| device.refresh_with(fields))
+    }
+
+    fn refresh_timeout(&self, device: &mut SysFsDevice, timeout: Duration) -> Result<()> {
+        self.refresh_bound(device, |device| device.refresh_timeout(timeout))
+    }
+
+    fn refresh_all(&self, devices: Vec<&mut SysFsDevice>, concurrently: bool) -> Vec<Result<()>> {
+        if !concurrently || devices.len() < 2 {
+            return devices.into_iter().map(|device| self.refresh(device)).collect();
+        }
+
+        // Each device is read in its own thread, so one slow driver
+        // is not delaying reads for the others
+        let jobs = devices
+            .into_iter()
+            .map(|device| {
+                let handle = thread::Builder::new()
+                    .name("battery-refresh".into())
+                    .spawn(device.refresh_job(None));
+                (device, handle.map_err(Error::from))
+            })
+            .collect::<Vec<_>>();
+
+        jobs.into_iter()
+            .map(|(device, handle)| {
+                let result = handle?.join().unwrap_or_else(|e| panic::resume_unwind(e));
+                match device.complete_refresh(result) {
+                    Ok(()) => Ok(()),
+                    Err(e) => {
+                        self.rebind(device, e)?;
+                        device.refresh()
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+/// Checks if the refresh error means that the device directory might be gone.
+fn is_gone(e: &Error) -> bool {
+    if e.is_removed() {
+        return true;
+    }
+
+    match StdError::source(e).and_then(|source| source.downcast_ref::<io::Error>()) {
+        Some(source) => source.kind() == io::ErrorKind::NotFound || source.raw_os_error() == Some(sysfs::fs::ENODEV),
+        None => false,
     }
 }
@@ -6,61 +6,38 @@ use std::str::FromStr;
 
 use super::{Scope, Type};
 use crate::units::{ElectricCharge, ElectricPotential, Energy, Power};
-use crate::Result;
+use crate::{Error, Result};
 
 // From the `errno.h`.
 // Easier than building whole `libc` dep.
-const ENODEV: i32 = 19;
+pub const ENODEV: i32 = 19;
 
-/// Read µWh value from the `energy_` file and convert into `Energy` type.
-pub fn energy<T: AsRef<Path>>(path: T) -> Result<Option<Energy>> {
-    let path = path.as_ref();
-    debug_assert!(path.file_name().unwrap().to_string_lossy().starts_with("energy_"));
-
-    match get::<f32, _>(path) {
-        Ok(Some(value_uwh)) => Ok(Some(microwatt_hour!(value_uwh))),
-        Ok(None) => Ok(None),
-        Err(e) => Err(e),
-    }
+/// Parse µWh value of the `energy_` attribute into `Energy` type.
+pub fn energy(value: &str) -> Option<Energy> {
+    parse::<f32>(value).map(|value_uwh| microwatt_hour!(value_uwh))
 }
 
-/// Read µAh value from the `charge_` file and convert into `ElectricCharge` type.
-pub fn charge<T: AsRef<Path>>(path: T) -> Result<Option<ElectricCharge>> {
-    let path = path.as_ref();
-    debug_assert!(path.file_name().unwrap().to_string_lossy().starts_with("charge_"));
-
-    match get::<f32, _>(path) {
-        Ok(Some(value_uah)) if value_uah > 1.0 => Ok(Some(microampere_hour!(value_uah))),
-        Ok(Some(_)) => Ok(None),
-        Ok(None) => Ok(None),
-        Err(e) => Err(e),
-    }
+/// Parse µAh value of the `charge_` attribute into `ElectricCharge` type.
+pub fn charge(value: &str) -> Option<ElectricCharge> {
+    parse::<f32>(value).map(|value_uah| microampere_hour!(value_uah))
 }
 
-/// Read µV value from the `voltage_` file and convert into `ElectricPotential` type.
-pub fn voltage<T: AsRef<Path>>(path: T) -> Result<Option<ElectricPotential>> {
-    let path = path.as_ref();
-    debug_assert!(path.file_name().unwrap().to_string_lossy().starts_with("voltage_"));
-
-    match get::<f32, _>(path) {
-        Ok(Some(value_uv)) if value_uv > 1.0 => Ok(Some(microvolt!(value_uv))),
-        Ok(Some(_)) => Ok(None),
-        Ok(None) => Ok(None),
-        Err(e) => Err(e),
+/// Parse µV value of the `voltage_` attribute into `ElectricPotential` type.
+pub fn voltage(value: &str) -> Option<ElectricPotential> {
+    match parse::<f32>(value) {
+        Some(value_uv) if value_uv > 1.0 => Some(microvolt!(value_uv)),
+        _ => None,
     }
 }
 
-/// Read µW value from the `power_` file and convert into `Power` type.
-pub fn power<T: AsRef<Path>>(path: T) -> Result<Option<Power>> {
-    let path = path.as_ref();
-    debug_assert!(path.file_name().unwrap().to_string_lossy().starts_with("power_"));
+/// Parse µW value of the `power_` attribute into `Power` type.
+pub fn power(value: &str) -> Option<Power> {
+    parse::<f32>(value).map(|value_uw| microwatt!(value_uw))
+}
 
-    match get::<f32, _>(path) {
-        Ok(Some(value_uw)) if value_uw > 10_000.0 => Ok(Some(microwatt!(value_uw))),
-        Ok(Some(_)) => Ok(None),
-        Ok(None) => Ok(None),
-        Err(e) => Err(e),
-    }
+/// Parse attribute value, returning `None` if it is malformed.
+pub fn parse<V: FromStr>(value: &str) -> Option<V> {
+    V::from_str(value).ok()
 }
 
 /// Read device `type` file and convert into `Type` enum.
@@ -93,16 +70,18 @@ pub fn scope<T: AsRef<Path>>(path: T) -> Result<Scope> {
 /// Ok(None) - file is missing
 /// Err(_) - unable to access file for some reasons (except `NotFound` and `ENODEV`)
 pub fn get_string<T: AsRef<Path>>(path: T) -> Result<Option<String>> {
+    let path = path.as_ref();
     match read_to_string(path) {
         Ok(mut content) => {
+            // In-place trim
+            if content.ends_with('\n') {
+                content.truncate(content.len() - 1);
+            }
+
             if content.starts_with('\0') {
-                Err(io::Error::from(io::ErrorKind::InvalidData).into())
+                let e = Error::invalid_data("Attribute value is not a text");
+                Err(e.with_attribute(path).with_raw_value(content))
             } else {
-                // In-place trim
-                if content.ends_with('\n') {
-                    content.truncate(content.len() - 1);
-                }
-
                 Ok(Some(content))
             }
         }
@@ -111,7 +90,15 @@ pub fn get_string<T: AsRef<Path>>(path: T) -> Result<Option<String>> {
         // fails with a `ENODEV` error.
         // See https://github.com/svartalf/rust-battery/issues/28
         Err(ref e) if e.raw_os_error() == Some(ENODEV) => Ok(None),
-        Err(e) => Err(e.into()),
+        Err(e) => Err(Error::from(e).with_attribute(path)),
+    }
+}
+
+/// Device name, which is the name of its sysfs directory.
+pub fn device_name(root: &Path) -> String {
+    match root.file_name() {
+        Some(name) => name.to_string_lossy().into_owned(),
+        None => root.to_string_lossy().into_owned(),
     }
 }
 
@@ -121,12 +108,5 @@ where
     V: FromStr,
     <V as FromStr>::Err: error::Error + Sync + Send,
 {
-    match get_string(path) {
-        Ok(Some(ref value)) => match V::from_str(value) {
-            Ok(result) => Ok(Some(result)),
-            Err(_) => Ok(None),
-        },
-        Ok(None) => Ok(None),
-        Err(e) => Err(e),
-    }
+    get_string(path).map(|value| value.and_then(|value| parse(&value)))
 }
@@ -4,7 +4,7 @@ use std::str::FromStr;
 pub mod fs;
 mod source;
 
-pub use self::source::{DataBuilder, InstantData};
+pub use self::source::{DataBuilder, InstantData, Tracker};
 
 #[allow(dead_code)]
 #[derive(Debug, Eq, PartialEq)]
@@ -1,49 +1,90 @@
+use std::cell::RefCell;
 use std::f32;
-use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
 use lazycell::LazyCell;
 use num_traits::identities::Zero;
 
 use super::fs;
+use crate::quirks::Correction;
+use crate::types::{Adjustment, Conversion, Diagnostics, Quantity, Reading, Trace, Warning};
 use crate::units::power::{microwatt, watt};
 use crate::units::{Bound, ElectricCharge, ElectricPotential, Energy, Power, Ratio, ThermodynamicTemperature};
-use crate::{Error, Result, State, Technology};
-
-#[derive(Debug)]
+use crate::{Error, Fields, Result, State, Technology};
+
+/// Holds the path of the attribute which is read at the moment.
+///
+/// Shared between the reading thread and the caller, so it is possible
+/// to tell which attribute hangs if the read takes too long.
+pub type Tracker = Arc<Mutex<Option<PathBuf>>>;
+
+/// Device values, as they were reported or calculated.
+///
+/// Missing values are not substituted here, it is up to the `SysFsDevice`
+/// to decide what should be returned instead of them.
+#[derive(Debug, Clone)]
 pub struct InstantData {
-    pub state_of_health: Ratio,
-    pub state_of_charge: Ratio,
-
-    pub energy: Energy,
-    pub energy_full: Energy,
-    pub energy_full_design: Energy,
-    pub energy_rate: Power,
-    pub voltage: ElectricPotential,
+    pub state_of_health: Reading<Ratio>,
+    pub state_of_charge: Reading<Ratio>,
+
+    pub energy: Reading<Energy>,
+    pub energy_full: Reading<Energy>,
+    pub energy_full_design: Reading<Energy>,
+    pub energy_rate: Reading<Power>,
+    pub voltage: Reading<ElectricPotential>,
     pub state: State,
-    pub temperature: Option<ThermodynamicTemperature>,
-    pub cycle_count: Option<u32>,
+    pub temperature: Reading<ThermodynamicTemperature>,
+    pub cycle_count: Reading<u32>,
+
+    pub diagnostics: Diagnostics,
+}
+
+impl InstantData {
+    /// Placeholder values for the device, which values were not loaded yet.
+    pub fn empty() -> InstantData {
+        InstantData {
+            state_of_health: Reading::Unavailable,
+            state_of_charge: Reading::Unavailable,
+            energy: Reading::Unavailable,
+            energy_full: Reading::Unavailable,
+            energy_full_design: Reading::Unavailable,
+            energy_rate: Reading::Unavailable,
+            voltage: Reading::Unavailable,
+            state: State::Unknown,
+            temperature: Reading::Unavailable,
+            cycle_count: Reading::Unavailable,
+            diagnostics: Diagnostics::default(),
+        }
+    }
 }
 
 pub struct DataBuilder<'p> {
     root: &'p Path,
+    tracker: Option<Tracker>,
+    corrections: &'p [Correction],
 
     design_voltage: LazyCell<ElectricPotential>,
-    energy: LazyCell<Energy>,
-    energy_full: LazyCell<Energy>,
-    energy_full_design: LazyCell<Energy>,
-    energy_rate: LazyCell<Power>,
+    energy: LazyCell<Reading<Energy>>,
+    energy_full: LazyCell<Reading<Energy>>,
+    energy_full_design: LazyCell<Reading<Energy>>,
+    energy_rate: LazyCell<Reading<Power>>,
 
-    state_of_health: LazyCell<Ratio>,
-    state_of_charge: LazyCell<Ratio>,
+    state_of_health: LazyCell<Reading<Ratio>>,
+    state_of_charge: LazyCell<Reading<Ratio>>,
 
     state: LazyCell<State>,
+
+    // Origins of the values calculated so far
+    traces: RefCell<Vec<Trace>>,
 }
 
 impl<'p> DataBuilder<'p> {
     pub fn new(path: &'p Path) -> DataBuilder<'p> {
         DataBuilder {
             root: path,
+            tracker: None,
+            corrections: &[],
 
             design_voltage: LazyCell::new(),
             energy: LazyCell::new(),
@@ -53,11 +94,39 @@ impl<'p> DataBuilder<'p> {
             state_of_health: LazyCell::new(),
             state_of_charge: LazyCell::new(),
             state: LazyCell::new(),
+            traces: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn with_tracker(path: &'p Path, tracker: Tracker) -> DataBuilder<'p> {
+        DataBuilder {
+            tracker: Some(tracker),
+            ..DataBuilder::new(path)
         }
     }
 
+    /// Applies quirks `corrections` to the device attributes.
+    pub fn with_corrections(self, corrections: &'p [Correction]) -> DataBuilder<'p> {
+        DataBuilder {
+            corrections,
+            ..self
+        }
+    }
+
+    /// Returns path to the device attribute, marking it as the one being read right now.
+    fn path(&self, attribute: &str) -> PathBuf {
+        let path = self.root.join(attribute);
+        if let Some(tracker) = &self.tracker {
+            if let Ok(mut current) = tracker.lock() {
+                *current = Some(path.clone());
+            }
+        }
+
+        path
+    }
+
     pub fn collect(self) -> Result<InstantData> {
-        Ok(InstantData {
+        let mut data = InstantData {
             state_of_charge: *self.state_of_charge()?,
             state_of_health: *self.state_of_health()?,
             energy: *self.energy()?,
@@ -68,159 +137,401 @@ impl<'p> DataBuilder<'p> {
             state: *self.state()?,
             temperature: self.temperature()?,
             cycle_count: self.cycle_count()?,
-        })
+            diagnostics: Diagnostics::default(),
+        };
+        data.diagnostics = self.diagnostics(Fields::ALL);
+
+        Ok(data)
     }
 
-    fn design_voltage(&self) -> Result<&ElectricPotential> {
-        self.design_voltage.try_borrow_with(|| {
-            let value = [
-                "voltage_max_design",
-                "voltage_min_design",
-                "voltage_present",
-                "voltage_now",
-            ]
-            .iter()
-            .filter_map(|filename| match fs::voltage(self.root.join(filename)) {
-                Ok(Some(value)) => Some(value),
-                _ => None,
-            })
-            .next();
+    /// Takes the traces of values belonging to the `fields`.
+    ///
+    /// Values which were calculated only as a dependency of the requested ones are skipped,
+    /// since they are not updated in the `InstantData`.
+    fn diagnostics(self, fields: Fields) -> Diagnostics {
+        let traces = self
+            .traces
+            .into_inner()
+            .into_iter()
+            .filter(|trace| Self::fields_of(trace.quantity()).intersects(fields))
+            .collect();
+
+        Diagnostics::new(traces)
+    }
+
+    fn fields_of(quantity: Quantity) -> Fields {
+        match quantity {
+            Quantity::State => Fields::STATE,
+            Quantity::StateOfCharge => Fields::CHARGE,
+            Quantity::EnergyRate => Fields::ENERGY_RATE,
+            Quantity::Voltage => Fields::VOLTAGE,
+            Quantity::Temperature => Fields::TEMPERATURE,
+            Quantity::CycleCount => Fields::CYCLE_COUNT,
+            Quantity::StateOfHealth | Quantity::Energy | Quantity::EnergyFull | Quantity::EnergyFullDesign => {
+                Fields::ENERGY
+            }
+            // Intermediate value, which is used by any of them
+            _ => Fields::ALL,
+        }
+    }
+
+    /// Updates only the requested `fields` of the already collected `data`.
+    ///
+    /// Values are read into a copy first, so `data` is left untouched if any read fails.
+    pub fn collect_into(self, data: &mut InstantData, fields: Fields) -> Result<()> {
+        let fields = fields.with_implied();
+        let mut updated = data.clone();
+
+        if fields.contains(Fields::STATE) {
+            updated.state = *self.state()?;
+        }
+        if fields.contains(Fields::CHARGE) {
+            updated.state_of_charge = *self.state_of_charge()?;
+        }
+        if fields.contains(Fields::ENERGY) {
+            updated.energy = *self.energy()?;
+            updated.energy_full = *self.energy_full()?;
+            updated.energy_full_design = *self.energy_full_design()?;
+            updated.state_of_health = *self.state_of_health()?;
+        }
+        if fields.contains(Fields::ENERGY_RATE) {
+            updated.energy_rate = *self.energy_rate()?;
+        }
+        if fields.contains(Fields::VOLTAGE) {
+            updated.voltage = self.voltage()?;
+        }
+        if fields.contains(Fields::TEMPERATURE) {
+            updated.temperature = self.temperature()?;
+        }
+        if fields.contains(Fields::CYCLE_COUNT) {
+            updated.cycle_count = self.cycle_count()?;
+        }
+
+        updated.diagnostics.merge(self.diagnostics(fields));
+        *data = updated;
+
+        Ok(())
+    }
+
+    /// Updates requested `fields` of the `data`, skipping the values which can't be loaded.
+    ///
+    /// Values which failed to load are keeping their previous values
+    /// and returned warnings are describing what had happened with them.
+    pub fn collect_lenient(self, data: &mut InstantData, fields: Fields) -> Vec<Warning> {
+        fn update<T>(warnings: &mut Vec<Warning>, field: Fields, value: Result<T>, target: &mut T) {
             match value {
-                Some(voltage) => Ok(voltage),
-                None => Err(io::Error::from(io::ErrorKind::NotFound).into()),
+                Ok(value) => *target = value,
+                Err(e) => warnings.push(Warning::new(field, e)),
             }
-        })
+        }
+        fn update_copy<T: Copy>(warnings: &mut Vec<Warning>, field: Fields, value: Result<&T>, target: &mut T) {
+            match value {
+                Ok(value) => *target = *value,
+                Err(e) => warnings.push(Warning::new(field, e)),
+            }
+        }
+
+        let fields = fields.with_implied();
+        let mut warnings = Vec::new();
+
+        if fields.contains(Fields::STATE) {
+            update_copy(&mut warnings, Fields::STATE, self.state(), &mut data.state);
+        }
+        if fields.contains(Fields::CHARGE) {
+            let value = self.state_of_charge();
+            update_copy(&mut warnings, Fields::CHARGE, value, &mut data.state_of_charge);
+        }
+        if fields.contains(Fields::ENERGY) {
+            update_copy(&mut warnings, Fields::ENERGY, self.energy(), &mut data.energy);
+            let value = self.energy_full();
+            update_copy(&mut warnings, Fields::ENERGY, value, &mut data.energy_full);
+            let value = self.energy_full_design();
+            update_copy(&mut warnings, Fields::ENERGY, value, &mut data.energy_full_design);
+            let value = self.state_of_health();
+            update_copy(&mut warnings, Fields::ENERGY, value, &mut data.state_of_health);
+        }
+        if fields.contains(Fields::ENERGY_RATE) {
+            let value = self.energy_rate();
+            update_copy(&mut warnings, Fields::ENERGY_RATE, value, &mut data.energy_rate);
+        }
+        if fields.contains(Fields::VOLTAGE) {
+            update(&mut warnings, Fields::VOLTAGE, self.voltage(), &mut data.voltage);
+        }
+        if fields.contains(Fields::TEMPERATURE) {
+            let value = self.temperature();
+            update(&mut warnings, Fields::TEMPERATURE, value, &mut data.temperature);
+        }
+        if fields.contains(Fields::CYCLE_COUNT) {
+            let value = self.cycle_count();
+            update(&mut warnings, Fields::CYCLE_COUNT, value, &mut data.cycle_count);
+        }
+
+        data.diagnostics.merge(self.diagnostics(fields));
+
+        warnings
     }
 
-    // Not cached because used only once
-    // IO errors are ignored, since later calculations will handle `None` result
-    fn energy_now(&self) -> Option<Energy> {
-        ["energy_now", "energy_avg"]
+    /// Reads raw `attribute` value.
+    fn read(&self, attribute: &str) -> Result<Option<String>> {
+        fs::get_string(self.path(attribute)).map_err(|e| self.error(e))
+    }
+
+    /// Adds device name to the error.
+    fn error(&self, e: Error) -> Error {
+        e.with_device(fs::device_name(self.root))
+    }
+
+    /// Reads raw `attribute` value with the quirks corrections applied, noting them in the `trace`.
+    ///
+    /// Corrections other than the units swap are applied by the name of the file actually read.
+    fn corrected(&self, trace: &mut Trace, attribute: &str) -> Result<Option<String>> {
+        let swapped = self.corrections.iter().any(|correction| match correction {
+            Correction::SwapUnits { attribute: swap } => {
+                swap == attribute || counterpart(swap).as_ref().map(AsRef::as_ref) == Some(attribute)
+            }
+            _ => false,
+        });
+        let file = match counterpart(attribute) {
+            Some(counterpart) if swapped => counterpart,
+            _ => attribute.to_string(),
+        };
+        let corrections = self
+            .corrections
             .iter()
-            .filter_map(|filename| match fs::energy(self.root.join(filename)) {
-                Ok(Some(value)) => Some(value),
-                _ => None,
-            })
-            .next()
+            .filter(|correction| correction.attribute() == file);
+
+        let raw = match self.read(&file)? {
+            Some(raw) => raw,
+            None => return Ok(None),
+        };
+        let mut value = raw.clone();
+        for correction in corrections {
+            let number = value.trim().parse::<f64>().ok();
+            value = match (correction, number) {
+                (Correction::Ignore { .. }, _) => {
+                    trace.adjust(Adjustment::Ignored {
+                        attribute: attribute.to_string(),
+                        raw,
+                    });
+                    return Ok(None);
+                }
+                (Correction::IgnoreBelow { threshold, .. }, Some(number)) if number <= f64::from(*threshold) => {
+                    trace.adjust(Adjustment::Ignored {
+                        attribute: attribute.to_string(),
+                        raw,
+                    });
+                    return Ok(None);
+                }
+                (Correction::InvertSign { .. }, Some(number)) => format_number(-number),
+                (Correction::Scale { factor, .. }, Some(number)) => format_number(number * f64::from(*factor)),
+                _ => value,
+            };
+        }
+        if swapped || value != raw {
+            trace.adjust(Adjustment::Corrected {
+                attribute: attribute.to_string(),
+                raw,
+            });
+        }
+
+        Ok(Some(value))
     }
 
-    // Not cached because used only once.
-    // IO errors are ignored, since later calculations will handle `None` result
-    fn charge_now(&self) -> Option<ElectricCharge> {
-        ["charge_now", "charge_avg"]
+    /// Reads `attribute` and parses it with `parse`, noting the outcome in the `trace`.
+    ///
+    /// Existing values which are rejected by `parse` are treated as missing ones.
+    fn attribute<T, F>(&self, trace: &mut Trace, attribute: &str, parse: F) -> Result<Option<T>>
+    where
+        F: Fn(&str) -> Option<T>,
+    {
+        match self.corrected(trace, attribute)? {
+            Some(raw) => match parse(&raw) {
+                Some(value) => {
+                    trace.source(attribute, raw);
+                    Ok(Some(value))
+                }
+                None => {
+                    trace.adjust(Adjustment::Ignored {
+                        attribute: attribute.to_string(),
+                        raw,
+                    });
+                    Ok(None)
+                }
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Returns first existing value from the `attributes` list.
+    ///
+    /// IO errors are ignored, since later calculations will handle `None` result.
+    fn first<T, F>(&self, trace: &mut Trace, attributes: &[&str], parse: F) -> Option<T>
+    where
+        F: Fn(&str) -> Option<T>,
+    {
+        attributes
             .iter()
-            .filter_map(|filename| match fs::charge(self.root.join(filename)) {
+            .filter_map(|attribute| match self.attribute(trace, attribute, &parse) {
                 Ok(Some(value)) => Some(value),
                 _ => None,
             })
             .next()
     }
 
+    fn record(&self, trace: Trace) {
+        self.traces.borrow_mut().push(trace);
+    }
+
+    /// Bounds `ratio` into the `0.0..=1.0` range, noting the clamp in the `trace`.
+    fn bounded(trace: &mut Trace, ratio: Ratio) -> Ratio {
+        let bounded = ratio.into_bounded();
+        if bounded != ratio {
+            trace.adjust(Adjustment::Clamped {
+                raw: ratio.value,
+            });
+        }
+
+        bounded
+    }
+
+    fn design_voltage(&self) -> Result<&ElectricPotential> {
+        self.design_voltage.try_borrow_with(|| {
+            let mut trace = Trace::new(Quantity::DesignVoltage);
+            let attributes = ["voltage_max_design", "voltage_min_design", "voltage_present", "voltage_now"];
+            match self.first(&mut trace, &attributes, fs::voltage) {
+                Some(voltage) => {
+                    self.record(trace);
+                    Ok(voltage)
+                }
+                None => Err(self.error(Error::not_found("Unable to find device design voltage value"))),
+            }
+        })
+    }
+
     // Not cached because used only once
-    fn charge_full(&self) -> ElectricCharge {
-        ["charge_full", "charge_full_design"]
-            .iter()
-            .filter_map(|filename| match fs::charge(self.root.join(filename)) {
-                Ok(Some(value)) => Some(value),
-                _ => None,
-            })
-            .next()
-            .unwrap_or_else(|| microampere_hour!(0.0))
+    fn energy_now(&self, trace: &mut Trace) -> Option<Energy> {
+        self.first(trace, &["energy_now", "energy_avg"], fs::energy)
+    }
+
+    // Not cached because used only once.
+    fn charge_now(&self, trace: &mut Trace) -> Option<ElectricCharge> {
+        self.first(trace, &["charge_now", "charge_avg"], fs::charge)
     }
 
-    pub fn state_of_health(&self) -> Result<&Ratio> {
+    pub fn state_of_health(&self) -> Result<&Reading<Ratio>> {
         self.state_of_health.try_borrow_with(|| {
-            let energy_full = self.energy_full()?;
-            if !energy_full.is_zero() {
-                let energy_full_design = self.energy_full_design()?;
-                Ok((*energy_full / *energy_full_design).into_bounded())
-            } else {
-                Ok(percent!(100.0))
-            }
+            let mut trace = Trace::new(Quantity::StateOfHealth);
+            let energy_full = self.energy_full()?.value();
+            let energy_full_design = self.energy_full_design()?.value();
+            let value = match (energy_full, energy_full_design) {
+                (Some(energy_full), Some(energy_full_design))
+                    if !energy_full.is_zero() && !energy_full_design.is_zero() =>
+                {
+                    trace.convert(Conversion::EnergyFullByDesign);
+                    Reading::Derived(Self::bounded(&mut trace, energy_full / energy_full_design))
+                }
+                _ => Reading::Unavailable,
+            };
+            self.record(trace);
+
+            Ok(value)
         })
     }
 
-    fn energy(&self) -> Result<&Energy> {
-        self.energy.try_borrow_with(|| match self.energy_now() {
-            Some(energy) => Ok(energy),
-            None => match self.charge_now() {
-                Some(charge) => Ok(charge * *self.design_voltage()?),
-                None => match fs::get::<f32, _>(self.root.join("capacity")) {
-                    Ok(Some(capacity)) => Ok(*self.energy_full()? * percent!(capacity).into_bounded()),
-                    _ => Err(Error::not_found("Unable to calculate device energy value")),
+    fn energy(&self) -> Result<&Reading<Energy>> {
+        self.energy.try_borrow_with(|| {
+            let mut trace = Trace::new(Quantity::Energy);
+            let value = match self.energy_now(&mut trace) {
+                Some(energy) => Reading::Reported(energy),
+                None => match self.charge_now(&mut trace) {
+                    Some(charge) => {
+                        trace.convert(Conversion::ChargeByDesignVoltage);
+                        Reading::Derived(charge * *self.design_voltage()?)
+                    }
+                    None => match self.attribute(&mut trace, "capacity", fs::parse::<f32>) {
+                        Ok(Some(capacity)) => {
+                            trace.convert(Conversion::CapacityByEnergyFull);
+                            let capacity = Self::bounded(&mut trace, percent!(capacity));
+                            self.energy_full()?.map(|energy_full| energy_full * capacity).derived()
+                        }
+                        _ => {
+                            // Keeping the trace, so it is possible to tell what was tried
+                            self.record(trace);
+                            let e = Error::not_found("Unable to calculate device energy value");
+                            return Err(self.error(e));
+                        }
+                    },
                 },
-            },
+            };
+            self.record(trace);
+
+            Ok(value)
         })
     }
 
-    fn energy_full(&self) -> Result<&Energy> {
-        self.energy_full
-            .try_borrow_with(|| match fs::energy(self.root.join("energy_full")) {
-                Ok(Some(value)) => Ok(value),
-                Ok(None) => match fs::charge(self.root.join("charge_full")) {
-                    Ok(Some(value)) => Ok(value * *self.design_voltage()?),
-                    Ok(None) => Ok(*self.energy_full_design()?),
-                    Err(e) => Err(e),
+    fn energy_full(&self) -> Result<&Reading<Energy>> {
+        self.energy_full.try_borrow_with(|| {
+            let mut trace = Trace::new(Quantity::EnergyFull);
+            let value = match self.attribute(&mut trace, "energy_full", fs::energy)? {
+                Some(value) => Reading::Reported(value),
+                None => match self.attribute(&mut trace, "charge_full", fs::charge)? {
+                    Some(value) => {
+                        trace.convert(Conversion::ChargeByDesignVoltage);
+                        Reading::Derived(value * *self.design_voltage()?)
+                    }
+                    None => {
+                        let value = self.energy_full_design()?.derived();
+                        if value.is_available() {
+                            trace.convert(Conversion::FromEnergyFullDesign);
+                        }
+                        value
+                    }
                 },
-                Err(e) => Err(e),
-            })
+            };
+            self.record(trace);
+
+            Ok(value)
+        })
     }
 
-    fn energy_full_design(&self) -> Result<&Energy> {
+    fn energy_full_design(&self) -> Result<&Reading<Energy>> {
         self.energy_full_design.try_borrow_with(|| {
-            match fs::energy(self.root.join("energy_full_design")) {
-                Ok(Some(value)) => Ok(value),
-                Ok(None) => match fs::charge(self.root.join("charge_full_design")) {
-                    Ok(Some(value)) => Ok(value * *self.design_voltage()?),
+            let mut trace = Trace::new(Quantity::EnergyFullDesign);
+            let value = match self.attribute(&mut trace, "energy_full_design", fs::energy)? {
+                Some(value) => Reading::Reported(value),
+                None => match self.attribute(&mut trace, "charge_full_design", fs::charge)? {
+                    Some(value) => {
+                        trace.convert(Conversion::ChargeByDesignVoltage);
+                        Reading::Derived(value * *self.design_voltage()?)
+                    }
                     // It is possible that both `energy_full_design` and `charge_full_design`
                     // files might be missing, see #40.
-                    // As a workaround, doing the same what `upower` does - falling back to zero value
-                    // It will affect other parameters calculation,
-                    // and in a future versions this function probably should return
-                    // `Result<Option<Energy>>` instead to mark missing value.
-                    Ok(None) => Ok(microwatt_hour!(0.0)),
-                    Err(e) => Err(e),
+                    None => Reading::Unavailable,
                 },
-                Err(e) => Err(e),
-            }
+            };
+            self.record(trace);
+
+            Ok(value)
         })
     }
 
-    fn energy_rate(&self) -> Result<&Power> {
+    fn energy_rate(&self) -> Result<&Reading<Power>> {
         self.energy_rate.try_borrow_with(|| {
-            let value = match fs::power(self.root.join("power_now"))? {
-                Some(power) => Some(power),
-                None => {
-                    match fs::get::<f32, _>(self.root.join("current_now"))? {
-                        Some(current_now) => {
-                            // If charge_full exists, then current_now is always reported in µA.
-                            // In the legacy case, where energy only units exist, and power_now isn't present
-                            // current_now is power in µW.
-                            // Source: upower
-                            if !self.charge_full().is_zero() {
-                                // µA then
-                                Some(microampere!(current_now) * *self.design_voltage()?)
-                            } else {
-                                // µW :|
-                                Some(microwatt!(current_now))
-                            }
-                        }
-                        None => None,
+            let mut trace = Trace::new(Quantity::EnergyRate);
+            let value = match self.attribute(&mut trace, "power_now", fs::power)? {
+                Some(power) => Reading::Reported(power),
+                // Legacy drivers are reporting power in the `current_now`,
+                // which is handled by the builtin quirks
+                None => match self.attribute(&mut trace, "current_now", fs::parse::<f32>)? {
+                    Some(current_now) => {
+                        trace.convert(Conversion::CurrentByDesignVoltage);
+                        Reading::Derived(microampere!(current_now) * *self.design_voltage()?)
                     }
-                }
+                    None => Reading::Unavailable,
+                },
             };
 
-            let value = value
-                // Sanity check if power is greater than 100W (upower)
-                .map(|power| if power.get::<watt>() > 100.0 { watt!(0.0) } else { power })
-                // Some batteries give out massive rate values when nearly empty (upower)
-                .map(|power| {
-                    if power.get::<microwatt>() < 10.0 {
-                        watt!(0.0)
-                    } else {
-                        power
-                    }
-                })
+            let adjustment = match value.value() {
                 // ACPI gives out the special 'Ones' (Constant Ones Object) value for rate
                 // when it's unable to calculate the true rate. We should set the rate zero,
                 // and wait for the BIOS to stabilise.
@@ -228,105 +539,178 @@ impl<'p> DataBuilder<'p> {
                 //
                 // It come as an `0xffff` originally, but we are operating with `Power` now,
                 // so this `Ones` value is recalculated a little.
-                .map(|power| {
-                    // TODO: There might be a chance that we had lost a precision during the conversion
-                    // from the microwatts into default watts, so this should be fixed
-                    if (power.get::<watt>() - 65535.0).abs() < f32::EPSILON {
-                        watt!(0.0)
-                    } else {
-                        power
-                    }
-                })
-                .unwrap_or_else(|| microwatt!(0.0));
+                //
+                // Checked before the upper limit, since it is above it too.
+                //
+                // TODO: There might be a chance that we had lost a precision during the conversion
+                // from the microwatts into default watts, so this should be fixed
+                Some(power) if (power.get::<watt>() - 65535.0).abs() < f32::EPSILON => {
+                    Some(Adjustment::RateUnknownSentinel)
+                }
+                // Sanity check if power is greater than 100W (upower)
+                Some(power) if power.get::<watt>() > 100.0 => Some(Adjustment::RateTooHigh {
+                    raw: power.get::<watt>(),
+                }),
+                // Some batteries give out massive rate values when nearly empty (upower)
+                Some(power) if power.get::<microwatt>() < 10.0 => Some(Adjustment::RateTooLow {
+                    raw: power.get::<watt>(),
+                }),
+                _ => None,
+            };
+            let value = match adjustment {
+                Some(adjustment) => {
+                    trace.adjust(adjustment);
+                    Reading::Unavailable
+                }
+                None => value,
+            };
 
             // TODO: Calculate energy_rate manually, if hardware fails.
             // if value < 0.01 {
             //    // Check upower `up_device_supply_calculate_rate` function
             // }
 
+            self.record(trace);
+
             Ok(value)
         })
     }
 
-    fn state_of_charge(&self) -> Result<&Ratio> {
+    fn state_of_charge(&self) -> Result<&Reading<Ratio>> {
         self.state_of_charge.try_borrow_with(|| {
-            match fs::get::<f32, _>(self.root.join("capacity")) {
-                Ok(Some(capacity)) => Ok(percent!(capacity).into_bounded()),
-                Ok(None) if self.energy_full()?.is_sign_positive() => Ok(*self.energy()? / *self.energy_full()?),
-                // Same as upower, falling back to 0.0%
-                Ok(None) => Ok(percent!(0.0)),
-                Err(e) => Err(e),
-            }
+            let mut trace = Trace::new(Quantity::StateOfCharge);
+            let value = match self.attribute(&mut trace, "capacity", fs::parse::<f32>)? {
+                Some(capacity) => Reading::Reported(Self::bounded(&mut trace, percent!(capacity))),
+                None => match self.energy_full()?.value() {
+                    Some(energy_full) if energy_full.is_sign_positive() && !energy_full.is_zero() => {
+                        trace.convert(Conversion::EnergyByEnergyFull);
+                        self.energy()?.map(|energy| energy / energy_full).derived()
+                    }
+                    _ => Reading::Unavailable,
+                },
+            };
+            self.record(trace);
+
+            Ok(value)
         })
     }
 
     fn state(&self) -> Result<&State> {
-        self.state
-            .try_borrow_with(|| match fs::get::<State, _>(self.root.join("status")) {
-                Ok(Some(state)) => Ok(state),
-                Ok(None) => Ok(State::Unknown),
-                Err(e) => Err(e),
-            })
-    }
+        self.state.try_borrow_with(|| {
+            let mut trace = Trace::new(Quantity::State);
+            let value = self.attribute(&mut trace, "status", fs::parse::<State>)?;
+            self.record(trace);
 
-    fn voltage(&self) -> Result<ElectricPotential> {
-        let mut value =
-            ["voltage_now", "voltage_avg"]
-                .iter()
-                .filter_map(|filename| match fs::voltage(self.root.join(filename)) {
-                    Ok(Some(value)) => Some(value),
-                    _ => None,
-                });
+            Ok(value.unwrap_or(State::Unknown))
+        })
+    }
 
-        match value.next() {
-            Some(value) => Ok(value),
-            None => Err(Error::not_found("Unable to calculate device voltage value")),
+    fn voltage(&self) -> Result<Reading<ElectricPotential>> {
+        let mut trace = Trace::new(Quantity::Voltage);
+        let value = self.first(&mut trace, &["voltage_now", "voltage_avg"], fs::voltage);
+        self.record(trace);
+        match value {
+            Some(value) => Ok(Reading::Reported(value)),
+            None => Err(self.error(Error::not_found("Unable to calculate device voltage value"))),
         }
     }
 
-    fn temperature(&self) -> Result<Option<ThermodynamicTemperature>> {
-        match fs::get::<f32, _>(self.root.join("temp")) {
-            Ok(Some(value)) => Ok(Some(celsius!(value / 10.0))),
-            Ok(None) => Ok(None),
-            Err(e) => Err(e),
-        }
+    fn temperature(&self) -> Result<Reading<ThermodynamicTemperature>> {
+        let mut trace = Trace::new(Quantity::Temperature);
+        let value = self.attribute(&mut trace, "temp", fs::parse::<f32>)?;
+        self.record(trace);
+
+        Ok(Reading::from(value.map(|value| celsius!(value / 10.0))))
     }
 
-    fn cycle_count(&self) -> Result<Option<u32>> {
-        fs::get::<u32, _>(self.root.join("cycle_count")).map(|value| {
-            // Handling zero cycles count as a non-existing value.
-            // Reason: some drivers are creating `cycle_count` with zero value
-            // even for old batteries.
-            // Since it is more often occasion than using fresh battery with zero cycles
-            // (real one this time), it is better just to ignore this value.
-            // See: https://github.com/svartalf/rust-battery/issues/23
-            match value {
-                Some(cycles) if cycles == 0 => None,
-                Some(cycles) => Some(cycles),
-                None => None,
-            }
-        })
+    fn cycle_count(&self) -> Result<Reading<u32>> {
+        let mut trace = Trace::new(Quantity::CycleCount);
+        // Handling zero cycles count as a non-existing value.
+        // Reason: some drivers are creating `cycle_count` with zero value
+        // even for old batteries.
+        // Since it is more often occasion than using fresh battery with zero cycles
+        // (real one this time), it is better just to ignore this value.
+        // See: https://github.com/svartalf/rust-battery/issues/23
+        let parse = |raw: &str| fs::parse::<u32>(raw).filter(|cycles| *cycles != 0);
+        let value = self.attribute(&mut trace, "cycle_count", parse)?;
+        self.record(trace);
+
+        Ok(Reading::from(value))
     }
 
     // Following methods are not cached in the struct
 
     pub fn manufacturer(&self) -> Result<Option<String>> {
-        fs::get_string(self.root.join("manufacturer"))
+        self.read("manufacturer")
     }
 
     pub fn model(&self) -> Result<Option<String>> {
-        fs::get_string(self.root.join("model_name"))
+        self.read("model_name")
     }
 
     pub fn serial_number(&self) -> Result<Option<String>> {
-        fs::get_string(self.root.join("serial_number"))
+        self.read("serial_number")
     }
 
-    pub fn technology(&self) -> Result<Technology> {
-        match fs::get::<Technology, _>(self.root.join("technology")) {
-            Ok(Some(tech)) => Ok(tech),
-            Ok(None) => Ok(Technology::Unknown),
-            Err(e) => Err(e),
+    /// Name of the device driver, used to match quirks.
+    pub fn driver(&self) -> Option<String> {
+        let driver = std::fs::read_link(self.root.join("device").join("driver")).ok()?;
+        driver.file_name().map(|name| name.to_string_lossy().into_owned())
+    }
+
+    /// Names of the attributes provided by the device, used to match quirks.
+    pub fn attributes(&self) -> Vec<String> {
+        match std::fs::read_dir(self.root) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .collect(),
+            Err(_) => Vec::new(),
         }
     }
+
+    /// Numeric values of the `attributes`, used to match quirks.
+    ///
+    /// Attributes which are missing or can't be parsed are skipped.
+    pub fn values(&self, attributes: &[&str]) -> Vec<(String, f32)> {
+        attributes
+            .iter()
+            .filter_map(|attribute| {
+                let value = self.read(attribute).ok()?.and_then(|value| fs::parse::<f32>(&value))?;
+                Some((attribute.to_string(), value))
+            })
+            .collect()
+    }
+
+    /// System vendor as reported by DMI, used to match quirks.
+    pub fn sys_vendor(&self) -> Option<String> {
+        // `/sys/class/power_supply/<name>` -> `/sys/class/dmi/id/sys_vendor`;
+        // paths are joined lexically, since the device directory is a symlink
+        let class = self.root.parent()?.parent()?;
+        fs::get_string(class.join("dmi").join("id").join("sys_vendor")).ok()?
+    }
+
+    pub fn technology(&self) -> Result<Technology> {
+        let value = self.read("technology")?;
+        Ok(value.and_then(|value| fs::parse(&value)).unwrap_or(Technology::Unknown))
+    }
+}
+
+/// Returns name of the attribute with the "sibling" units: `energy_*` for `charge_*`,
+/// `power_*` for `current_*` and vice versa.
+fn counterpart(attribute: &str) -> Option<String> {
+    let pairs = [("energy_", "charge_"), ("charge_", "energy_"), ("power_", "current_"), ("current_", "power_")];
+    pairs
+        .iter()
+        .find(|(from, _)| attribute.starts_with(from))
+        .map(|(from, to)| format!("{}{}", to, &attribute[from.len()..]))
+}
+
+/// Formats corrected value the same way as drivers do, without the fractional part if possible.
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < std::i64::MAX as f64 {
+        format!("{}", value as i64)
+    } else {
+        format!("{}", value)
+    }
 }
@@ -0,0 +1,106 @@
+use std::fs;
+
+use super::super::SysFsDevice;
+use crate::diagnostics::Quantity;
+use crate::platform::traits::BatteryDevice;
+use crate::Anomaly;
+use crate::quirks::Quirks;
+
+fn anomalies(device: &SysFsDevice) -> Vec<Anomaly> {
+    Anomaly::detect(&device.readings(), device.state(), &device.diagnostics())
+}
+
+#[test]
+fn test_no_anomalies() {
+    let root = sysfs_test_suite!(
+        "type" => "Battery",
+        "status" => "Discharging",
+        "energy_now" => 25000000,
+        "energy_full" => 50000000,
+        "energy_full_design" => 52000000,
+        "power_now" => 10000000,
+        "voltage_now" => 12000000
+    );
+
+    let path = root.keep();
+    let device = SysFsDevice::try_from(path.clone(), &Quirks::new()).unwrap();
+
+    assert!(anomalies(&device).is_empty());
+
+    fs::remove_dir_all(path).unwrap();
+}
+
+#[test]
+fn test_energy_anomalies() {
+    let root = sysfs_test_suite!(
+        "type" => "Battery",
+        "status" => "Discharging",
+        "capacity" => 120,
+        "energy_now" => 60000000,
+        "energy_full" => 50000000,
+        "energy_full_design" => 30000000,
+        "power_now" => 65535000000u64,
+        "voltage_now" => 12000000
+    );
+
+    let path = root.keep();
+    let device = SysFsDevice::try_from(path.clone(), &Quirks::new()).unwrap();
+    let anomalies = anomalies(&device);
+
+    assert!(anomalies.iter().any(|a| match a {
+        Anomaly::EnergyAboveFull { .. } => true,
+        _ => false,
+    }));
+    assert!(anomalies.iter().any(|a| match a {
+        Anomaly::EnergyFullAboveDesign { .. } => true,
+        _ => false,
+    }));
+    assert!(anomalies.contains(&Anomaly::UnknownRateSentinel));
+    assert!(anomalies.iter().any(|a| match a {
+        Anomaly::RatioOutOfRange {
+            quantity: Quantity::StateOfCharge,
+            ..
+        } => true,
+        _ => false,
+    }));
+
+    fs::remove_dir_all(path).unwrap();
+}
+
+#[test]
+fn test_charging_with_negative_current() {
+    let root = sysfs_test_suite!(
+        "type" => "Battery",
+        "status" => "Charging",
+        "charge_now" => 1500000,
+        "charge_full" => 3000000,
+        "current_now" => -1500000,
+        "voltage_min_design" => 11400000,
+        "voltage_now" => 12000000
+    );
+
+    let path = root.keep();
+    let device = SysFsDevice::try_from(path.clone(), &Quirks::new()).unwrap();
+
+    assert_eq!(anomalies(&device), vec![Anomaly::ChargingWithNegativeCurrent]);
+
+    fs::remove_dir_all(path).unwrap();
+}
+
+#[test]
+fn test_zero_voltage() {
+    let root = sysfs_test_suite!(
+        "type" => "Battery",
+        "status" => "Discharging",
+        "energy_now" => 25000000,
+        "energy_full" => 50000000,
+        "voltage_now" => 0
+    );
+
+    let path = root.keep();
+    let device = SysFsDevice::try_from_lenient(path.clone(), &Quirks::new());
+
+    assert_eq!(anomalies(&device), vec![Anomaly::ZeroVoltage]);
+
+    fs::remove_dir_all(path).unwrap();
+}
@@ -0,0 +1,96 @@
+use std::fs;
+use std::time::Duration;
+
+use approx::assert_abs_diff_eq;
+
+use crate::archive::{Archive, Frame, Recorder, Replay};
+use crate::State;
+
+fn battery_frame(elapsed: u64, status: &str, energy_now: &str) -> Frame {
+    let mut frame = Frame::new(Duration::from_millis(elapsed));
+    frame.set("BAT0", "type", "Battery").unwrap();
+    frame.set("BAT0", "status", status).unwrap();
+    frame.set("BAT0", "energy_now", energy_now).unwrap();
+    frame.set("BAT0", "energy_full", "50000000").unwrap();
+    frame.set("BAT0", "voltage_now", "12000000").unwrap();
+    frame
+}
+
+#[test]
+fn test_capture() {
+    let root = tempfile::tempdir().unwrap();
+    let path = root.path().join("BAT0");
+    fs::create_dir(&path).unwrap();
+    fs::write(path.join("type"), "Battery\n").unwrap();
+    fs::write(path.join("energy_now"), "10000000\n").unwrap();
+    fs::create_dir(path.join("power")).unwrap();
+
+    let mut recorder = Recorder::with_root(root.path());
+    recorder.capture().unwrap();
+    recorder.capture().unwrap();
+
+    let archive = recorder.into_archive();
+    assert_eq!(archive.frames().len(), 2);
+    let frame = &archive.frames()[0];
+    assert_eq!(frame.get("BAT0", "type"), Some("Battery"));
+    assert_eq!(frame.get("BAT0", "energy_now"), Some("10000000"));
+    assert_eq!(frame.devices()["BAT0"].len(), 2);
+}
+
+#[test]
+fn test_round_trip() {
+    let mut archive = Archive::new();
+    let mut frame = battery_frame(0, "Discharging", "10000000");
+    frame.set("BAT0", "model_name", "multi\nline \\ value").unwrap();
+    archive.push(frame);
+    archive.push(battery_frame(1500, "Charging", "11000000"));
+
+    let mut buffer = Vec::new();
+    archive.write_to(&mut buffer).unwrap();
+    let restored = Archive::read_from(buffer.as_slice()).unwrap();
+    assert_eq!(restored, archive);
+    assert_eq!(restored.frames()[1].elapsed(), Duration::from_millis(1500));
+
+    let e = Archive::read_from("BAT0/type=Battery\n".as_bytes()).unwrap_err();
+    assert_eq!(e.raw_value(), Some("BAT0/type=Battery"));
+}
+
+#[test]
+fn test_invalid_names() {
+    let mut frame = Frame::new(Duration::default());
+    assert!(frame.set("..", "type", "Battery").is_err());
+    assert!(frame.set("BAT0", "../type", "Battery").is_err());
+    assert!(frame.set("/tmp", "type", "Battery").is_err());
+    assert!(frame.devices().is_empty());
+
+    let e = Archive::read_from("frame 0\nBAT0/../../type=Battery\n".as_bytes()).unwrap_err();
+    assert_eq!(e.raw_value(), Some("BAT0/../../type=Battery"));
+    assert!(Archive::read_from("frame 0\n../type=Battery\n".as_bytes()).is_err());
+}
+
+#[test]
+fn test_replay() {
+    let mut archive = Archive::new();
+    archive.push(battery_frame(0, "Discharging", "10000000"));
+    archive.push(battery_frame(1000, "Charging", "20000000"));
+    archive.push(Frame::new(Duration::from_millis(2000)));
+
+    let mut replay = Replay::new(archive).unwrap();
+    let manager = replay.manager().unwrap();
+    let mut battery = manager.batteries().unwrap().next().unwrap().unwrap();
+    assert_eq!(battery.state(), State::Discharging);
+    assert_abs_diff_eq!(battery.energy().value, 36000.0);
+
+    assert!(replay.advance().unwrap());
+    manager.refresh(&mut battery).unwrap();
+    assert_eq!(battery.state(), State::Charging);
+    assert_abs_diff_eq!(battery.energy().value, 72000.0);
+
+    assert!(replay.advance().unwrap());
+    assert!(manager.refresh(&mut battery).unwrap_err().is_removed());
+    assert!(!replay.advance().unwrap());
+
+    let root = replay.root().to_path_buf();
+    drop(replay);
+    assert!(!root.exists());
+}
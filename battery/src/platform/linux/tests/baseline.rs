@@ -0,0 +1,63 @@
+use approx::assert_abs_diff_eq;
+
+use crate::baseline::{Baselines, Source};
+use crate::test_util::{FakePowerSupply, FakeSysfs};
+use crate::units::electric_potential::volt;
+use crate::units::energy::watt_hour;
+use crate::units::ratio::percent;
+use crate::units::{ElectricPotential, Energy};
+
+fn battery(energy_full: f32, energy_full_design: f32) -> FakePowerSupply {
+    FakePowerSupply::battery("BAT0")
+        .model_name("5B10W13930")
+        .serial_number("1234")
+        .energy_now(Energy::new::<watt_hour>(20.0))
+        .energy_full(Energy::new::<watt_hour>(energy_full))
+        .energy_full_design(Energy::new::<watt_hour>(energy_full_design))
+        .voltage_now(ElectricPotential::new::<volt>(12.0))
+}
+
+#[test]
+fn test_first_seen() {
+    let root = tempfile::tempdir().unwrap();
+    let baselines = Baselines::open(root.path()).unwrap();
+    let mut sysfs = FakeSysfs::new().unwrap();
+    // Firmware reports the current full energy as the design one
+    sysfs.add(battery(48.0, 48.0)).unwrap();
+    let manager = sysfs.manager().unwrap();
+    let mut device = manager.batteries().unwrap().next().unwrap().unwrap();
+
+    let health = baselines.update(&device).unwrap();
+    assert!(health.is_design_suspicious());
+    assert_eq!(health.baseline().unwrap().source(), Source::FirstSeen);
+    assert_abs_diff_eq!(health.baseline_state_of_health().unwrap().get::<percent>(), 100.0, epsilon = 0.01);
+
+    sysfs.add(battery(45.6, 45.6)).unwrap();
+    manager.refresh(&mut device).unwrap();
+    let health = baselines.update(&device).unwrap();
+    assert_abs_diff_eq!(health.state_of_health().unwrap().get::<percent>(), 100.0, epsilon = 0.01);
+    assert_abs_diff_eq!(health.baseline_state_of_health().unwrap().get::<percent>(), 95.0, epsilon = 0.01);
+    assert_abs_diff_eq!(health.baseline().unwrap().energy().get::<watt_hour>(), 48.0, epsilon = 0.01);
+}
+
+#[test]
+fn test_nominal() {
+    let root = tempfile::tempdir().unwrap();
+    let baselines = Baselines::open(root.path()).unwrap();
+    let mut sysfs = FakeSysfs::new().unwrap();
+    sysfs.add(battery(45.0, 50.0)).unwrap();
+    let manager = sysfs.manager().unwrap();
+    let device = manager.batteries().unwrap().next().unwrap().unwrap();
+
+    baselines.update(&device).unwrap();
+    baselines.set_nominal(&device.id(), Energy::new::<watt_hour>(60.0)).unwrap();
+    baselines.set_nominal(&device.id(), Energy::new::<watt_hour>(57.0)).unwrap();
+
+    let health = baselines.get(&device).unwrap();
+    assert!(!health.is_design_suspicious());
+    assert_eq!(health.baseline().unwrap().source(), Source::Nominal);
+    assert_abs_diff_eq!(health.state_of_health().unwrap().get::<percent>(), 90.0, epsilon = 0.01);
+    assert_abs_diff_eq!(health.baseline_state_of_health().unwrap().get::<percent>(), 78.947, epsilon = 0.01);
+    let baseline = baselines.baseline(&device.id()).unwrap().unwrap();
+    assert_abs_diff_eq!(baseline.energy().get::<watt_hour>(), 57.0, epsilon = 0.01);
+}
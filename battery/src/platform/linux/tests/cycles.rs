@@ -0,0 +1,46 @@
+use approx::assert_abs_diff_eq;
+
+use crate::cycles::CycleCounter;
+use crate::test_util::{FakePowerSupply, FakeSysfs};
+use crate::units::electric_potential::volt;
+use crate::units::energy::watt_hour;
+use crate::units::{ElectricPotential, Energy};
+use crate::State;
+
+#[test]
+fn test_update() {
+    let battery = |energy: f32, cycles: Option<u32>| {
+        let battery = FakePowerSupply::battery("BAT0")
+            .status(State::Discharging)
+            .serial_number("1234")
+            .energy_now(Energy::new::<watt_hour>(energy))
+            .energy_full(Energy::new::<watt_hour>(45.0))
+            .energy_full_design(Energy::new::<watt_hour>(50.0))
+            .voltage_now(ElectricPotential::new::<volt>(12.0));
+        match cycles {
+            Some(cycles) => battery.cycle_count(cycles),
+            None => battery,
+        }
+    };
+    let root = tempfile::tempdir().unwrap();
+    let counter = CycleCounter::open(root.path()).unwrap();
+    let mut sysfs = FakeSysfs::new().unwrap();
+    sysfs.add(battery(45.0, None)).unwrap();
+    let manager = sysfs.manager().unwrap();
+    let mut device = manager.batteries().unwrap().next().unwrap().unwrap();
+
+    for energy in [45.0, 5.0, 40.0, 10.0].iter() {
+        sysfs.add(battery(*energy, None)).unwrap();
+        manager.refresh(&mut device).unwrap();
+        counter.update(&device).unwrap();
+    }
+    let count = counter.get(&device).unwrap();
+    assert_eq!(count.reported(), None);
+    // 40 Wh + 30 Wh discharged out of 50 Wh design energy
+    assert_abs_diff_eq!(count.estimated().unwrap(), 1.4, epsilon = 0.001);
+    assert_eq!(count.to_string(), "1.4 (estimated)");
+
+    sysfs.add(battery(10.0, Some(3))).unwrap();
+    manager.refresh(&mut device).unwrap();
+    assert_eq!(counter.update(&device).unwrap().to_string(), "3 (1.4 estimated)");
+}
@@ -0,0 +1,87 @@
+use std::fs;
+
+use super::super::SysFsDevice;
+use crate::diagnostics::{Adjustment, Conversion, Quantity};
+use crate::platform::traits::BatteryDevice;
+use crate::Fields;
+use crate::quirks::Quirks;
+
+#[test]
+fn test_diagnostics_for_charge_values() {
+    let root = sysfs_test_suite!(
+        "charge_full_design" => 3600000,
+        "charge_now" => 725000,
+        "type" => "Battery",
+        "charge_full" => 3424000,
+        "cycle_count" => 0,
+        "voltage_now" => 10663000,
+        "status" => "Discharging",
+        "voltage_min_design" => 11400000
+    );
+
+    let path = root.keep();
+    let device = SysFsDevice::try_from(path.clone(), &Quirks::new()).unwrap();
+    let diagnostics = device.diagnostics();
+
+    let energy = diagnostics.trace(Quantity::Energy).unwrap();
+    assert_eq!(energy.sources().len(), 1);
+    assert_eq!(energy.sources()[0].attribute(), "charge_now");
+    assert_eq!(energy.sources()[0].raw(), "725000");
+    assert_eq!(energy.conversion(), Some(Conversion::ChargeByDesignVoltage));
+
+    let design_voltage = diagnostics.trace(Quantity::DesignVoltage).unwrap();
+    assert_eq!(design_voltage.sources()[0].attribute(), "voltage_min_design");
+
+    let cycle_count = diagnostics.trace(Quantity::CycleCount).unwrap();
+    assert!(cycle_count.sources().is_empty());
+    assert_eq!(
+        cycle_count.adjustments(),
+        &[Adjustment::Ignored {
+            attribute: "cycle_count".to_string(),
+            raw: "0".to_string(),
+        }]
+    );
+
+    let report = diagnostics.to_string();
+    assert!(report.contains("energy: charge_now = \"725000\" (charge × design voltage)"));
+
+    fs::remove_dir_all(path).unwrap();
+}
+
+#[test]
+fn test_diagnostics_for_rejected_values() {
+    let root = sysfs_test_suite!(
+        "capacity" => 105,
+        "energy_now" => 50000000,
+        "energy_full" => 50000000,
+        "power_now" => 150000000,
+        "status" => "Discharging",
+        "type" => "Battery",
+        "voltage_now" => 12000000
+    );
+
+    let path = root.keep();
+    let mut device = SysFsDevice::try_from(path.clone(), &Quirks::new()).unwrap();
+
+    let state_of_charge = device.diagnostics().trace(Quantity::StateOfCharge).cloned().unwrap();
+    assert_eq!(state_of_charge.sources()[0].attribute(), "capacity");
+    assert_eq!(state_of_charge.adjustments().len(), 1);
+
+    let energy_rate = device.diagnostics().trace(Quantity::EnergyRate).cloned().unwrap();
+    assert_eq!(energy_rate.sources()[0].attribute(), "power_now");
+    assert_eq!(
+        energy_rate.adjustments(),
+        &[Adjustment::RateTooHigh {
+            raw: 150.0,
+        }]
+    );
+
+    // Partial refresh replaces traces of the refreshed values only
+    fs::write(path.join("power_now"), "15000000\n").unwrap();
+    device.refresh_with(Fields::ENERGY_RATE).unwrap();
+    let diagnostics = device.diagnostics();
+    assert!(diagnostics.trace(Quantity::EnergyRate).unwrap().adjustments().is_empty());
+    assert_eq!(diagnostics.trace(Quantity::StateOfCharge), Some(&state_of_charge));
+
+    fs::remove_dir_all(path).unwrap();
+}
@@ -0,0 +1,55 @@
+use std::fs;
+use std::io;
+
+use super::super::SysFsDevice;
+use crate::quirks::Quirks;
+use crate::{Error, ErrorKind};
+
+#[test]
+fn test_missing_value_error() {
+    let root = sysfs_test_suite!(
+        "type" => "Battery",
+        "status" => "Discharging",
+        "energy_now" => 25000000,
+        "energy_full" => 50000000
+    );
+
+    let path = root.keep();
+    let e = SysFsDevice::try_from(path.clone(), &Quirks::new()).unwrap_err();
+
+    assert_eq!(e.kind(), ErrorKind::AttributeMissing);
+    assert_eq!(e.device(), path.file_name().and_then(|name| name.to_str()));
+    assert!(!e.is_removed());
+
+    fs::remove_dir_all(path).unwrap();
+}
+
+#[test]
+fn test_io_error_kinds() {
+    let e = fs::read_to_string("/nonexistent/battery/energy_now").map_err(Error::from).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::AttributeMissing);
+    assert!(!e.is_removed());
+
+    assert_eq!(Error::from(io::Error::from(io::ErrorKind::PermissionDenied)).kind(), ErrorKind::PermissionDenied);
+    assert_eq!(Error::from(io::Error::from(io::ErrorKind::Interrupted)).kind(), ErrorKind::Io);
+}
+
+#[test]
+fn test_invalid_value_error() {
+    let root = sysfs_test_suite!(
+        "type" => "Battery",
+        "status" => "Discharging",
+        "energy_now" => 25000000,
+        "energy_full" => "\0\0\0\0",
+        "voltage_now" => 12000000
+    );
+
+    let path = root.keep();
+    let e = SysFsDevice::try_from(path.clone(), &Quirks::new()).unwrap_err();
+
+    assert_eq!(e.kind(), ErrorKind::InvalidValue);
+    assert_eq!(e.attribute(), Some(path.join("energy_full").as_path()));
+    assert_eq!(e.raw_value(), Some("\0\0\0\0"));
+
+    fs::remove_dir_all(path).unwrap();
+}
@@ -0,0 +1,35 @@
+use std::fs;
+
+use approx::assert_abs_diff_eq;
+
+use super::super::SysFsManager;
+use super::create_battery;
+use crate::{ErrorKind, Manager};
+
+#[test]
+fn test_rebind_replugged_battery() {
+    let root = tempfile::tempdir().unwrap();
+    create_battery(root.path(), "BAT0", Some("1234"), 10_000_000);
+
+    let manager = Manager::from(SysFsManager::with_root(root.path().to_path_buf()));
+    let mut battery = manager.batteries().unwrap().next().unwrap().unwrap();
+
+    // Same battery is added back under another name
+    fs::remove_dir_all(root.path().join("BAT0")).unwrap();
+    create_battery(root.path(), "BAT1", Some("1234"), 20_000_000);
+
+    manager.refresh(&mut battery).unwrap();
+    assert_abs_diff_eq!(battery.energy().value, 72000.0);
+    assert_eq!(battery.root(), root.path().join("BAT1").as_path());
+    assert_eq!(battery.name(), Some("BAT1"));
+
+    // Battery is gone and another one is added instead
+    fs::remove_dir_all(root.path().join("BAT1")).unwrap();
+    create_battery(root.path(), "BAT2", Some("5678"), 30_000_000);
+
+    let e = manager.refresh(&mut battery).unwrap_err();
+    assert!(e.is_removed());
+    assert_eq!(e.kind(), ErrorKind::DeviceGone);
+    assert_eq!(e.device(), Some("BAT1"));
+    assert_abs_diff_eq!(battery.energy().value, 72000.0);
+}
@@ -4,6 +4,7 @@ use approx::assert_abs_diff_eq;
 
 use super::super::SysFsDevice;
 use crate::platform::traits::BatteryDevice;
+use crate::quirks::Quirks;
 use crate::{State, Technology};
 
 // https://github.com/svartalf/rust-battery/issues/28
@@ -33,8 +34,8 @@ fn test_issue_28() {
         "capacity_level" => "Normal"
     );
 
-    let path = root.into_path();
-    let device = SysFsDevice::try_from(path.clone());
+    let path = root.keep();
+    let device = SysFsDevice::try_from(path.clone(), &Quirks::builtin());
 
     assert!(device.is_ok());
     let device = device.unwrap();
@@ -0,0 +1,48 @@
+use std::fs;
+
+use approx::assert_abs_diff_eq;
+
+use super::super::SysFsDevice;
+use crate::platform::traits::BatteryDevice;
+use crate::quirks::Quirks;
+use crate::{Fields, State};
+
+// Device without any voltage attributes can't be loaded in the strict mode,
+// but all other values are still readable.
+#[test]
+fn test_lenient_device_without_voltage() {
+    let root = sysfs_test_suite!(
+        "type" => "Battery",
+        "status" => "Charging",
+        "capacity" => 40,
+        "energy_now" => 20000000,
+        "energy_full" => 50000000,
+        "energy_full_design" => 50000000
+    );
+
+    let path = root.keep();
+    assert!(SysFsDevice::try_from(path.clone(), &Quirks::new()).is_err());
+
+    let mut device = SysFsDevice::try_from_lenient(path.clone(), &Quirks::new());
+    assert_eq!(device.missing_fields(), Fields::VOLTAGE);
+    assert_eq!(device.warnings().len(), 1);
+    assert_eq!(device.warnings()[0].fields(), Fields::VOLTAGE);
+    assert_eq!(device.state(), State::Charging);
+    assert_abs_diff_eq!(device.state_of_charge().value, 0.4);
+    assert_abs_diff_eq!(device.energy().value, 72000.0);
+    assert_abs_diff_eq!(device.voltage().value, 0.0);
+
+    fs::write(path.join("voltage_now"), "12000000\n").unwrap();
+    device.refresh().unwrap();
+    assert!(device.missing_fields().is_empty());
+    assert!(device.warnings().is_empty());
+    assert_abs_diff_eq!(device.voltage().value, 12.0);
+
+    // Previous value is kept if attribute is gone again
+    fs::remove_file(path.join("voltage_now")).unwrap();
+    device.refresh().unwrap();
+    assert_eq!(device.missing_fields(), Fields::VOLTAGE);
+    assert_abs_diff_eq!(device.voltage().value, 12.0);
+
+    fs::remove_dir_all(path).unwrap();
+}
@@ -1,23 +1,70 @@
+use std::path::Path;
+
 /// This macro generates the bunch of files representing the
 /// `/sys/class/power_supply/{name}/*` directory contents.
+///
+/// Files are written into the new temporary directory, which is returned,
+/// or into the given one, if it is prefixed with `in path;`.
 macro_rules! sysfs_test_suite {
-    ( $( $name:expr => $value:expr ),* ) => {{
+    ( in $path:expr; $( $name:expr => $value:expr ),* ) => {{
         use ::std::io::Write;
 
-        let root = tempfile::tempdir().unwrap();
+        let path = $path;
+        let path: &::std::path::Path = path.as_ref();
+        ::std::fs::create_dir_all(path).unwrap();
 
         $(
             let mut file = ::std::fs::OpenOptions::new()
                 .write(true)
                 .create_new(true)
-                .open(root.path().join($name))
+                .open(path.join($name))
                 .unwrap();
             file.write_fmt(format_args!("{}\n", $value)).unwrap();
         )*
+    }};
+    ( $( $name:expr => $value:expr ),* ) => {{
+        let root = tempfile::tempdir().unwrap();
+        sysfs_test_suite!(in root.path(); $( $name => $value ),*);
 
         root
     }};
 }
 
+/// Creates the discharging `name` battery directory in the `root` directory.
+fn create_battery(root: &Path, name: &str, serial_number: Option<&str>, energy_now: u32) {
+    let path = root.join(name);
+    sysfs_test_suite!(in &path;
+        "type" => "Battery",
+        "status" => "Discharging",
+        "manufacturer" => "SMP",
+        "model_name" => "5B10W13930",
+        "energy_now" => energy_now,
+        "energy_full" => 50_000_000,
+        "voltage_now" => 12_000_000
+    );
+    if let Some(serial_number) = serial_number {
+        sysfs_test_suite!(in &path; "serial_number" => serial_number);
+    }
+}
+
+mod anomalies;
+mod archive;
+#[cfg(all(feature = "test-util", feature = "history"))]
+mod baseline;
+#[cfg(all(feature = "test-util", feature = "history"))]
+mod cycles;
+mod diagnostics;
+mod errors;
+mod hotplug;
 mod issue_28;
 mod issue_40;
+mod lenient;
+mod quirks;
+mod readings;
+mod refresh_all;
+mod refresh_timeout;
+mod refresh_with;
+#[cfg(feature = "simulation")]
+mod simulation;
+#[cfg(feature = "test-util")]
+mod test_util;
@@ -0,0 +1,260 @@
+use std::fs;
+use std::os::unix::fs::symlink;
+
+use approx::assert_abs_diff_eq;
+
+use super::super::SysFsDevice;
+use crate::diagnostics::{Adjustment, Quantity};
+use crate::platform::traits::BatteryDevice;
+use crate::quirks::{Correction, Quirk, Quirks};
+use crate::State;
+
+fn quirks(quirk: Quirk) -> Quirks {
+    let mut quirks = Quirks::new();
+    quirks.push(quirk);
+    quirks
+}
+
+#[test]
+fn test_quirks_corrections() {
+    let root = sysfs_test_suite!(
+        "type" => "Battery",
+        "status" => "Charging",
+        "manufacturer" => "SMP",
+        "model_name" => "5B10W13930",
+        "energy_now" => 25000,
+        "energy_full" => 50000000,
+        "power_now" => -10000000,
+        "cycle_count" => 100500,
+        "voltage_now" => 12000000
+    );
+
+    let quirk = Quirk::new()
+        .vendor("smp")
+        .model("5B10W13930")
+        .correction(Correction::Scale {
+            attribute: "energy_now".into(),
+            factor: 1000.0,
+        })
+        .correction(Correction::InvertSign {
+            attribute: "power_now".into(),
+        })
+        .correction(Correction::Ignore {
+            attribute: "cycle_count".into(),
+        });
+
+    let path = root.keep();
+    let device = SysFsDevice::try_from(path.clone(), &quirks(quirk)).unwrap();
+
+    assert_abs_diff_eq!(device.energy().value, 90000.0);
+    assert_abs_diff_eq!(device.energy_rate().value, 10.0);
+    assert_eq!(device.cycle_count(), None);
+    assert_eq!(device.state(), State::Charging);
+
+    let diagnostics = device.diagnostics();
+    let energy = diagnostics.trace(Quantity::Energy).unwrap();
+    assert_eq!(energy.sources()[0].raw(), "25000000");
+    assert_eq!(
+        energy.adjustments(),
+        &[Adjustment::Corrected {
+            attribute: "energy_now".to_string(),
+            raw: "25000".to_string(),
+        }]
+    );
+
+    fs::remove_dir_all(path).unwrap();
+}
+
+#[test]
+fn test_quirks_not_matching() {
+    let root = sysfs_test_suite!(
+        "type" => "Battery",
+        "status" => "Discharging",
+        "manufacturer" => "SMP",
+        "energy_now" => 25000000,
+        "energy_full" => 50000000,
+        "voltage_now" => 12000000
+    );
+
+    let quirk = Quirk::new().vendor("SMP").driver("bq27xxx-battery").correction(Correction::Ignore {
+        attribute: "energy_now".into(),
+    });
+
+    let path = root.keep();
+    let device = SysFsDevice::try_from(path.clone(), &quirks(quirk)).unwrap();
+
+    assert_abs_diff_eq!(device.energy().value, 90000.0);
+
+    fs::remove_dir_all(path).unwrap();
+}
+
+#[test]
+fn test_quirks_swap_units_by_driver_and_sys_vendor() {
+    // Mimics the `/sys/class/power_supply/BAT0` and `/sys/class/dmi/id` layout
+    let root = tempfile::tempdir().unwrap();
+    let class = root.path().join("class");
+    let dmi = class.join("dmi").join("id");
+    fs::create_dir_all(&dmi).unwrap();
+    fs::write(dmi.join("sys_vendor"), "LENOVO\n").unwrap();
+    let driver = root.path().join("bus").join("acpi").join("drivers").join("battery");
+    fs::create_dir_all(&driver).unwrap();
+
+    let path = class.join("power_supply").join("BAT0");
+    fs::create_dir_all(path.join("device")).unwrap();
+    symlink(&driver, path.join("device").join("driver")).unwrap();
+    fs::write(path.join("type"), "Battery\n").unwrap();
+    fs::write(path.join("status"), "Discharging\n").unwrap();
+    // Driver reports charge values in the energy attributes
+    fs::write(path.join("energy_now"), "1500000\n").unwrap();
+    fs::write(path.join("energy_full"), "3000000\n").unwrap();
+    fs::write(path.join("voltage_min_design"), "10000000\n").unwrap();
+    fs::write(path.join("voltage_now"), "12000000\n").unwrap();
+
+    let quirk = Quirk::new()
+        .driver("battery")
+        .sys_vendor("lenovo")
+        .correction(Correction::SwapUnits {
+            attribute: "energy_now".into(),
+        })
+        .correction(Correction::SwapUnits {
+            attribute: "energy_full".into(),
+        });
+
+    let device = SysFsDevice::try_from(path, &quirks(quirk)).unwrap();
+
+    // 1.5 Ah × 10 V = 15 Wh
+    assert_abs_diff_eq!(device.energy().value, 54000.0);
+    assert!(device.readings().energy().is_derived());
+    assert_abs_diff_eq!(device.energy_full().value, 108000.0);
+}
+
+#[test]
+fn test_builtin_quirks_ignore_garbage_values() {
+    let root = sysfs_test_suite!(
+        "type" => "Battery",
+        "status" => "Discharging",
+        "charge_now" => 0,
+        "charge_avg" => 1500000,
+        "charge_full" => 3000000,
+        "power_now" => 5000,
+        "current_now" => 1000000,
+        "voltage_min_design" => 10000000,
+        "voltage_now" => 12000000
+    );
+
+    let path = root.keep();
+    let device = SysFsDevice::try_from(path.clone(), &Quirks::builtin()).unwrap();
+
+    // `power_now` is ignored, so the rate is derived from `current_now`: 1 A × 10 V
+    assert_abs_diff_eq!(device.energy_rate().value, 10.0);
+    assert!(device.readings().energy_rate().is_derived());
+    // `charge_now` is ignored too, so `charge_avg` is used instead: 1.5 Ah × 10 V
+    assert_abs_diff_eq!(device.energy().value, 54000.0);
+    let diagnostics = device.diagnostics();
+    let rate = diagnostics.trace(Quantity::EnergyRate).unwrap();
+    assert!(rate.adjustments().contains(&Adjustment::Ignored {
+        attribute: "power_now".to_string(),
+        raw: "5000".to_string(),
+    }));
+
+    // Same values are kept as is without quirks
+    let device = SysFsDevice::try_from(path.clone(), &Quirks::new()).unwrap();
+    assert_abs_diff_eq!(device.energy_rate().value, 0.005);
+    assert_abs_diff_eq!(device.energy().value, 0.0);
+
+    fs::remove_dir_all(path).unwrap();
+}
+
+#[test]
+fn test_builtin_quirks_current_as_power() {
+    // Legacy driver reports power in `current_now`
+    let root = sysfs_test_suite!(
+        "type" => "Battery",
+        "status" => "Discharging",
+        "energy_now" => 25000000,
+        "energy_full" => 50000000,
+        "current_now" => 15000000,
+        "voltage_now" => 12000000
+    );
+
+    let path = root.keep();
+    let device = SysFsDevice::try_from(path.clone(), &Quirks::builtin()).unwrap();
+
+    assert_abs_diff_eq!(device.energy_rate().value, 15.0);
+    assert!(device.readings().energy_rate().is_reported());
+
+    // Garbage `power_now` is the same as the missing one
+    fs::write(path.join("power_now"), "0\n").unwrap();
+    let device = SysFsDevice::try_from(path.clone(), &Quirks::builtin()).unwrap();
+    assert_abs_diff_eq!(device.energy_rate().value, 15.0);
+
+    // Devices which are providing the `power_now` are not affected
+    fs::write(path.join("power_now"), "10000000\n").unwrap();
+    let mut device = SysFsDevice::try_from(path.clone(), &Quirks::builtin()).unwrap();
+    assert_abs_diff_eq!(device.energy_rate().value, 10.0);
+
+    // Values are compared again on refresh
+    fs::write(path.join("power_now"), "0\n").unwrap();
+    device.refresh().unwrap();
+    assert_abs_diff_eq!(device.energy_rate().value, 15.0);
+
+    // Neither are the ones providing the design charge, `current_now` is a current for them
+    fs::write(path.join("power_now"), "0\n").unwrap();
+    fs::write(path.join("charge_full"), "0\n").unwrap();
+    fs::write(path.join("charge_full_design"), "5000000\n").unwrap();
+    fs::write(path.join("current_now"), "1500000\n").unwrap();
+    let device = SysFsDevice::try_from(path.clone(), &Quirks::builtin()).unwrap();
+    assert_abs_diff_eq!(device.energy_rate().value, 18.0);
+    assert!(device.readings().energy_rate().is_derived());
+
+    fs::remove_dir_all(path).unwrap();
+}
+
+#[cfg(feature = "quirks-toml")]
+#[test]
+fn test_quirks_from_toml() {
+    let quirks = Quirks::from_toml(
+        r#"
+        [[quirk]]
+        vendor = "SMP"
+        missing = ["power_now"]
+        ignore = ["cycle_count"]
+        invert_sign = ["current_now"]
+
+        [quirk.scale]
+        power_now = 1000.0
+
+        [quirk.ignore_below]
+        current_now = 1000.0
+
+        [quirk.missing_below]
+        charge_full = 1.0
+        "#,
+    )
+    .unwrap();
+
+    let quirk = quirks.iter().next().unwrap();
+    assert_eq!(
+        quirk.corrections(),
+        &[
+            Correction::InvertSign {
+                attribute: "current_now".into(),
+            },
+            Correction::Ignore {
+                attribute: "cycle_count".into(),
+            },
+            Correction::Scale {
+                attribute: "power_now".into(),
+                factor: 1000.0,
+            },
+            Correction::IgnoreBelow {
+                attribute: "current_now".into(),
+                threshold: 1000.0,
+            },
+        ]
+    );
+
+    assert_eq!(quirks.compared_attributes(), vec!["charge_full"]);
+
+    assert!(Quirks::from_toml("[[quirk]]\nunknown = 1").is_err());
+}
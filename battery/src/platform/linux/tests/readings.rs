@@ -0,0 +1,69 @@
+use std::fs;
+
+use super::super::SysFsDevice;
+use crate::platform::traits::BatteryDevice;
+use crate::Reading;
+use crate::quirks::Quirks;
+
+// Same data as in the `issue_40` test:
+// driver reports neither energy nor charge values, only the `capacity`.
+#[test]
+fn test_readings_without_design_capacity() {
+    let root = sysfs_test_suite!(
+        "capacity" => 83,
+        "current_now" => 898,
+        "status" => "Discharging",
+        "technology" => "Li-ion",
+        "temp" => 258,
+        "type" => "Battery",
+        "voltage_now" => 11829000
+    );
+
+    let path = root.keep();
+    let device = SysFsDevice::try_from(path.clone(), &Quirks::builtin()).unwrap();
+    let readings = device.readings();
+
+    assert!(readings.state_of_charge().is_reported());
+    assert_eq!(readings.state_of_health(), Reading::Unavailable);
+    assert_eq!(readings.energy(), Reading::Unavailable);
+    assert_eq!(readings.energy_full(), Reading::Unavailable);
+    assert_eq!(readings.energy_full_design(), Reading::Unavailable);
+    assert!(readings.energy_rate().is_reported());
+    assert!(readings.voltage().is_reported());
+    assert!(readings.temperature().is_reported());
+    assert_eq!(readings.cycle_count(), Reading::Unavailable);
+
+    // Legacy methods are still substituting the missing values
+    assert_eq!(device.state_of_health().value, 1.0);
+    assert_eq!(device.energy_full_design().value, 0.0);
+
+    fs::remove_dir_all(path).unwrap();
+}
+
+#[test]
+fn test_readings_derived_from_charge() {
+    let root = sysfs_test_suite!(
+        "charge_full_design" => 3600000,
+        "charge_now" => 725000,
+        "type" => "Battery",
+        "charge_full" => 3424000,
+        "cycle_count" => 0,
+        "voltage_now" => 10663000,
+        "status" => "Discharging",
+        "voltage_min_design" => 11400000
+    );
+
+    let path = root.keep();
+    let device = SysFsDevice::try_from(path.clone(), &Quirks::new()).unwrap();
+    let readings = device.readings();
+
+    assert!(readings.energy().is_derived());
+    assert!(readings.energy_full().is_derived());
+    assert!(readings.energy_full_design().is_derived());
+    assert!(readings.state_of_charge().is_derived());
+    assert!(readings.state_of_health().is_derived());
+    assert_eq!(readings.energy_rate(), Reading::Unavailable);
+    assert_eq!(readings.time_to_empty(), Reading::Unavailable);
+
+    fs::remove_dir_all(path).unwrap();
+}
@@ -0,0 +1,80 @@
+use std::fs;
+
+use approx::assert_abs_diff_eq;
+
+use super::super::SysFsManager;
+use super::create_battery;
+use crate::{Manager, Result};
+
+#[test]
+fn test_refresh_all_reports_removed_and_added() {
+    let root = tempfile::tempdir().unwrap();
+    create_battery(root.path(), "BAT0", None, 10_000_000);
+    create_battery(root.path(), "BAT1", None, 20_000_000);
+
+    let manager = Manager::from(SysFsManager::with_root(root.path().to_path_buf()));
+    let mut batteries = manager.batteries().unwrap().collect::<Result<Vec<_>>>().unwrap();
+    batteries.sort_by_key(|battery| battery.energy().value as u32);
+    assert_eq!(batteries.len(), 2);
+
+    fs::write(root.path().join("BAT0").join("energy_now"), "15000000\n").unwrap();
+    fs::remove_dir_all(root.path().join("BAT1")).unwrap();
+    create_battery(root.path(), "BAT2", None, 30_000_000);
+
+    let report = manager.refresh_all_concurrently(&mut batteries).unwrap();
+
+    assert!(!report.is_ok());
+    assert!(report.results()[0].is_ok());
+    assert!(report.results()[1].is_err());
+    assert_eq!(report.removed(), &[1]);
+    assert_eq!(report.added().len(), 1);
+    assert_abs_diff_eq!(report.added()[0].energy().value, 108000.0);
+    assert_abs_diff_eq!(batteries[0].energy().value, 54000.0);
+}
+
+// Batteries of the same model without serial numbers should not be merged or reported as re-plugged
+#[test]
+fn test_refresh_all_without_serial_numbers() {
+    let root = tempfile::tempdir().unwrap();
+    create_battery(root.path(), "BAT0", None, 10_000_000);
+    create_battery(root.path(), "BAT1", None, 10_000_000);
+
+    let manager = Manager::from(SysFsManager::with_root(root.path().to_path_buf()));
+    let mut batteries = manager.batteries().unwrap().collect::<Result<Vec<_>>>().unwrap();
+    assert_eq!(batteries.len(), 2);
+
+    fs::write(root.path().join("BAT1").join("energy_now"), "20000000\n").unwrap();
+
+    for concurrently in [false, true].iter() {
+        let report = if *concurrently {
+            manager.refresh_all_concurrently(&mut batteries).unwrap()
+        } else {
+            manager.refresh_all(&mut batteries).unwrap()
+        };
+
+        assert!(report.is_ok());
+        assert!(report.removed().is_empty());
+        assert!(report.added().is_empty());
+    }
+    let energy = |name| batteries.iter().find(|battery| battery.name() == Some(name)).unwrap().energy().value;
+    assert_abs_diff_eq!(energy("BAT0"), 36000.0);
+    assert_abs_diff_eq!(energy("BAT1"), 72000.0);
+}
+
+// Battery which fails to refresh, but is still in its directory, is neither removed nor added
+#[test]
+fn test_refresh_all_failed_battery_is_present() {
+    let root = tempfile::tempdir().unwrap();
+    create_battery(root.path(), "BAT0", None, 10_000_000);
+
+    let manager = Manager::from(SysFsManager::with_root(root.path().to_path_buf()));
+    let mut batteries = manager.batteries().unwrap().collect::<Result<Vec<_>>>().unwrap();
+
+    fs::write(root.path().join("BAT0").join("energy_now"), "garbage\n").unwrap();
+    fs::write(root.path().join("BAT0").join("energy_full"), "garbage\n").unwrap();
+
+    let report = manager.refresh_all(&mut batteries).unwrap();
+    assert!(report.results()[0].is_err());
+    assert!(report.removed().is_empty());
+    assert!(report.added().is_empty());
+}
@@ -0,0 +1,83 @@
+use std::error::Error as StdError;
+use std::ffi::CString;
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use approx::assert_abs_diff_eq;
+
+use super::super::SysFsDevice;
+use crate::platform::traits::BatteryDevice;
+use crate::ErrorKind;
+use crate::quirks::Quirks;
+
+fn io_kind(e: &crate::Error) -> io::ErrorKind {
+    e.source().unwrap().downcast_ref::<io::Error>().unwrap().kind()
+}
+
+fn mkfifo(path: &Path) -> io::Result<()> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    match unsafe { libc::mkfifo(path.as_ptr(), 0o644) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+// Named pipe without a writer blocks the reader,
+// which is exactly what hanging drivers are doing.
+#[test]
+fn test_refresh_timeout_keeps_previous_values() {
+    let root = sysfs_test_suite!(
+        "type" => "Battery",
+        "status" => "Discharging",
+        "energy_now" => 25000000,
+        "energy_full" => 50000000,
+        "voltage_now" => 12000000
+    );
+
+    let path = root.keep();
+    let mut device = SysFsDevice::try_from(path.clone(), &Quirks::new()).unwrap();
+
+    let fifo = path.join("energy_now");
+    fs::remove_file(&fifo).unwrap();
+    mkfifo(&fifo).unwrap();
+
+    let e = device.refresh_timeout(Duration::from_millis(100)).unwrap_err();
+    assert_eq!(io_kind(&e), io::ErrorKind::TimedOut);
+    assert_eq!(e.kind(), ErrorKind::Timeout);
+    assert_eq!(e.attribute(), Some(fifo.as_path()));
+    assert_eq!(e.to_string().matches("energy_now").count(), 1);
+    assert!(e.to_string().starts_with("Reading timed out after 100ms"));
+    assert_abs_diff_eq!(device.energy().value, 90000.0);
+
+    // Previous read still hangs, so this one fails right away
+    let e = device.refresh_timeout(Duration::from_secs(60)).unwrap_err();
+    assert_eq!(io_kind(&e), io::ErrorKind::TimedOut);
+
+    // Unblock the hanging thread and replace the pipe with a regular file
+    let writer = thread::spawn(move || {
+        let mut pipe = fs::OpenOptions::new().write(true).open(&fifo).unwrap();
+        pipe.write_all(b"30000000\n").unwrap();
+    });
+    writer.join().unwrap();
+    fs::remove_file(path.join("energy_now")).unwrap();
+    fs::write(path.join("energy_now"), "35000000\n").unwrap();
+
+    // Hanging thread finishes on its own, refreshes are failing until it does
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        match device.refresh_timeout(Duration::from_secs(5)) {
+            Ok(()) => break,
+            Err(ref e) if io_kind(e) == io::ErrorKind::TimedOut && Instant::now() < deadline => {
+                thread::sleep(Duration::from_millis(1));
+            }
+            Err(e) => panic!("{}", e),
+        }
+    }
+    assert_abs_diff_eq!(device.energy().value, 126000.0);
+
+    fs::remove_dir_all(path).unwrap();
+}
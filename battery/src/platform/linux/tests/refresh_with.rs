@@ -0,0 +1,73 @@
+use std::fs;
+
+use approx::assert_abs_diff_eq;
+
+use super::super::SysFsDevice;
+use crate::platform::traits::BatteryDevice;
+use crate::quirks::Quirks;
+use crate::{Fields, State};
+
+// Values which were not requested for refresh should keep their previous values,
+// even if they were changed in the sysfs meanwhile.
+#[test]
+fn test_refresh_with_selected_fields() {
+    let root = sysfs_test_suite!(
+        "type" => "Battery",
+        "status" => "Discharging",
+        "capacity" => 50,
+        "energy_now" => 25000000,
+        "energy_full" => 50000000,
+        "energy_full_design" => 60000000,
+        "power_now" => 12000000,
+        "voltage_now" => 12000000
+    );
+
+    let path = root.keep();
+    let mut device = SysFsDevice::try_from(path.clone(), &Quirks::new()).unwrap();
+
+    fs::write(path.join("status"), "Charging\n").unwrap();
+    fs::write(path.join("capacity"), "60\n").unwrap();
+    fs::write(path.join("energy_now"), "30000000\n").unwrap();
+    fs::write(path.join("voltage_now"), "12500000\n").unwrap();
+
+    device.refresh_with(Fields::STATE | Fields::CHARGE).unwrap();
+
+    assert_eq!(device.state(), State::Charging);
+    assert_abs_diff_eq!(device.state_of_charge().value, 0.6);
+    assert_abs_diff_eq!(device.voltage().value, 12.0);
+
+    fs::write(path.join("energy_now"), "35000000\n").unwrap();
+    device.refresh_with(Fields::ENERGY).unwrap();
+
+    assert_abs_diff_eq!(device.energy().value, 126000.0);
+    assert_abs_diff_eq!(device.state_of_charge().value, 0.6);
+    assert_abs_diff_eq!(device.voltage().value, 12.0);
+
+    fs::remove_dir_all(path).unwrap();
+}
+
+// State of charge and energy values should never disagree after a partial refresh
+#[test]
+fn test_refresh_charge_implies_energy() {
+    let root = sysfs_test_suite!(
+        "type" => "Battery",
+        "status" => "Discharging",
+        "energy_now" => 25000000,
+        "energy_full" => 50000000,
+        "energy_full_design" => 60000000,
+        "voltage_now" => 12000000
+    );
+
+    let path = root.keep();
+    let mut device = SysFsDevice::try_from(path.clone(), &Quirks::new()).unwrap();
+    assert_abs_diff_eq!(device.state_of_charge().value, 0.5);
+
+    fs::write(path.join("energy_now"), "40000000\n").unwrap();
+    device.refresh_with(Fields::CHARGE).unwrap();
+
+    assert_abs_diff_eq!(device.energy().value, 144000.0);
+    assert_abs_diff_eq!(device.state_of_charge().value, 0.8);
+    assert_eq!(device.state(), State::Discharging);
+
+    fs::remove_dir_all(path).unwrap();
+}
@@ -0,0 +1,107 @@
+use std::time::Duration;
+
+use approx::assert_abs_diff_eq;
+
+use crate::simulation::{Event, Profile, Scenario, Simulation};
+use crate::units::energy::watt_hour;
+use crate::units::power::watt;
+use crate::units::ratio::{percent, ratio};
+use crate::units::thermodynamic_temperature::degree_celsius;
+use crate::units::{Power, Ratio};
+use crate::State;
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+
+#[test]
+fn test_scenario_parse() {
+    let scenario = Scenario::parse(
+        "# comment
+         1h30m unplug
+         0s load 25 W
+         10m plug
+         2h ambient 35 C",
+    )
+    .unwrap();
+    let events = scenario.events();
+    assert_eq!(events.len(), 4);
+    assert_eq!(events[0], (Duration::from_secs(0), Event::Load(Power::new::<watt>(25.0))));
+    assert_eq!(events[1], (Duration::from_secs(10 * MINUTE), Event::Plug));
+    assert_eq!(events[2], (Duration::from_secs(90 * MINUTE), Event::Unplug));
+
+    let e = Scenario::parse("10m load").unwrap_err();
+    assert_eq!(e.raw_value(), Some("10m load"));
+    assert!(Scenario::parse("soon plug").is_err());
+    assert!(Scenario::parse("10m load 25").is_err());
+    assert!(Scenario::parse("10m load 25 kW").is_err());
+    assert!(Scenario::parse("10m ambient 300 K").is_err());
+    assert!(Scenario::parse("10m load 25 W now").is_err());
+    assert!(Scenario::parse("10m plug in").is_err());
+}
+
+#[test]
+fn test_discharge_and_charge() {
+    let scenario = Scenario::new()
+        .at(Duration::from_secs(0), Event::Load(Power::new::<watt>(25.0)))
+        .at(Duration::from_secs(HOUR), Event::Plug);
+    let mut simulation = Simulation::new(Profile::new(), scenario).unwrap();
+    let manager = simulation.manager().unwrap();
+    let mut battery = manager.batteries().unwrap().next().unwrap().unwrap();
+    assert_eq!(battery.state(), State::Discharging);
+    assert_abs_diff_eq!(battery.state_of_charge().get::<percent>(), 100.0, epsilon = 0.1);
+
+    simulation.advance(Duration::from_secs(HOUR - 1)).unwrap();
+    manager.refresh(&mut battery).unwrap();
+    assert_eq!(battery.state(), State::Discharging);
+    // Part of the energy is lost as heat
+    assert_abs_diff_eq!(battery.energy().get::<watt_hour>(), 24.5, epsilon = 0.5);
+    assert_abs_diff_eq!(battery.energy_rate().get::<watt>(), 25.0, epsilon = 1.0);
+    // Discharging current is heating the battery a bit
+    assert!(battery.temperature().unwrap().get::<degree_celsius>() > 25.0);
+
+    // Constant current phase
+    simulation.advance(Duration::from_secs(10 * MINUTE)).unwrap();
+    manager.refresh(&mut battery).unwrap();
+    assert_eq!(battery.state(), State::Charging);
+    let cc_rate = battery.energy_rate().get::<watt>();
+    assert!(cc_rate > 30.0, "{}", cc_rate);
+
+    // Constant voltage phase, current is decaying
+    simulation.advance(Duration::from_secs(HOUR)).unwrap();
+    manager.refresh(&mut battery).unwrap();
+    assert_eq!(battery.state(), State::Charging);
+    assert!(battery.state_of_charge().get::<ratio>() > 0.8);
+    assert!(battery.energy_rate().get::<watt>() < cc_rate);
+
+    simulation.advance(Duration::from_secs(3 * HOUR)).unwrap();
+    manager.refresh(&mut battery).unwrap();
+    assert_eq!(battery.state(), State::Full);
+    assert_abs_diff_eq!(battery.energy_rate().get::<watt>(), 0.0);
+    assert!(battery.state_of_charge().get::<ratio>() > 0.95);
+    assert_eq!(simulation.elapsed(), Duration::from_secs(5 * HOUR + 10 * MINUTE - 1));
+}
+
+#[test]
+fn test_empty_battery() {
+    let profile = Profile::new().state_of_charge(Ratio::new::<percent>(10.0));
+    let mut simulation = Simulation::new(profile, Scenario::new()).unwrap();
+    simulation.apply(Event::Load(Power::new::<watt>(50.0))).unwrap();
+    simulation.advance(Duration::from_secs(HOUR)).unwrap();
+
+    let manager = simulation.manager().unwrap();
+    let battery = manager.batteries().unwrap().next().unwrap().unwrap();
+    assert_eq!(battery.state(), State::Empty);
+    assert_abs_diff_eq!(battery.energy().value, 0.0);
+}
+
+#[test]
+fn test_capacity_fade() {
+    let profile = Profile::new().cycles(250.0).capacity_fade(Ratio::new::<percent>(0.1));
+    let simulation = Simulation::new(profile, Scenario::new()).unwrap();
+
+    let manager = simulation.manager().unwrap();
+    let battery = manager.batteries().unwrap().next().unwrap().unwrap();
+    assert_eq!(battery.cycle_count(), Some(250));
+    assert_abs_diff_eq!(battery.energy_full().get::<watt_hour>(), 37.5, epsilon = 0.01);
+    assert_abs_diff_eq!(battery.state_of_health().get::<percent>(), 75.0, epsilon = 0.1);
+}
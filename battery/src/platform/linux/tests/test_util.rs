@@ -0,0 +1,77 @@
+use approx::assert_abs_diff_eq;
+
+use crate::test_util::{FakePowerSupply, FakeSysfs};
+use crate::units::electric_potential::volt;
+use crate::units::energy::watt_hour;
+use crate::units::power::watt;
+use crate::units::thermodynamic_temperature::degree_celsius;
+use crate::units::{ElectricPotential, Energy, Power, ThermodynamicTemperature};
+use crate::{State, Technology};
+
+fn fake_battery(name: &str, serial_number: &str) -> FakePowerSupply {
+    FakePowerSupply::battery(name)
+        .status(State::Discharging)
+        .technology(Technology::LithiumIon)
+        .serial_number(serial_number)
+        .energy_now(Energy::new::<watt_hour>(25.0))
+        .energy_full(Energy::new::<watt_hour>(50.0))
+        .power_now(Power::new::<watt>(10.0))
+        .voltage_now(ElectricPotential::new::<volt>(12.0))
+        .temp(ThermodynamicTemperature::new::<degree_celsius>(30.5))
+}
+
+#[test]
+fn test_values() {
+    let mut sysfs = FakeSysfs::new().unwrap();
+    sysfs.add(fake_battery("BAT0", "1234")).unwrap();
+    sysfs.add(FakePowerSupply::mains("AC").online(true)).unwrap();
+    sysfs.add(fake_battery("hid-mouse", "5678").peripheral()).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(sysfs.root().join("BAT0/energy_now")).unwrap(),
+        "25000000\n"
+    );
+
+    let manager = sysfs.manager().unwrap();
+    let batteries = manager.batteries().unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(batteries.len(), 1);
+    let battery = &batteries[0];
+    assert_eq!(battery.technology(), Technology::LithiumIon);
+    assert_abs_diff_eq!(battery.state_of_charge().value, 0.5);
+    assert_abs_diff_eq!(battery.energy_rate().value, 10.0);
+    assert_abs_diff_eq!(battery.temperature().unwrap().get::<degree_celsius>(), 30.5, epsilon = 0.01);
+}
+
+#[test]
+fn test_mutations() {
+    let mut sysfs = FakeSysfs::new().unwrap();
+    sysfs.add(fake_battery("BAT0", "1234")).unwrap();
+    let manager = sysfs.manager().unwrap();
+    let mut battery = manager.batteries().unwrap().next().unwrap().unwrap();
+
+    sysfs
+        .update("BAT0", |battery| {
+            battery
+                .status(State::Charging)
+                .energy_now(Energy::new::<watt_hour>(40.0))
+                .without("power_now")
+        })
+        .unwrap();
+    manager.refresh(&mut battery).unwrap();
+    assert_eq!(battery.state(), State::Charging);
+    assert_abs_diff_eq!(battery.state_of_charge().value, 0.8);
+    assert!(battery.readings().energy_rate().value().is_none());
+
+    // Same battery is re-plugged under another name
+    sysfs.remove("BAT0").unwrap();
+    sysfs.add(fake_battery("BAT1", "1234")).unwrap();
+    manager.refresh(&mut battery).unwrap();
+    assert_eq!(battery.root(), sysfs.root().join("BAT1").as_path());
+
+    sysfs.remove("BAT1").unwrap();
+    assert!(manager.refresh(&mut battery).unwrap_err().is_removed());
+    assert!(sysfs.update("BAT1", |battery| battery).is_err());
+
+    let root = sysfs.root().to_path_buf();
+    drop(sysfs);
+    assert!(!root.exists());
+}
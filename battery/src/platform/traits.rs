@@ -2,19 +2,65 @@
 
 use std::fmt::Debug;
 use std::rc::Rc;
+use std::time::Duration;
 
 use num_traits::identities::Zero;
 use uom::si::time::{day, hour};
 
 use crate::units::{Bound, ElectricPotential, Energy, Power, Ratio, ThermodynamicTemperature, Time};
-use crate::{Result, State, Technology};
+use crate::quirks::Quirks;
+use crate::types::{Diagnostics, Reading, Readings};
+use crate::{Fields, Result, State, Technology, Warning};
 
 pub trait BatteryManager: Debug + Sized {
     type Iterator: BatteryIterator;
 
     fn new() -> Result<Self>;
 
+    /// Creates manager, which applies `quirks` to the devices values.
+    ///
+    /// Platforms which are not able to apply quirks are ignoring them.
+    fn with_quirks(quirks: Quirks) -> Result<Self> {
+        let _ = quirks;
+        Self::new()
+    }
+
     fn refresh(&self, battery: &mut <Self::Iterator as BatteryIterator>::Device) -> Result<()>;
+
+    /// Refresh only the requested `fields`, leaving other values untouched.
+    ///
+    /// Platforms which are fetching all battery values at once are not required
+    /// to implement this method, in that case whole battery is refreshed.
+    fn refresh_with(&self, battery: &mut <Self::Iterator as BatteryIterator>::Device, fields: Fields) -> Result<()> {
+        let _ = fields;
+        self.refresh(battery)
+    }
+
+    /// Refresh battery, waiting for at most `timeout` for the device to respond.
+    ///
+    /// Platforms which are not able to read the device in background
+    /// are not required to implement this method, in that case `timeout` is ignored.
+    fn refresh_timeout(
+        &self,
+        battery: &mut <Self::Iterator as BatteryIterator>::Device,
+        timeout: Duration,
+    ) -> Result<()> {
+        let _ = timeout;
+        self.refresh(battery)
+    }
+
+    /// Refresh multiple batteries at once, returning results in the same order.
+    ///
+    /// `concurrently` is a hint; platforms which can't read devices in parallel
+    /// are refreshing them one by one.
+    fn refresh_all(
+        &self,
+        batteries: Vec<&mut <Self::Iterator as BatteryIterator>::Device>,
+        concurrently: bool,
+    ) -> Vec<Result<()>> {
+        let _ = concurrently;
+        batteries.into_iter().map(|battery| self.refresh(battery)).collect()
+    }
 }
 
 pub trait BatteryIterator: Iterator<Item = Result<<Self as BatteryIterator>::Device>> + Debug + Sized {
@@ -28,10 +74,62 @@ pub trait BatteryIterator: Iterator<Item = Result<<Self as BatteryIterator>::Dev
     /// Implemented `next()` for `<Self as Iterator>` must preload all needed battery data
     /// in this method, because `BatteryDevice` methods are infallible.
     fn new(manager: Rc<Self::Manager>) -> Result<Self>;
+
+    /// Same as `new`, but yielded devices should not fail if some of their values can't be loaded,
+    /// reporting the problems via `BatteryDevice::warnings` instead.
+    ///
+    /// Platforms which are loading all values at once are not required to implement this method.
+    fn new_lenient(manager: Rc<Self::Manager>) -> Result<Self> {
+        Self::new(manager)
+    }
+
+    /// Checks which of the just refreshed `batteries` are still available and loads the ones added since.
+    ///
+    /// `results` are the `batteries` refresh results. Returns presence flags
+    /// in the same order as `batteries` and the added devices; devices which failed to load are skipped.
+    ///
+    /// Default implementation enumerates all devices, loading all their values;
+    /// platforms should override it if there is a cheaper way to list the devices.
+    fn presence(
+        manager: Rc<Self::Manager>,
+        batteries: &[&Self::Device],
+        results: &[Result<()>],
+    ) -> Result<(Vec<bool>, Vec<Self::Device>)> {
+        let _ = results;
+        let mut present = vec![false; batteries.len()];
+        let mut added = Vec::new();
+        for device in Self::new(manager)? {
+            // Failed device will be reported by its own refresh result
+            let device = match device {
+                Ok(device) => device,
+                Err(_) => continue,
+            };
+
+            // Identical devices without serial numbers are matched in order, one device per battery
+            let known = batteries
+                .iter()
+                .enumerate()
+                .position(|(idx, battery)| !present[idx] && battery.is_same_device(&device));
+            match known {
+                Some(idx) => present[idx] = true,
+                None => added.push(device),
+            }
+        }
+
+        Ok((present, added))
+    }
 }
 
 /// Underline type for `Battery`, different for each supported platform.
 pub trait BatteryDevice: Sized + Debug {
+    /// Checks if both values are representing the same physical device.
+    ///
+    /// Default implementation compares static device information,
+    /// platforms should override it if there is a more precise way to do that.
+    fn is_same_device(&self, other: &Self) -> bool {
+        self.vendor() == other.vendor() && self.model() == other.model() && self.serial_number() == other.serial_number()
+    }
+
     fn state_of_health(&self) -> Ratio {
         // It it possible to get values greater that `1.0`, which is logical nonsense,
         // forcing the value to be in `0.0..=1.0` range
@@ -44,6 +142,28 @@ pub trait BatteryDevice: Sized + Debug {
         (self.energy() / self.energy_full()).into_bounded()
     }
 
+    /// Problems happened during the latest device load or refresh in the lenient mode.
+    fn warnings(&self) -> &[Warning] {
+        &[]
+    }
+
+    /// Values which failed to load in the lenient mode.
+    fn missing_fields(&self) -> Fields {
+        Fields::empty()
+    }
+
+    /// Origins of the device values.
+    ///
+    /// Platforms are not required to track them, so report is empty by default.
+    fn diagnostics(&self) -> Diagnostics {
+        Diagnostics::default()
+    }
+
+    /// Device name used by the OS, if there is any.
+    fn name(&self) -> Option<&str> {
+        None
+    }
+
     fn energy(&self) -> Energy;
 
     fn energy_full(&self) -> Energy;
@@ -68,6 +188,28 @@ pub trait BatteryDevice: Sized + Debug {
 
     fn cycle_count(&self) -> Option<u32>;
 
+    /// Device values without substitution of the missing ones.
+    ///
+    /// Default implementation is not able to tell if platform substituted some values,
+    /// so it marks all values read from device as reported ones,
+    /// and values calculated by this trait as derived ones.
+    fn readings(&self) -> Readings {
+        let derived = |value: Option<Time>| value.map_or(Reading::Unavailable, Reading::Derived);
+        Readings {
+            state_of_charge: Reading::Derived(self.state_of_charge()),
+            state_of_health: Reading::Derived(self.state_of_health()),
+            energy: Reading::Reported(self.energy()),
+            energy_full: Reading::Reported(self.energy_full()),
+            energy_full_design: Reading::Reported(self.energy_full_design()),
+            energy_rate: Reading::Reported(self.energy_rate()),
+            voltage: Reading::Reported(self.voltage()),
+            temperature: Reading::from(self.temperature()),
+            cycle_count: Reading::from(self.cycle_count()),
+            time_to_full: derived(self.time_to_full()),
+            time_to_empty: derived(self.time_to_empty()),
+        }
+    }
+
     // Default implementation for `time_to_full` and `time_to_empty`
     // uses calculation based on the current energy flow,
     // but if device provides by itself provides these **instant** values (do not use average values),
@@ -120,3 +262,131 @@ pub trait BatteryDevice: Sized + Debug {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::vec;
+
+    use super::{BatteryDevice, BatteryIterator, BatteryManager};
+    use crate::units::{ElectricPotential, Energy, Power, ThermodynamicTemperature};
+    use crate::{Result, State, Technology};
+
+    #[derive(Debug)]
+    struct FakeManager {
+        count: Cell<usize>,
+    }
+
+    impl BatteryManager for FakeManager {
+        type Iterator = FakeIterator;
+
+        fn new() -> Result<Self> {
+            Ok(FakeManager {
+                count: Cell::new(0),
+            })
+        }
+
+        fn refresh(&self, _battery: &mut FakeDevice) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[derive(Debug)]
+    struct FakeIterator(vec::IntoIter<FakeDevice>);
+
+    impl BatteryIterator for FakeIterator {
+        type Manager = FakeManager;
+        type Device = FakeDevice;
+
+        fn new(manager: Rc<FakeManager>) -> Result<Self> {
+            let devices = (0..manager.count.get()).map(|_| FakeDevice).collect::<Vec<_>>();
+            Ok(FakeIterator(devices.into_iter()))
+        }
+    }
+
+    impl Iterator for FakeIterator {
+        type Item = Result<FakeDevice>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            self.0.next().map(Ok)
+        }
+    }
+
+    /// Device of the same model without serial number, as all of them are.
+    #[derive(Debug)]
+    struct FakeDevice;
+
+    impl BatteryDevice for FakeDevice {
+        fn energy(&self) -> Energy {
+            Energy::default()
+        }
+
+        fn energy_full(&self) -> Energy {
+            Energy::default()
+        }
+
+        fn energy_full_design(&self) -> Energy {
+            Energy::default()
+        }
+
+        fn energy_rate(&self) -> Power {
+            Power::default()
+        }
+
+        fn state(&self) -> State {
+            State::Unknown
+        }
+
+        fn voltage(&self) -> ElectricPotential {
+            ElectricPotential::default()
+        }
+
+        fn temperature(&self) -> Option<ThermodynamicTemperature> {
+            None
+        }
+
+        fn vendor(&self) -> Option<&str> {
+            Some("SMP")
+        }
+
+        fn model(&self) -> Option<&str> {
+            Some("5B10W13930")
+        }
+
+        fn serial_number(&self) -> Option<&str> {
+            None
+        }
+
+        fn technology(&self) -> Technology {
+            Technology::Unknown
+        }
+
+        fn cycle_count(&self) -> Option<u32> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_presence_without_serial_numbers() {
+        let manager = Rc::new(FakeManager::new().unwrap());
+        let batteries = [FakeDevice, FakeDevice];
+        let batteries = batteries.iter().collect::<Vec<_>>();
+        let results = vec![Ok(()), Ok(())];
+
+        manager.count.set(2);
+        let (present, added) = FakeIterator::presence(manager.clone(), &batteries, &results).unwrap();
+        assert_eq!(present, vec![true, true]);
+        assert!(added.is_empty());
+
+        manager.count.set(3);
+        let (present, added) = FakeIterator::presence(manager.clone(), &batteries, &results).unwrap();
+        assert_eq!(present, vec![true, true]);
+        assert_eq!(added.len(), 1);
+
+        manager.count.set(1);
+        let (present, added) = FakeIterator::presence(manager, &batteries, &results).unwrap();
+        assert_eq!(present, vec![true, false]);
+        assert!(added.is_empty());
+    }
+}
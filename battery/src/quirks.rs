@@ -0,0 +1,422 @@
+//! Hardware quirks.
+//!
+//! Some device drivers are reporting values in wrong units, with the wrong sign
+//! or are providing attributes which are plain garbage.
+//! Quirks are allowing to correct such values for the specific devices only,
+//! matching them by the battery vendor and model, device driver, system vendor
+//! and the attributes device does not provide.
+//!
+//! [Quirks::builtin](struct.Quirks.html#method.builtin) table is used by default,
+//! additional quirks can be provided with the
+//! [Manager::with_quirks](../struct.Manager.html#method.with_quirks) method.
+//!
+//! Only Linux implementation is applying quirks for now; corrections are referring
+//! to the attribute names from the `/sys/class/power_supply/<name>/` directory.
+//!
+//! # Example
+//!
+//! ```edition2018
+//! # use battery::{Manager, Result};
+//! use battery::quirks::{Correction, Quirk, Quirks};
+//!
+//! # fn main() -> Result<()> {
+//! let mut quirks = Quirks::builtin();
+//! quirks.push(
+//!     Quirk::new()
+//!         .vendor("SMP")
+//!         .model("5B10W13930")
+//!         .correction(Correction::InvertSign {
+//!             attribute: "current_now".into(),
+//!         }),
+//! );
+//!
+//! let manager = Manager::with_quirks(quirks)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! With the `quirks-toml` feature enabled, quirks can be loaded from the TOML file too:
+//!
+//! ```toml
+//! [[quirk]]
+//! vendor = "SMP"
+//! model = "5B10W13930"
+//! driver = "battery"
+//! sys_vendor = "LENOVO"
+//! missing = ["power_now"]
+//! ignore = ["cycle_count"]
+//! invert_sign = ["current_now"]
+//! swap_units = ["energy_now"]
+//!
+//! [quirk.scale]
+//! power_now = 1000.0
+//!
+//! [quirk.ignore_below]
+//! current_now = 1000.0
+//!
+//! [quirk.missing_below]
+//! charge_full = 1.0
+//! ```
+
+use std::slice;
+
+/// Correction applied to the device attribute.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Correction {
+    /// Attribute holds value in the "sibling" units: energy (`µWh`) attribute holds charge (`µAh`)
+    /// or power (`µW`) attribute holds current (`µA`), and vice versa.
+    ///
+    /// For example, swapping `energy_now` means that `energy_now` is read as `charge_now`
+    /// and `charge_now` is read as `energy_now`.
+    SwapUnits { attribute: String },
+    /// Attribute value has an inverted sign.
+    InvertSign { attribute: String },
+    /// Attribute value is garbage and should be treated as a missing one.
+    Ignore { attribute: String },
+    /// Attribute value should be multiplied by the `factor`.
+    Scale { attribute: String, factor: f32 },
+    /// Attribute values which are not greater than the `threshold` are garbage
+    /// and should be treated as missing ones.
+    IgnoreBelow { attribute: String, threshold: f32 },
+
+    // Awaiting for https://github.com/rust-lang/rust/issues/44109
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+impl Correction {
+    /// Attribute this correction is applied to.
+    pub fn attribute(&self) -> &str {
+        match self {
+            Correction::SwapUnits { attribute } => attribute,
+            Correction::InvertSign { attribute } => attribute,
+            Correction::Ignore { attribute } => attribute,
+            Correction::Scale { attribute, .. } => attribute,
+            Correction::IgnoreBelow { attribute, .. } => attribute,
+            _ => "",
+        }
+    }
+}
+
+/// Set of corrections for the matching devices.
+///
+/// Device matches the quirk if it matches all of the specified conditions,
+/// values are compared case-insensitively. Quirk without conditions matches all devices.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Quirk {
+    vendor: Option<String>,
+    model: Option<String>,
+    driver: Option<String>,
+    sys_vendor: Option<String>,
+    missing: Vec<String>,
+    missing_below: Vec<(String, f32)>,
+    corrections: Vec<Correction>,
+}
+
+impl Quirk {
+    /// Creates quirk which matches all devices and has no corrections.
+    pub fn new() -> Quirk {
+        Quirk::default()
+    }
+
+    /// Matches devices with the battery vendor.
+    pub fn vendor<T: Into<String>>(mut self, vendor: T) -> Quirk {
+        self.vendor = Some(vendor.into());
+        self
+    }
+
+    /// Matches devices with the battery model.
+    pub fn model<T: Into<String>>(mut self, model: T) -> Quirk {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Matches devices handled by the driver, for example, `battery` for the ACPI batteries on Linux.
+    pub fn driver<T: Into<String>>(mut self, driver: T) -> Quirk {
+        self.driver = Some(driver.into());
+        self
+    }
+
+    /// Matches devices in the system from the vendor, as reported by DMI (`/sys/class/dmi/id/sys_vendor`).
+    pub fn sys_vendor<T: Into<String>>(mut self, sys_vendor: T) -> Quirk {
+        self.sys_vendor = Some(sys_vendor.into());
+        self
+    }
+
+    /// Matches devices which do not provide the `attribute`.
+    pub fn missing<T: Into<String>>(mut self, attribute: T) -> Quirk {
+        self.missing.push(attribute.into());
+        self
+    }
+
+    /// Matches devices which do not provide the `attribute` or report its value
+    /// not greater than the `threshold`, that is, the value which is ignored by the
+    /// [IgnoreBelow](enum.Correction.html#variant.IgnoreBelow) correction.
+    pub fn missing_below<T: Into<String>>(mut self, attribute: T, threshold: f32) -> Quirk {
+        self.missing_below.push((attribute.into(), threshold));
+        self
+    }
+
+    /// Adds correction applied to the matching devices.
+    pub fn correction(mut self, correction: Correction) -> Quirk {
+        self.corrections.push(correction);
+        self
+    }
+
+    /// Corrections applied to the matching devices.
+    pub fn corrections(&self) -> &[Correction] {
+        &self.corrections
+    }
+
+    /// Checks if device matches this quirk conditions, except the ones on the attributes values,
+    /// which are checked by the [matches_values](#method.matches_values) method.
+    pub fn matches(&self, device: &DeviceInfo) -> bool {
+        fn matches(expected: &Option<String>, actual: Option<&str>) -> bool {
+            match (expected, actual) {
+                (None, _) => true,
+                (Some(expected), Some(actual)) => expected.trim().eq_ignore_ascii_case(actual.trim()),
+                (Some(_), None) => false,
+            }
+        }
+
+        let is_missing = |attribute: &String| match device.attributes {
+            Some(attributes) => !attributes.contains(attribute),
+            None => false,
+        };
+
+        matches(&self.vendor, device.vendor)
+            && matches(&self.model, device.model)
+            && matches(&self.driver, device.driver)
+            && matches(&self.sys_vendor, device.sys_vendor)
+            && self.missing.iter().all(is_missing)
+    }
+
+    /// Checks if the attributes `values` match this quirk conditions.
+    ///
+    /// Attributes which are not provided or are not numbers should be omitted from the `values`.
+    pub fn matches_values(&self, values: &[(String, f32)]) -> bool {
+        self.missing_below.iter().all(|(attribute, threshold)| {
+            match values.iter().find(|(name, _)| name == attribute) {
+                Some((_, value)) => value <= threshold,
+                None => true,
+            }
+        })
+    }
+}
+
+/// Device properties quirks are matched against.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct DeviceInfo<'a> {
+    pub vendor: Option<&'a str>,
+    pub model: Option<&'a str>,
+    pub driver: Option<&'a str>,
+    pub sys_vendor: Option<&'a str>,
+    /// Attributes provided by the device, `None` if they are unknown.
+    pub attributes: Option<&'a [String]>,
+}
+
+/// Table of quirks.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Quirks {
+    quirks: Vec<Quirk>,
+}
+
+impl Quirks {
+    /// Creates empty table.
+    pub fn new() -> Quirks {
+        Quirks::default()
+    }
+
+    /// Quirks for the known broken devices, which are applied by default.
+    ///
+    /// Besides the device-specific entries, it holds the workarounds needed by the most of devices:
+    ///
+    ///  * `power_now` values up to `10 mW` and `charge_*` values up to `1 µAh` are ignored,
+    ///    as drivers are reporting them instead of the missing values
+    ///  * `current_now` is read as power in `µW` for the legacy drivers,
+    ///    which are not providing `charge_full`, `charge_full_design` and `power_now` attributes
+    ///    or are reporting the ignored values in them (source: upower)
+    pub fn builtin() -> Quirks {
+        let ignore_below = |attribute: &str, threshold| Correction::IgnoreBelow {
+            attribute: attribute.to_string(),
+            threshold,
+        };
+
+        let mut quirks = Quirks::new();
+        quirks.push(
+            Quirk::new()
+                .correction(ignore_below("power_now", 10_000.0))
+                .correction(ignore_below("charge_now", 1.0))
+                .correction(ignore_below("charge_avg", 1.0))
+                .correction(ignore_below("charge_full", 1.0))
+                .correction(ignore_below("charge_full_design", 1.0)),
+        );
+        quirks.push(
+            Quirk::new()
+                .missing_below("power_now", 10_000.0)
+                .missing_below("charge_full", 1.0)
+                .missing_below("charge_full_design", 1.0)
+                .correction(Correction::SwapUnits {
+                    attribute: "current_now".into(),
+                }),
+        );
+
+        quirks
+    }
+
+    /// Adds `quirk` to the table.
+    pub fn push(&mut self, quirk: Quirk) {
+        self.quirks.push(quirk);
+    }
+
+    /// Adds all quirks from the `other` table.
+    pub fn extend(&mut self, other: Quirks) {
+        self.quirks.extend(other.quirks);
+    }
+
+    /// Returns an iterator over the quirks.
+    pub fn iter(&self) -> slice::Iter<'_, Quirk> {
+        self.quirks.iter()
+    }
+
+    /// Returns `true` if table has no quirks.
+    pub fn is_empty(&self) -> bool {
+        self.quirks.is_empty()
+    }
+
+    /// Names of the attributes which values are compared by the quirks conditions.
+    ///
+    /// These values might change with time, so they are checked on each device read.
+    pub fn compared_attributes(&self) -> Vec<&str> {
+        let mut attributes = self
+            .quirks
+            .iter()
+            .flat_map(|quirk| quirk.missing_below.iter().map(|(attribute, _)| attribute.as_str()))
+            .collect::<Vec<_>>();
+        attributes.sort();
+        attributes.dedup();
+
+        attributes
+    }
+
+    /// Returns the quirks matching the `device`, which conditions on the attributes values
+    /// are left to be checked by the [corrections_with](#method.corrections_with) method.
+    pub fn candidates_for(&self, device: &DeviceInfo) -> Quirks {
+        let quirks = self.quirks.iter().filter(|quirk| quirk.matches(device)).cloned().collect();
+
+        Quirks {
+            quirks,
+        }
+    }
+
+    /// Collects corrections of all quirks matching the attributes `values`.
+    pub fn corrections_with(&self, values: &[(String, f32)]) -> Vec<Correction> {
+        self.quirks
+            .iter()
+            .filter(|quirk| quirk.matches_values(values))
+            .flat_map(|quirk| quirk.corrections.iter().cloned())
+            .collect()
+    }
+}
+
+#[cfg(feature = "quirks-toml")]
+mod toml_impl {
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::path::Path;
+
+    use serde::Deserialize;
+
+    use super::{Correction, Quirk, Quirks};
+    use crate::{Error, Result};
+
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct File {
+        #[serde(default)]
+        quirk: Vec<Entry>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Entry {
+        vendor: Option<String>,
+        model: Option<String>,
+        driver: Option<String>,
+        sys_vendor: Option<String>,
+        #[serde(default)]
+        missing: Vec<String>,
+        #[serde(default)]
+        missing_below: BTreeMap<String, f32>,
+        #[serde(default)]
+        swap_units: Vec<String>,
+        #[serde(default)]
+        invert_sign: Vec<String>,
+        #[serde(default)]
+        ignore: Vec<String>,
+        #[serde(default)]
+        scale: BTreeMap<String, f32>,
+        #[serde(default)]
+        ignore_below: BTreeMap<String, f32>,
+    }
+
+    impl From<Entry> for Quirk {
+        fn from(entry: Entry) -> Quirk {
+            let mut corrections = Vec::new();
+            corrections.extend(entry.swap_units.into_iter().map(|attribute| Correction::SwapUnits {
+                attribute,
+            }));
+            corrections.extend(entry.invert_sign.into_iter().map(|attribute| Correction::InvertSign {
+                attribute,
+            }));
+            corrections.extend(entry.ignore.into_iter().map(|attribute| Correction::Ignore {
+                attribute,
+            }));
+            corrections.extend(entry.scale.into_iter().map(|(attribute, factor)| Correction::Scale {
+                attribute,
+                factor,
+            }));
+            corrections.extend(
+                entry
+                    .ignore_below
+                    .into_iter()
+                    .map(|(attribute, threshold)| Correction::IgnoreBelow {
+                        attribute,
+                        threshold,
+                    }),
+            );
+
+            Quirk {
+                vendor: entry.vendor,
+                model: entry.model,
+                driver: entry.driver,
+                sys_vendor: entry.sys_vendor,
+                missing: entry.missing,
+                missing_below: entry.missing_below.into_iter().collect(),
+                corrections,
+            }
+        }
+    }
+
+    impl Quirks {
+        /// Parses quirks table from the TOML document.
+        ///
+        /// See the [module documentation](index.html) for the format description.
+        pub fn from_toml(document: &str) -> Result<Quirks> {
+            let file: File = toml::from_str(document)
+                .map_err(|e| Error::invalid_data(format!("Invalid quirks document: {}", e)))?;
+
+            Ok(Quirks {
+                quirks: file.quirk.into_iter().map(Quirk::from).collect(),
+            })
+        }
+
+        /// Reads quirks table from the TOML file.
+        pub fn from_toml_file<T: AsRef<Path>>(path: T) -> Result<Quirks> {
+            let path = path.as_ref();
+            let document = fs::read_to_string(path).map_err(|e| Error::from(e).with_attribute(path))?;
+
+            Self::from_toml(&document).map_err(|e| e.with_attribute(path))
+        }
+    }
+}
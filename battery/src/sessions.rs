@@ -0,0 +1,443 @@
+//! Charge and discharge sessions.
+//!
+//! [SessionTracker] segments the stream of [samples](../history/struct.Sample.html) taken on repeated refreshes
+//! into the continuous [sessions](struct.Session.html) of charging or discharging, separately
+//! for each [BatteryId](../struct.BatteryId.html). Session ends when the battery switches into another state;
+//! brief `Unknown` states (which some drivers are reporting around the charger plug events)
+//! are ignored, unless they are lasting longer than the grace period (two minutes by default).
+//!
+//! Each completed session reports its duration, energy charged or discharged, average and peak power,
+//! and the state of charge at its start and end. Sessions could be also extracted from the recorded
+//! [history](../history/index.html) with the [segment] function.
+//!
+//! This module requires the `history` feature.
+//!
+//! # Example
+//!
+//! ```edition2018,no_run
+//! # use std::thread;
+//! # use std::time::Duration;
+//! # use battery::Result;
+//! use battery::sessions::SessionTracker;
+//!
+//! # fn main() -> Result<()> {
+//! let mut tracker = SessionTracker::new();
+//! let manager = battery::Manager::new()?;
+//! let mut battery = manager.batteries()?.next().unwrap()?;
+//! loop {
+//!     manager.refresh(&mut battery)?;
+//!     if let Some(session) = tracker.update(&battery) {
+//!         println!("{}", session);
+//!     }
+//!     thread::sleep(Duration::from_secs(30));
+//! }
+//! # }
+//! ```
+//!
+//! # Format
+//!
+//! Sessions are formatted as the lines of space-separated values:
+//!
+//! ```text
+//! 1700000000000 1700003600000 charging 108000 30 42.5 0.35 0.95
+//! ```
+//!
+//! Values are the start and end time in milliseconds since the Unix epoch, state, energy in joules,
+//! average and peak power in watts, start and end state of charge ratio.
+//! Values which were not available are written as `-`.
+//!
+//! [SessionTracker]: struct.SessionTracker.html
+//! [segment]: fn.segment.html
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::history::{seconds, unix_millis, Sample};
+use crate::units::energy::joule;
+use crate::units::power::watt;
+use crate::units::ratio::ratio;
+use crate::units::{Energy, Power, Ratio};
+use crate::{Battery, BatteryId, Error, Result, State};
+
+/// Completed charge or discharge session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    state: State,
+    start: SystemTime,
+    end: SystemTime,
+    energy: Option<Energy>,
+    average_power: Option<Power>,
+    peak_power: Option<Power>,
+    start_state_of_charge: Option<Ratio>,
+    end_state_of_charge: Option<Ratio>,
+}
+
+impl Session {
+    /// Either `State::Charging` or `State::Discharging`.
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Time of the first sample in the session.
+    pub fn start(&self) -> SystemTime {
+        self.start
+    }
+
+    /// Time of the last sample in the session.
+    pub fn end(&self) -> SystemTime {
+        self.end
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.end.duration_since(self.start).unwrap_or_default()
+    }
+
+    /// Energy charged into or discharged from the battery, always positive.
+    ///
+    /// It is the energy difference between the session start and end if the device reports energy,
+    /// otherwise the energy rate integrated over the session.
+    pub fn energy(&self) -> Option<Energy> {
+        self.energy
+    }
+
+    pub fn average_power(&self) -> Option<Power> {
+        self.average_power
+    }
+
+    /// Highest energy rate reported during the session.
+    pub fn peak_power(&self) -> Option<Power> {
+        self.peak_power
+    }
+
+    pub fn start_state_of_charge(&self) -> Option<Ratio> {
+        self.start_state_of_charge
+    }
+
+    pub fn end_state_of_charge(&self) -> Option<Ratio> {
+        self.end_state_of_charge
+    }
+}
+
+impl fmt::Display for Session {
+    /// Formats session as the space-separated line.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn value<T: fmt::Display>(f: &mut fmt::Formatter, value: Option<T>) -> fmt::Result {
+            match value {
+                Some(value) => write!(f, " {}", value),
+                None => f.write_str(" -"),
+            }
+        }
+
+        write!(f, "{} {} {}", unix_millis(self.start), unix_millis(self.end), self.state)?;
+        value(f, self.energy.map(|value| value.get::<joule>()))?;
+        value(f, self.average_power.map(|value| value.get::<watt>()))?;
+        value(f, self.peak_power.map(|value| value.get::<watt>()))?;
+        value(f, self.start_state_of_charge.map(|value| value.get::<ratio>()))?;
+        value(f, self.end_state_of_charge.map(|value| value.get::<ratio>()))
+    }
+}
+
+impl FromStr for Session {
+    type Err = Error;
+
+    /// Parses the session line.
+    fn from_str(s: &str) -> Result<Session> {
+        fn value(part: Option<&str>) -> Option<Option<f32>> {
+            match part? {
+                "-" => Some(None),
+                part => part.parse().ok().map(Some),
+            }
+        }
+
+        let invalid = || Error::invalid_data("Malformed session").with_raw_value(s);
+        let mut parts = s.split_whitespace();
+        let start = parts.next().and_then(|part| part.parse::<u64>().ok()).ok_or_else(invalid)?;
+        let end = parts.next().and_then(|part| part.parse::<u64>().ok()).ok_or_else(invalid)?;
+        let state = parts.next().and_then(|part| part.parse::<State>().ok()).ok_or_else(invalid)?;
+        let energy = value(parts.next()).ok_or_else(invalid)?;
+        let average_power = value(parts.next()).ok_or_else(invalid)?;
+        let peak_power = value(parts.next()).ok_or_else(invalid)?;
+        let start_state_of_charge = value(parts.next()).ok_or_else(invalid)?;
+        let end_state_of_charge = value(parts.next()).ok_or_else(invalid)?;
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+
+        Ok(Session {
+            state,
+            start: UNIX_EPOCH + Duration::from_millis(start),
+            end: UNIX_EPOCH + Duration::from_millis(end),
+            energy: energy.map(Energy::new::<joule>),
+            average_power: average_power.map(Power::new::<watt>),
+            peak_power: peak_power.map(Power::new::<watt>),
+            start_state_of_charge: start_state_of_charge.map(Ratio::new::<ratio>),
+            end_state_of_charge: end_state_of_charge.map(Ratio::new::<ratio>),
+        })
+    }
+}
+
+/// Session which is still in progress.
+#[derive(Debug, Clone)]
+struct Pending {
+    first: Sample,
+    last: Sample,
+    /// Energy rate integrated over the session, `None` until there is a rate reported for some interval.
+    integrated: Option<Energy>,
+    peak_power: Option<Power>,
+    /// Time of the first `Unknown` sample after the last sample in the session state;
+    /// session ends if the following `Unknown` samples are reported for longer than the grace period.
+    unknown_since: Option<SystemTime>,
+}
+
+impl Pending {
+    fn new(sample: &Sample) -> Pending {
+        Pending {
+            first: sample.clone(),
+            last: sample.clone(),
+            integrated: None,
+            peak_power: sample.energy_rate().map(|rate| Power::new::<watt>(rate.get::<watt>().abs())),
+            unknown_since: None,
+        }
+    }
+
+    fn push(&mut self, sample: &Sample) {
+        let elapsed = seconds(sample.time().duration_since(self.last.time()).unwrap_or_default()) as f32;
+        // Some drivers are reporting the signed rate, so only its magnitude is used
+        let magnitude = |sample: &Sample| sample.energy_rate().map(|rate| rate.get::<watt>().abs());
+        let rate = match (magnitude(&self.last), magnitude(sample)) {
+            (Some(previous), Some(current)) => Some((previous + current) / 2.0),
+            (previous, current) => previous.or(current),
+        };
+        if let Some(rate) = rate {
+            let energy = Energy::new::<joule>(rate * elapsed);
+            self.integrated = Some(self.integrated.map_or(energy, |integrated| integrated + energy));
+        }
+        if let Some(rate) = sample.energy_rate() {
+            let rate = Power::new::<watt>(rate.get::<watt>().abs());
+            self.peak_power = match self.peak_power {
+                Some(peak) if peak >= rate => Some(peak),
+                _ => Some(rate),
+            };
+        }
+        self.last = sample.clone();
+        self.unknown_since = None;
+    }
+
+    /// Returns the completed session, `None` if it consists of a single sample.
+    fn finish(self) -> Option<Session> {
+        let duration = seconds(self.last.time().duration_since(self.first.time()).ok()?) as f32;
+        if duration <= 0.0 {
+            return None;
+        }
+
+        let energy = match (self.first.energy(), self.last.energy()) {
+            (Some(first), Some(last)) => Some(Energy::new::<joule>((last - first).get::<joule>().abs())),
+            _ => self.integrated,
+        };
+
+        Some(Session {
+            state: self.first.state(),
+            start: self.first.time(),
+            end: self.last.time(),
+            energy,
+            average_power: energy.map(|energy| Power::new::<watt>(energy.get::<joule>() / duration)),
+            peak_power: self.peak_power,
+            start_state_of_charge: self.first.state_of_charge(),
+            end_state_of_charge: self.last.state_of_charge(),
+        })
+    }
+}
+
+/// Segments the batteries samples into the charge and discharge sessions.
+///
+/// See the [module documentation](index.html) for details.
+#[derive(Debug, Clone)]
+pub struct SessionTracker {
+    grace_period: Duration,
+    pending: HashMap<BatteryId, Pending>,
+}
+
+impl SessionTracker {
+    pub fn new() -> SessionTracker {
+        SessionTracker {
+            grace_period: Duration::from_secs(2 * 60),
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Sets how long the `Unknown` state could last without ending the session.
+    pub fn with_grace_period(mut self, grace_period: Duration) -> SessionTracker {
+        self.grace_period = grace_period;
+        self
+    }
+
+    pub fn grace_period(&self) -> Duration {
+        self.grace_period
+    }
+
+    /// Accounts the current `battery` readings, returning the session completed by them.
+    pub fn update(&mut self, battery: &Battery) -> Option<Session> {
+        self.push(&battery.id(), &Sample::from_battery(battery, SystemTime::now()))
+    }
+
+    /// Accounts the battery `id` sample, returning the session completed by it.
+    ///
+    /// Samples are expected to be pushed in the time order.
+    pub fn push(&mut self, id: &BatteryId, sample: &Sample) -> Option<Session> {
+        let state = sample.state();
+        let is_session = state == State::Charging || state == State::Discharging;
+        let mut pending = match self.pending.remove(id) {
+            Some(pending) => pending,
+            None => {
+                if is_session {
+                    self.pending.insert(id.clone(), Pending::new(sample));
+                }
+                return None;
+            }
+        };
+
+        if state == State::Unknown {
+            let since = *pending.unknown_since.get_or_insert(sample.time());
+            if sample.time().duration_since(since).unwrap_or_default() > self.grace_period {
+                return pending.finish();
+            }
+            self.pending.insert(id.clone(), pending);
+            return None;
+        }
+
+        if state == pending.first.state() {
+            pending.push(sample);
+            self.pending.insert(id.clone(), pending);
+            return None;
+        }
+
+        if is_session {
+            self.pending.insert(id.clone(), Pending::new(sample));
+        }
+        pending.finish()
+    }
+
+    /// Completes the battery `id` session in progress, for example, when the battery is removed.
+    pub fn finish(&mut self, id: &BatteryId) -> Option<Session> {
+        self.pending.remove(id).and_then(Pending::finish)
+    }
+}
+
+impl Default for SessionTracker {
+    fn default() -> SessionTracker {
+        SessionTracker::new()
+    }
+}
+
+/// Segments time-ordered `samples` of a single battery into sessions,
+/// including the one which was in progress at the last sample.
+pub fn segment(samples: &[Sample], grace_period: Duration) -> Vec<Session> {
+    let id = BatteryId::new::<String>(None, None, None);
+    let mut tracker = SessionTracker::new().with_grace_period(grace_period);
+    let mut sessions = samples
+        .iter()
+        .filter_map(|sample| tracker.push(&id, sample))
+        .collect::<Vec<_>>();
+    sessions.extend(tracker.finish(&id));
+
+    sessions
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use approx::assert_abs_diff_eq;
+
+    use super::{segment, Session, SessionTracker};
+    use crate::history::tests::id;
+    use crate::history::{self, Sample};
+    use crate::units::energy::watt_hour;
+    use crate::units::power::watt;
+    use crate::units::ratio::percent;
+    use crate::units::Power;
+    use crate::{BatteryId, State};
+
+    fn sample(minutes: u64, state: State, percents: f32, rate: f32) -> Sample {
+        history::tests::sample(1_700_000_000 + minutes * 60, state, percents).with_energy_rate(Power::new::<watt>(rate))
+    }
+
+    #[test]
+    fn test_push() {
+        let mut tracker = SessionTracker::new();
+        let id = id();
+        let other = BatteryId::new(None, Some("Other"), None);
+
+        assert_eq!(tracker.push(&id, &sample(0, State::Discharging, 80.0, 10.0)), None);
+        assert_eq!(tracker.push(&other, &sample(0, State::Charging, 10.0, 20.0)), None);
+        assert_eq!(tracker.push(&id, &sample(30, State::Discharging, 70.0, 14.0)), None);
+        // Brief flap around the charger plug event is ignored
+        assert_eq!(tracker.push(&id, &sample(31, State::Unknown, 70.0, 0.0)), None);
+        assert_eq!(tracker.push(&id, &sample(60, State::Discharging, 60.0, 8.0)), None);
+
+        let session = tracker.push(&id, &sample(61, State::Charging, 60.0, 30.0)).unwrap();
+        assert_eq!(session.state(), State::Discharging);
+        assert_eq!(session.duration(), Duration::from_secs(60 * 60));
+        assert_abs_diff_eq!(session.energy().unwrap().get::<watt_hour>(), 10.0, epsilon = 0.001);
+        assert_abs_diff_eq!(session.average_power().unwrap().get::<watt>(), 10.0, epsilon = 0.001);
+        assert_abs_diff_eq!(session.peak_power().unwrap().get::<watt>(), 14.0, epsilon = 0.001);
+        assert_abs_diff_eq!(session.start_state_of_charge().unwrap().get::<percent>(), 80.0, epsilon = 0.001);
+        assert_abs_diff_eq!(session.end_state_of_charge().unwrap().get::<percent>(), 60.0, epsilon = 0.001);
+
+        // Long `Unknown` state ends the session at the last charging sample
+        assert_eq!(tracker.push(&id, &sample(71, State::Charging, 70.0, 30.0)), None);
+        assert_eq!(tracker.push(&id, &sample(72, State::Unknown, 70.0, 0.0)), None);
+        let session = tracker.push(&id, &sample(80, State::Unknown, 70.0, 0.0)).unwrap();
+        assert_eq!(session.state(), State::Charging);
+        assert_eq!(session.duration(), Duration::from_secs(10 * 60));
+        assert_abs_diff_eq!(session.energy().unwrap().get::<watt_hour>(), 5.0, epsilon = 0.001);
+        assert_eq!(tracker.push(&id, &sample(81, State::Charging, 70.0, 30.0)), None);
+        assert_eq!(tracker.finish(&id), None);
+
+        // Single sample session is not reported
+        assert_eq!(tracker.push(&other, &sample(5, State::Full, 100.0, 0.0)), None);
+    }
+
+    #[test]
+    fn test_segment() {
+        let samples = [
+            sample(0, State::Charging, 50.0, 20.0).with_energy_rate(Power::new::<watt>(20.0)),
+            sample(60, State::Charging, 90.0, 40.0),
+            sample(61, State::Full, 100.0, 0.0),
+            sample(62, State::Discharging, 100.0, 6.0),
+            sample(122, State::Discharging, 94.0, 6.0),
+        ];
+        let sessions = segment(&samples, Duration::from_secs(120));
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].state(), State::Charging);
+        assert_abs_diff_eq!(sessions[0].energy().unwrap().get::<watt_hour>(), 20.0, epsilon = 0.001);
+        assert_eq!(sessions[1].state(), State::Discharging);
+        assert_abs_diff_eq!(sessions[1].energy().unwrap().get::<watt_hour>(), 3.0, epsilon = 0.001);
+
+        // Energy rate is integrated if the energy is not reported
+        let samples = [
+            Sample::new(UNIX_EPOCH, State::Discharging).with_energy_rate(Power::new::<watt>(10.0)),
+            Sample::new(UNIX_EPOCH + Duration::from_secs(1800), State::Discharging)
+                .with_energy_rate(Power::new::<watt>(-20.0)),
+        ];
+        let sessions = segment(&samples, Duration::from_secs(120));
+        assert_abs_diff_eq!(sessions[0].energy().unwrap().get::<watt_hour>(), 7.5, epsilon = 0.001);
+        assert_abs_diff_eq!(sessions[0].peak_power().unwrap().get::<watt>(), 20.0, epsilon = 0.001);
+        assert_eq!(sessions[0].start_state_of_charge(), None);
+    }
+
+    #[test]
+    fn test_format() {
+        let samples = [sample(0, State::Charging, 50.0, 20.0), sample(60, State::Charging, 90.0, 40.0)];
+        let session = segment(&samples, Duration::from_secs(120)).remove(0);
+        let line = session.to_string();
+        assert!(line.starts_with("1700000000000 1700003600000 charging "));
+        assert_eq!(line.parse::<Session>().unwrap(), session);
+
+        let line = "1700000000000 1700003600000 discharging - - - 0.5 0.4";
+        assert_eq!(line.parse::<Session>().unwrap().to_string(), line);
+        assert!("1700000000000 charging".parse::<Session>().is_err());
+    }
+}
@@ -0,0 +1,540 @@
+//! Virtual battery, which charges and discharges over the simulated time.
+//!
+//! [Simulation] models one lithium-ion battery: it is drained by the configurable load,
+//! charged with the CC/CV (constant current, then constant voltage) curve while plugged in,
+//! heated by the internal resistance losses and slowly loses capacity with the charge cycles.
+//!
+//! Time is virtual and advances only with the [Simulation::advance] calls;
+//! [Scenario] describes what happens at the specific moments (plug in at 10 minutes, set load to 25 W...).
+//!
+//! Simulated battery is exposed as a fake `/sys/class/power_supply` tree in the temporary directory,
+//! so it is available via the normal [Manager](../struct.Manager.html) API
+//! and is handled by exactly the same code as the real devices.
+//!
+//! This module requires the `simulation` feature. Since the simulated battery is read by the sysfs backend,
+//! it is available on Linux only; other platforms have no way to substitute the system devices.
+//!
+//! # Example
+//!
+//! ```edition2018
+//! # use std::time::Duration;
+//! # use battery::Result;
+//! use battery::simulation::{Profile, Scenario, Simulation};
+//! use battery::State;
+//!
+//! # fn main() -> Result<()> {
+//! let scenario = Scenario::parse(
+//!     "0s load 25 W
+//!      30m plug",
+//! )?;
+//! let mut simulation = Simulation::new(Profile::new(), scenario)?;
+//! let manager = simulation.manager()?;
+//! let mut battery = manager.batteries()?.next().unwrap()?;
+//!
+//! simulation.advance(Duration::from_secs(10 * 60))?;
+//! manager.refresh(&mut battery)?;
+//! assert_eq!(battery.state(), State::Discharging);
+//!
+//! simulation.advance(Duration::from_secs(30 * 60))?;
+//! manager.refresh(&mut battery)?;
+//! assert_eq!(battery.state(), State::Charging);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [Simulation]: struct.Simulation.html
+//! [Simulation::advance]: struct.Simulation.html#method.advance
+//! [Scenario]: struct.Scenario.html
+
+use std::f64;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::archive::{self, Frame};
+use crate::units::electric_current::ampere;
+use crate::units::electric_potential::volt;
+use crate::units::energy::joule;
+use crate::units::power::watt;
+use crate::units::ratio::ratio;
+use crate::units::thermodynamic_temperature::{degree_celsius, kelvin};
+use crate::units::{ElectricCurrent, ElectricPotential, Energy, Power, Ratio, ThermodynamicTemperature};
+use crate::{Error, Manager, Result, State};
+
+// Simulation step, values are changing slowly enough for the one second resolution
+const STEP: Duration = Duration::from_secs(1);
+
+/// Battery cell parameters.
+///
+/// Default profile describes a typical `50 Wh` three-cell notebook battery.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Profile {
+    energy_full_design: f64,
+    voltage_empty: f64,
+    voltage_full: f64,
+    voltage_design: f64,
+    charge_current: f64,
+    cv_threshold: f64,
+    termination: f64,
+    internal_resistance: f64,
+    thermal_mass: f64,
+    thermal_resistance: f64,
+    capacity_fade: f64,
+    state_of_charge: f64,
+    cycles: f64,
+    ambient: f64,
+}
+
+impl Profile {
+    /// Creates default profile.
+    pub fn new() -> Profile {
+        Profile {
+            energy_full_design: 50.0 * 3600.0,
+            voltage_empty: 9.6,
+            voltage_full: 12.6,
+            voltage_design: 11.1,
+            charge_current: 3.0,
+            cv_threshold: 0.8,
+            termination: 0.05,
+            internal_resistance: 0.15,
+            thermal_mass: 250.0,
+            thermal_resistance: 8.0,
+            capacity_fade: 0.0002,
+            state_of_charge: 1.0,
+            cycles: 0.0,
+            ambient: 298.15,
+        }
+    }
+
+    /// Energy of the new battery.
+    pub fn energy_full_design(mut self, value: Energy) -> Profile {
+        self.energy_full_design = f64::from(value.get::<joule>());
+        self
+    }
+
+    /// Open-circuit voltages of the empty and fully charged battery;
+    /// voltage changes linearly with the state of charge between them.
+    pub fn voltage_range(mut self, empty: ElectricPotential, full: ElectricPotential) -> Profile {
+        self.voltage_empty = f64::from(empty.get::<volt>());
+        self.voltage_full = f64::from(full.get::<volt>());
+        self
+    }
+
+    /// Nominal voltage, reported as the `voltage_min_design` attribute.
+    pub fn voltage_design(mut self, value: ElectricPotential) -> Profile {
+        self.voltage_design = f64::from(value.get::<volt>());
+        self
+    }
+
+    /// Current of the constant current charging phase.
+    pub fn charge_current(mut self, value: ElectricCurrent) -> Profile {
+        self.charge_current = f64::from(value.get::<ampere>());
+        self
+    }
+
+    /// State of charge at which the constant voltage charging phase starts.
+    pub fn cv_threshold(mut self, value: Ratio) -> Profile {
+        self.cv_threshold = f64::from(value.get::<ratio>());
+        self
+    }
+
+    /// Charging stops when the current drops below this part of the [charge current](#method.charge_current).
+    pub fn termination(mut self, value: Ratio) -> Profile {
+        self.termination = f64::from(value.get::<ratio>());
+        self
+    }
+
+    /// Internal resistance in ohms, which converts part of the current into heat.
+    pub fn internal_resistance(mut self, ohms: f32) -> Profile {
+        self.internal_resistance = f64::from(ohms);
+        self
+    }
+
+    /// Heat capacity in `J/K` and thermal resistance to the ambient air in `K/W`.
+    pub fn thermal(mut self, mass: f32, resistance: f32) -> Profile {
+        self.thermal_mass = f64::from(mass);
+        self.thermal_resistance = f64::from(resistance);
+        self
+    }
+
+    /// Part of the design energy lost with each full charge cycle.
+    pub fn capacity_fade(mut self, value: Ratio) -> Profile {
+        self.capacity_fade = f64::from(value.get::<ratio>());
+        self
+    }
+
+    /// Initial state of charge.
+    pub fn state_of_charge(mut self, value: Ratio) -> Profile {
+        self.state_of_charge = f64::from(value.get::<ratio>()).max(0.0).min(1.0);
+        self
+    }
+
+    /// Charge cycles battery went through before the simulation start.
+    pub fn cycles(mut self, value: f32) -> Profile {
+        self.cycles = f64::from(value.max(0.0));
+        self
+    }
+
+    /// Initial ambient temperature.
+    pub fn ambient(mut self, value: ThermodynamicTemperature) -> Profile {
+        self.ambient = f64::from(value.get::<kelvin>());
+        self
+    }
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Change of the simulation conditions.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Event {
+    /// AC adapter is plugged in.
+    Plug,
+    /// AC adapter is unplugged.
+    Unplug,
+    /// System power consumption; battery is drained by it while AC adapter is unplugged.
+    Load(Power),
+    /// Ambient temperature.
+    Ambient(ThermodynamicTemperature),
+
+    // Awaiting for https://github.com/rust-lang/rust/issues/44109
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::Plug => write!(f, "plug"),
+            Event::Unplug => write!(f, "unplug"),
+            Event::Load(power) => write!(f, "load {} W", power.get::<watt>()),
+            Event::Ambient(temperature) => write!(f, "ambient {} C", temperature.get::<degree_celsius>()),
+            _ => write!(f, "unknown"),
+        }
+    }
+}
+
+/// Timeline of the [events](enum.Event.html), relative to the simulation start.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Scenario {
+    events: Vec<(Duration, Event)>,
+}
+
+impl Scenario {
+    /// Creates empty scenario: AC adapter is unplugged and there is no load.
+    pub fn new() -> Scenario {
+        Scenario::default()
+    }
+
+    /// Adds `event`, which happens at the `at` moment.
+    pub fn at(mut self, at: Duration, event: Event) -> Scenario {
+        let position = self.events.iter().take_while(|(time, _)| *time <= at).count();
+        self.events.insert(position, (at, event));
+        self
+    }
+
+    /// Parses scenario script.
+    ///
+    /// Each line contains time (with the `s`, `m` or `h` suffix), event name and value with its unit, if needed;
+    /// `load` is set in watts (`W`) and `ambient` temperature in degrees Celsius (`C`):
+    ///
+    /// ```text
+    /// 0s load 25 W
+    /// 10m plug
+    /// 1h30m unplug
+    /// 2h ambient 35 C
+    /// ```
+    ///
+    /// Empty lines and lines starting with `#` are ignored.
+    pub fn parse(script: &str) -> Result<Scenario> {
+        let mut scenario = Scenario::new();
+        for (idx, line) in script.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = || Error::invalid_data(format!("Malformed scenario line {}", idx + 1)).with_raw_value(line);
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            let at = words.first().and_then(|word| parse_duration(word)).ok_or_else(invalid)?;
+            // Value should be followed by the expected unit and nothing else
+            let value = |unit: &str| match words[2..] {
+                [value, word] if word == unit => value.parse::<f32>().map_err(|_| invalid()),
+                _ => Err(invalid()),
+            };
+            let event = match words.get(1) {
+                Some(&"plug") if words.len() == 2 => Event::Plug,
+                Some(&"unplug") if words.len() == 2 => Event::Unplug,
+                Some(&"load") => Event::Load(Power::new::<watt>(value("W")?)),
+                Some(&"ambient") => Event::Ambient(ThermodynamicTemperature::new::<degree_celsius>(value("C")?)),
+                _ => return Err(invalid()),
+            };
+            scenario = scenario.at(at, event);
+        }
+
+        Ok(scenario)
+    }
+
+    /// Events, ordered by time.
+    pub fn events(&self) -> &[(Duration, Event)] {
+        &self.events
+    }
+}
+
+/// Simulated battery.
+///
+/// See the [module documentation](index.html) for details.
+#[derive(Debug)]
+pub struct Simulation {
+    profile: Profile,
+    scenario: Scenario,
+    root: PathBuf,
+    elapsed: Duration,
+    next_event: usize,
+    plugged: bool,
+    load: f64,
+    ambient: f64,
+    // Battery state in SI units: J, A (positive while charging), K
+    energy: f64,
+    current: f64,
+    temperature: f64,
+    cycles: f64,
+    state: State,
+}
+
+impl Simulation {
+    /// Creates simulation and writes the initial battery state.
+    pub fn new(profile: Profile, scenario: Scenario) -> Result<Simulation> {
+        let mut simulation = Simulation {
+            root: archive::temp_dir()?,
+            elapsed: Duration::default(),
+            next_event: 0,
+            plugged: false,
+            load: 0.0,
+            ambient: profile.ambient,
+            energy: 0.0,
+            current: 0.0,
+            temperature: profile.ambient,
+            cycles: profile.cycles,
+            state: State::Discharging,
+            scenario,
+            profile,
+        };
+        simulation.energy = simulation.energy_full() * simulation.profile.state_of_charge;
+        simulation.apply_events();
+        simulation.write()?;
+
+        Ok(simulation)
+    }
+
+    /// Creates manager, which reads the simulated battery.
+    pub fn manager(&self) -> Result<Manager> {
+        Manager::with_sysfs_root(self.root.clone())
+    }
+
+    /// Advances the virtual clock by `duration`, applying the scenario events on the way.
+    pub fn advance(&mut self, duration: Duration) -> Result<()> {
+        let until = self.elapsed + duration;
+        while self.elapsed < until {
+            let step = (until - self.elapsed).min(STEP);
+            self.elapsed += step;
+            self.apply_events();
+            self.step(step.as_secs() as f64 + f64::from(step.subsec_nanos()) / 1e9);
+        }
+
+        self.write()
+    }
+
+    /// Time passed since the simulation start.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Applies `event` immediately, in addition to the scenario ones.
+    pub fn apply(&mut self, event: Event) -> Result<()> {
+        self.handle(event);
+        self.write()
+    }
+
+    /// Full battery energy, reduced by the capacity fade.
+    fn energy_full(&self) -> f64 {
+        let fade = (self.profile.capacity_fade * self.cycles).min(1.0);
+        self.profile.energy_full_design * (1.0 - fade)
+    }
+
+    fn state_of_charge(&self) -> f64 {
+        let energy_full = self.energy_full();
+        if energy_full > 0.0 {
+            (self.energy / energy_full).max(0.0).min(1.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Open-circuit voltage.
+    fn ocv(&self) -> f64 {
+        let profile = &self.profile;
+        profile.voltage_empty + (profile.voltage_full - profile.voltage_empty) * self.state_of_charge()
+    }
+
+    /// Terminal voltage: open-circuit voltage plus the internal resistance drop.
+    fn voltage(&self) -> f64 {
+        self.ocv() + self.current * self.profile.internal_resistance
+    }
+
+    fn apply_events(&mut self) {
+        while let Some(&(at, event)) = self.scenario.events.get(self.next_event) {
+            if at > self.elapsed {
+                break;
+            }
+            self.handle(event);
+            self.next_event += 1;
+        }
+    }
+
+    fn handle(&mut self, event: Event) {
+        match event {
+            Event::Plug => self.plugged = true,
+            Event::Unplug => self.plugged = false,
+            Event::Load(power) => self.load = f64::from(power.get::<watt>()).max(0.0),
+            Event::Ambient(temperature) => self.ambient = f64::from(temperature.get::<kelvin>()),
+            _ => {}
+        }
+    }
+
+    fn step(&mut self, seconds: f64) {
+        let profile = self.profile.clone();
+        let soc = self.state_of_charge();
+
+        self.current = if self.plugged {
+            // Adapter powers the system, battery gets the charger current
+            let current = if soc < profile.cv_threshold {
+                profile.charge_current
+            } else {
+                // Current decays while the cell voltage is held at the maximum one
+                profile.charge_current * (1.0 - soc) / (1.0 - profile.cv_threshold).max(f64::EPSILON)
+            };
+            if current < profile.charge_current * profile.termination || self.state == State::Full {
+                0.0
+            } else {
+                current
+            }
+        } else if self.energy > 0.0 {
+            // Current, at which the terminal voltage multiplied by it equals to the load:
+            // `I × (ocv - I × R) = load`; load above the maximum transferable power is capped
+            let ocv = self.ocv();
+            let resistance = profile.internal_resistance;
+            if resistance > 0.0 {
+                let discriminant = (ocv * ocv - 4.0 * resistance * self.load).max(0.0);
+                -(ocv - discriminant.sqrt()) / (2.0 * resistance)
+            } else {
+                -self.load / ocv
+            }
+        } else {
+            0.0
+        };
+
+        // Stored energy changes by the open-circuit voltage, resistance losses are turned into heat
+        let power = self.current * self.ocv();
+        let energy_full = self.energy_full();
+        let delta = (power * seconds).max(-self.energy).min(energy_full - self.energy);
+        self.energy += delta;
+        // One cycle is the full energy amount discharged
+        if delta < 0.0 && energy_full > 0.0 {
+            self.cycles += -delta / energy_full;
+        }
+        self.energy = self.energy.min(self.energy_full());
+
+        let losses = self.current * self.current * profile.internal_resistance;
+        let cooling = (self.temperature - self.ambient) / profile.thermal_resistance;
+        self.temperature += (losses - cooling) * seconds / profile.thermal_mass;
+
+        self.state = if self.plugged && (self.current == 0.0 || self.energy >= self.energy_full()) {
+            self.current = 0.0;
+            State::Full
+        } else if self.plugged {
+            State::Charging
+        } else if self.energy <= 0.0 {
+            State::Empty
+        } else {
+            State::Discharging
+        };
+    }
+
+    /// Writes the battery and AC adapter state in the units used by the kernel.
+    fn write(&mut self) -> Result<()> {
+        // `µWh` from joules, `µW`, `µV` and tenths of degree Celsius
+        let micro_wh = |joules: f64| format!("{}", (joules / 3600.0 * 1_000_000.0).round());
+        let micro = |value: f64| format!("{}", (value * 1_000_000.0).round());
+        let status = match self.state {
+            State::Charging => "Charging",
+            State::Discharging => "Discharging",
+            State::Empty => "Empty",
+            State::Full => "Full",
+            _ => "Unknown",
+        };
+        let attributes = [
+            ("type", "Battery".to_string()),
+            ("manufacturer", "rust-battery".to_string()),
+            ("model_name", "Simulated".to_string()),
+            ("serial_number", "0001".to_string()),
+            ("technology", "Li-ion".to_string()),
+            ("status", status.to_string()),
+            ("energy_now", micro_wh(self.energy)),
+            ("energy_full", micro_wh(self.energy_full())),
+            ("energy_full_design", micro_wh(self.profile.energy_full_design)),
+            ("power_now", micro((self.current * self.voltage()).abs())),
+            ("voltage_now", micro(self.voltage())),
+            ("voltage_min_design", micro(self.profile.voltage_design)),
+            ("temp", format!("{}", ((self.temperature - 273.15) * 10.0).round())),
+            ("cycle_count", format!("{}", self.cycles.floor())),
+        ];
+
+        let mut frame = Frame::new(self.elapsed);
+        for (attribute, value) in attributes.iter() {
+            frame.set("BAT0", *attribute, value.as_str())?;
+        }
+        frame.set("AC", "type", "Mains")?;
+        frame.set("AC", "online", if self.plugged { "1" } else { "0" })?;
+
+        frame.write_into(&self.root)
+    }
+}
+
+impl Drop for Simulation {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+/// Parses `1h30m`, `10m` or `45s`-like durations, plain numbers are seconds.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let mut seconds = 0.0;
+    let mut number = String::new();
+    for chr in value.chars() {
+        let multiplier = match chr {
+            'h' => 3600.0,
+            'm' => 60.0,
+            's' => 1.0,
+            _ => {
+                number.push(chr);
+                continue;
+            }
+        };
+        seconds += number.parse::<f64>().ok()? * multiplier;
+        number.clear();
+    }
+    if !number.is_empty() {
+        seconds += number.parse::<f64>().ok()?;
+    }
+
+    if seconds.is_finite() && seconds >= 0.0 && seconds < std::u64::MAX as f64 {
+        Some(Duration::new(seconds.trunc() as u64, (seconds.fract() * 1e9) as u32))
+    } else {
+        None
+    }
+}
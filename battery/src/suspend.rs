@@ -0,0 +1,388 @@
+//! Battery drain during the system suspend.
+//!
+//! Monotonic clock is not advancing while the system is suspended, so when the wall-clock time passed
+//! between two consecutive refreshes exceeds the monotonic time by more than a threshold
+//! (one minute by default), the system was sleeping in between. [SuspendTracker] records the energy
+//! and state of charge lost across each such gap per [BatteryId](../struct.BatteryId.html)
+//! and reports the average drain rate in sleep.
+//!
+//! Recorded suspends are appended to the `suspend` file of the [battery directory](../history/index.html#storage).
+//!
+//! This module requires the `history` feature.
+//!
+//! Gaps during which the battery was charging or full on either side are skipped,
+//! as the system was most probably connected to the charger.
+//!
+//! # Example
+//!
+//! ```edition2018,no_run
+//! # use std::thread;
+//! # use std::time::Duration;
+//! # use battery::Result;
+//! use battery::suspend::SuspendTracker;
+//!
+//! # fn main() -> Result<()> {
+//! let mut tracker = SuspendTracker::open("/var/lib/battery/suspend")?;
+//! let manager = battery::Manager::new()?;
+//! let mut battery = manager.batteries()?.next().unwrap()?;
+//! loop {
+//!     manager.refresh(&mut battery)?;
+//!     if tracker.update(&battery)?.is_some() {
+//!         println!("{:?}", tracker.report(&battery.id())?);
+//!     }
+//!     thread::sleep(Duration::from_secs(60));
+//! }
+//! # }
+//! ```
+//!
+//! [SuspendTracker]: struct.SuspendTracker.html
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::history::store::{self, Store};
+use crate::history::{seconds, unix_seconds, Sample};
+use crate::units::energy::joule;
+use crate::units::power::watt;
+use crate::units::ratio::ratio;
+use crate::units::{Energy, Power, Ratio};
+use crate::{Battery, BatteryId, Result, State};
+
+#[allow(clippy::redundant_static_lifetimes)]
+static HEADER: &'static str = "# battery suspend v1";
+#[allow(clippy::redundant_static_lifetimes)]
+static FILE: &'static str = "suspend";
+
+const HOUR: f32 = 60.0 * 60.0;
+
+/// Energy and state of charge lost while the system was suspended.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suspend {
+    start: SystemTime,
+    end: SystemTime,
+    suspended: Duration,
+    energy: Option<Energy>,
+    state_of_charge: Option<Ratio>,
+}
+
+impl Suspend {
+    /// Time of the last refresh before the suspend.
+    pub fn start(&self) -> SystemTime {
+        self.start
+    }
+
+    /// Time of the first refresh after the resume.
+    pub fn end(&self) -> SystemTime {
+        self.end
+    }
+
+    /// Time spent in suspend, which is the wall-clock gap minus the monotonic one.
+    pub fn suspended(&self) -> Duration {
+        self.suspended
+    }
+
+    /// Energy lost between the refreshes.
+    pub fn energy(&self) -> Option<Energy> {
+        self.energy
+    }
+
+    /// State of charge lost between the refreshes.
+    pub fn state_of_charge(&self) -> Option<Ratio> {
+        self.state_of_charge
+    }
+
+    fn to_line(&self) -> String {
+        let optional = |value: Option<f32>| value.map_or_else(|| "-".to_string(), |value| value.to_string());
+        format!(
+            "{} {} {} {} {}",
+            unix_seconds(self.start),
+            unix_seconds(self.end),
+            self.suspended.as_secs(),
+            optional(self.energy.map(|value| value.get::<joule>())),
+            optional(self.state_of_charge.map(|value| value.get::<ratio>())),
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Suspend> {
+        let mut parts = line.split_whitespace();
+        let start = parts.next()?.parse::<u64>().ok()?;
+        let end = parts.next()?.parse::<u64>().ok()?;
+        let suspended = parts.next()?.parse::<u64>().ok()?;
+        let energy = match parts.next()? {
+            "-" => None,
+            value => Some(value.parse::<f32>().ok()?),
+        };
+        let state_of_charge = match parts.next()? {
+            "-" => None,
+            value => Some(value.parse::<f32>().ok()?),
+        };
+        if parts.next().is_some() {
+            return None;
+        }
+
+        Some(Suspend {
+            start: UNIX_EPOCH + Duration::from_secs(start),
+            end: UNIX_EPOCH + Duration::from_secs(end),
+            suspended: Duration::from_secs(suspended),
+            energy: energy.map(Energy::new::<joule>),
+            state_of_charge: state_of_charge.map(Ratio::new::<ratio>),
+        })
+    }
+}
+
+/// Average battery drain in suspend.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SuspendReport {
+    suspends: usize,
+    suspended: Duration,
+    drain_rate: Option<Power>,
+    state_of_charge_per_hour: Option<Ratio>,
+}
+
+impl SuspendReport {
+    /// Number of suspends the report is based on.
+    pub fn suspends(&self) -> usize {
+        self.suspends
+    }
+
+    /// Total time spent in suspend.
+    pub fn suspended(&self) -> Duration {
+        self.suspended
+    }
+
+    /// Average power drawn from the battery in suspend,
+    /// `None` if the energy was not reported.
+    pub fn drain_rate(&self) -> Option<Power> {
+        self.drain_rate
+    }
+
+    /// Average state of charge lost per hour of suspend,
+    /// `None` if the state of charge was not reported.
+    pub fn state_of_charge_per_hour(&self) -> Option<Ratio> {
+        self.state_of_charge_per_hour
+    }
+}
+
+/// Averages the drain over the `suspends`, weighted by their duration.
+///
+/// Returns `None` if there are no suspends.
+pub fn summarize(suspends: &[Suspend]) -> Option<SuspendReport> {
+    if suspends.is_empty() {
+        return None;
+    }
+
+    let rate = |values: Vec<(Duration, f32)>| {
+        let seconds = values.iter().map(|(duration, _)| seconds(*duration) as f32).sum::<f32>();
+        if values.is_empty() || seconds <= 0.0 {
+            None
+        } else {
+            Some(values.iter().map(|(_, value)| value).sum::<f32>() / seconds)
+        }
+    };
+    let energy = suspends
+        .iter()
+        .filter_map(|suspend| suspend.energy.map(|value| (suspend.suspended, value.get::<joule>())))
+        .collect();
+    let state_of_charge = suspends
+        .iter()
+        .filter_map(|suspend| suspend.state_of_charge.map(|value| (suspend.suspended, value.get::<ratio>())))
+        .collect();
+
+    Some(SuspendReport {
+        suspends: suspends.len(),
+        suspended: suspends.iter().map(|suspend| suspend.suspended).sum(),
+        drain_rate: rate(energy).map(Power::new::<watt>),
+        state_of_charge_per_hour: rate(state_of_charge).map(|value| Ratio::new::<ratio>(value * HOUR)),
+    })
+}
+
+/// Persistent suspend drain tracker.
+///
+/// Detection relies on the monotonic clock being stopped during the suspend, which holds for `Instant`
+/// on Linux and macOS. On Windows `Instant` keeps counting in sleep, so [update](#method.update)
+/// never records a suspend there; [push](#method.push) should be given the readings of a clock
+/// which excludes the sleep time instead, for example, `QueryUnbiasedInterruptTime`.
+///
+/// See the [module documentation](index.html) for details.
+#[derive(Debug, Clone)]
+pub struct SuspendTracker {
+    store: Store,
+    threshold: Duration,
+    started: Instant,
+    last: HashMap<BatteryId, (Sample, Duration)>,
+}
+
+impl SuspendTracker {
+    /// Opens tracker, which records suspends under the `dir` root directory.
+    pub fn open<T: Into<PathBuf>>(dir: T) -> Result<SuspendTracker> {
+        Ok(SuspendTracker {
+            store: Store::open(dir.into())?,
+            threshold: Duration::from_secs(60),
+            started: Instant::now(),
+            last: HashMap::new(),
+        })
+    }
+
+    /// Sets how much the wall-clock gap should exceed the monotonic one to be considered a suspend.
+    pub fn with_threshold(mut self, threshold: Duration) -> SuspendTracker {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        self.store.root()
+    }
+
+    /// Accounts the current `battery` readings, returning the suspend recorded since the previous update.
+    pub fn update(&mut self, battery: &Battery) -> Result<Option<Suspend>> {
+        let sample = Sample::from_battery(battery, SystemTime::now());
+        let monotonic = Instant::now().duration_since(self.started);
+        self.push(&battery.id(), sample, monotonic)
+    }
+
+    /// Accounts the battery `id` sample taken when the `monotonic` clock read the given time,
+    /// returning the suspend recorded since the previous one.
+    ///
+    /// `monotonic` clock should not advance while the system is suspended;
+    /// samples with the clock going backwards are not considered to be suspends.
+    pub fn push(&mut self, id: &BatteryId, sample: Sample, monotonic: Duration) -> Result<Option<Suspend>> {
+        let (last, last_monotonic) = match self.last.insert(id.clone(), (sample.clone(), monotonic)) {
+            Some(last) => last,
+            None => return Ok(None),
+        };
+        let wall = match sample.time().duration_since(last.time()) {
+            Ok(wall) => wall,
+            // Wall clock was set back
+            Err(_) => return Ok(None),
+        };
+        let elapsed = match monotonic.checked_sub(last_monotonic) {
+            Some(elapsed) => elapsed,
+            None => return Ok(None),
+        };
+        let suspended = match wall.checked_sub(elapsed) {
+            Some(suspended) if suspended > self.threshold => suspended,
+            _ => return Ok(None),
+        };
+        let is_charging = |state: State| state == State::Charging || state == State::Full;
+        if is_charging(last.state()) || is_charging(sample.state()) {
+            return Ok(None);
+        }
+
+        let suspend = Suspend {
+            start: last.time(),
+            end: sample.time(),
+            suspended,
+            energy: last.energy().and_then(|last| sample.energy().map(|current| last - current)),
+            state_of_charge: last
+                .state_of_charge()
+                .and_then(|last| sample.state_of_charge().map(|current| last - current)),
+        };
+        self.record(id, &suspend)?;
+
+        Ok(Some(suspend))
+    }
+
+    /// Loads the battery `id` suspends, ordered by time.
+    pub fn suspends(&self, id: &BatteryId) -> Result<Vec<Suspend>> {
+        let mut suspends = match self.store.find_file(id, FILE)? {
+            Some(path) => store::read_records(&path, "suspend", Suspend::from_line)?,
+            None => Vec::new(),
+        };
+        suspends.sort_by_key(Suspend::start);
+
+        Ok(suspends)
+    }
+
+    /// Averages the battery `id` drain in suspend, `None` if there were no suspends recorded.
+    pub fn report(&self, id: &BatteryId) -> Result<Option<SuspendReport>> {
+        Ok(summarize(&self.suspends(id)?))
+    }
+
+    fn record(&self, id: &BatteryId, suspend: &Suspend) -> Result<()> {
+        store::append_record(&self.store.file(id, FILE)?, HEADER, &suspend.to_line())?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use approx::assert_abs_diff_eq;
+
+    use super::{summarize, SuspendTracker};
+    use crate::history::tests::{id, sample};
+    use crate::history::Sample;
+    use crate::units::energy::watt_hour;
+    use crate::units::power::watt;
+    use crate::units::ratio::percent;
+    use crate::{BatteryId, State};
+
+    #[test]
+    fn test_push() {
+        let root = tempfile::tempdir().unwrap();
+        let mut tracker = SuspendTracker::open(root.path()).unwrap();
+        let id = id();
+        let at = Duration::from_secs;
+        const HOUR: u64 = 60 * 60;
+
+        assert_eq!(tracker.push(&id, sample(0, State::Discharging, 90.0), at(0)).unwrap(), None);
+        assert_eq!(tracker.push(&id, sample(60, State::Discharging, 89.0), at(60)).unwrap(), None);
+        // Eight hours of sleep, the monotonic clock was stopped
+        let suspend = tracker
+            .push(&id, sample(60 + 8 * HOUR + 30, State::Discharging, 81.0), at(90))
+            .unwrap()
+            .unwrap();
+        assert_eq!(suspend.suspended(), Duration::from_secs(8 * HOUR));
+        assert_abs_diff_eq!(suspend.energy().unwrap().get::<watt_hour>(), 4.0, epsilon = 0.001);
+        assert_abs_diff_eq!(suspend.state_of_charge().unwrap().get::<percent>(), 8.0, epsilon = 0.001);
+
+        // Sleeping on the charger is not counted
+        assert_eq!(tracker.push(&id, sample(20 * HOUR, State::Charging, 95.0), at(120)).unwrap(), None);
+        assert_eq!(tracker.push(&id, sample(20 * HOUR + 60, State::Discharging, 95.0), at(180)).unwrap(), None);
+        // Short gap is below the threshold
+        assert_eq!(tracker.push(&id, sample(20 * HOUR + 150, State::Discharging, 95.0), at(210)).unwrap(), None);
+        assert!(tracker.push(&id, sample(24 * HOUR + 150, State::Unknown, 93.0), at(240)).unwrap().is_some());
+        // Monotonic clock going backwards is ignored
+        assert_eq!(tracker.push(&id, sample(30 * HOUR, State::Discharging, 90.0), at(60)).unwrap(), None);
+
+        let tracker = SuspendTracker::open(root.path()).unwrap();
+        assert_eq!(tracker.suspends(&id).unwrap().len(), 2);
+        let report = tracker.report(&id).unwrap().unwrap();
+        assert_eq!(report.suspends(), 2);
+        assert!(report.suspended() > Duration::from_secs(12 * HOUR - 60));
+        // 5 Wh and 10% in twelve hours
+        assert_abs_diff_eq!(report.drain_rate().unwrap().get::<watt>(), 5.0 / 12.0, epsilon = 0.001);
+        assert_abs_diff_eq!(report.state_of_charge_per_hour().unwrap().get::<percent>(), 10.0 / 12.0, epsilon = 0.001);
+
+        let other = BatteryId::new(None, Some("Other"), None);
+        assert_eq!(tracker.report(&other).unwrap(), None);
+
+        fs::write(root.path().join(id.to_string()).join("suspend"), "1 2 -\n").unwrap();
+        assert!(tracker.suspends(&id).is_err());
+    }
+
+    #[test]
+    fn test_summarize() {
+        assert_eq!(summarize(&[]), None);
+
+        let root = tempfile::tempdir().unwrap();
+        let mut tracker = SuspendTracker::open(root.path()).unwrap().with_threshold(Duration::from_secs(600));
+        let id = BatteryId::new(None, Some("Other"), None);
+        let samples = [
+            Sample::new(UNIX_EPOCH, State::Discharging),
+            Sample::new(UNIX_EPOCH + Duration::from_secs(1200), State::Discharging),
+        ];
+        tracker.push(&id, samples[0].clone(), Duration::from_secs(0)).unwrap();
+        let suspend = tracker.push(&id, samples[1].clone(), Duration::from_secs(60)).unwrap().unwrap();
+        assert_eq!(suspend.energy(), None);
+
+        let report = summarize(&[suspend]).unwrap();
+        assert_eq!(report.suspended(), Duration::from_secs(1140));
+        assert_eq!(report.drain_rate(), None);
+        assert_eq!(report.state_of_charge_per_hour(), None);
+    }
+}
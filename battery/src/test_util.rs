@@ -0,0 +1,311 @@
+//! Fake `/sys/class/power_supply` trees for the integration tests.
+//!
+//! [FakeSysfs] writes devices described by the [FakePowerSupply] builders into a temporary directory
+//! and creates [Manager](../struct.Manager.html) instances reading from it.
+//! Devices can be changed, added and removed between the `Manager` calls
+//! in order to simulate charging, broken drivers or hotplug.
+//!
+//! This module requires the `test-util` feature and is available on Linux only.
+//!
+//! # Example
+//!
+//! ```edition2018
+//! # use battery::Result;
+//! use battery::test_util::{FakePowerSupply, FakeSysfs};
+//! use battery::units::electric_potential::volt;
+//! use battery::units::energy::watt_hour;
+//! use battery::units::{ElectricPotential, Energy};
+//! use battery::State;
+//!
+//! # fn main() -> Result<()> {
+//! let mut sysfs = FakeSysfs::new()?;
+//! sysfs.add(
+//!     FakePowerSupply::battery("BAT0")
+//!         .status(State::Discharging)
+//!         .energy_now(Energy::new::<watt_hour>(25.0))
+//!         .energy_full(Energy::new::<watt_hour>(50.0))
+//!         .voltage_now(ElectricPotential::new::<volt>(12.0)),
+//! )?;
+//!
+//! let manager = sysfs.manager()?;
+//! let mut battery = manager.batteries()?.next().unwrap()?;
+//! assert_eq!(battery.state(), State::Discharging);
+//!
+//! sysfs.update("BAT0", |battery| battery.status(State::Charging))?;
+//! manager.refresh(&mut battery)?;
+//! assert_eq!(battery.state(), State::Charging);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [FakeSysfs]: struct.FakeSysfs.html
+//! [FakePowerSupply]: struct.FakePowerSupply.html
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::archive::{self, Attributes, Frame};
+use crate::units::electric_charge::ampere_hour;
+use crate::units::electric_current::ampere;
+use crate::units::electric_potential::volt;
+use crate::units::energy::watt_hour;
+use crate::units::power::watt;
+use crate::units::ratio::percent;
+use crate::units::thermodynamic_temperature::degree_celsius;
+use crate::units::{ElectricCharge, ElectricCurrent, ElectricPotential, Energy, Power, Ratio, ThermodynamicTemperature};
+use crate::{Error, Manager, Result, State, Technology};
+
+/// Builder for the `/sys/class/power_supply/<name>/` directory contents.
+///
+/// Typed methods are converting values into the units used by the kernel
+/// (`µWh`, `µAh`, `µW`, `µA`, `µV` and tenths of degree Celsius);
+/// arbitrary values (including invalid ones) can be set with the [attribute](#method.attribute) method.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FakePowerSupply {
+    name: String,
+    attributes: Attributes,
+}
+
+impl FakePowerSupply {
+    /// Creates system battery device without any values.
+    pub fn battery<T: Into<String>>(name: T) -> FakePowerSupply {
+        FakePowerSupply {
+            name: name.into(),
+            attributes: Attributes::new(),
+        }
+        .attribute("type", "Battery")
+    }
+
+    /// Creates AC adapter device.
+    pub fn mains<T: Into<String>>(name: T) -> FakePowerSupply {
+        FakePowerSupply {
+            name: name.into(),
+            attributes: Attributes::new(),
+        }
+        .attribute("type", "Mains")
+    }
+
+    /// Device name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Device attributes in the sysfs format.
+    pub fn attributes(&self) -> &Attributes {
+        &self.attributes
+    }
+
+    /// Sets raw attribute value.
+    pub fn attribute<N: Into<String>, V: Into<String>>(mut self, name: N, value: V) -> FakePowerSupply {
+        self.attributes.insert(name.into(), value.into());
+        self
+    }
+
+    /// Removes attribute, as if device driver does not provide it.
+    pub fn without(mut self, name: &str) -> FakePowerSupply {
+        self.attributes.remove(name);
+        self
+    }
+
+    /// Sets the `status` attribute, for example, `Discharging`.
+    pub fn status(self, state: State) -> FakePowerSupply {
+        let value = match state {
+            State::Charging => "Charging",
+            State::Discharging => "Discharging",
+            State::Empty => "Empty",
+            State::Full => "Full",
+            _ => "Unknown",
+        };
+        self.attribute("status", value)
+    }
+
+    /// Sets the `technology` attribute, for example, `Li-ion`.
+    pub fn technology(self, technology: Technology) -> FakePowerSupply {
+        let value = match technology {
+            Technology::LithiumIon => "Li-ion",
+            Technology::LeadAcid => "Pb",
+            Technology::LithiumPolymer => "Li-poly",
+            Technology::NickelMetalHydride => "NiMH",
+            Technology::NickelCadmium => "NiCd",
+            Technology::NickelZinc => "NiZn",
+            Technology::LithiumIronPhosphate => "LiFe",
+            Technology::RechargeableAlkalineManganese => "RAM",
+            _ => "Unknown",
+        };
+        self.attribute("technology", value)
+    }
+
+    /// Sets the `scope` attribute to `Device`, which is used for the peripheral devices batteries.
+    pub fn peripheral(self) -> FakePowerSupply {
+        self.attribute("scope", "Device")
+    }
+
+    /// Sets the AC adapter `online` attribute.
+    pub fn online(self, online: bool) -> FakePowerSupply {
+        self.attribute("online", if online { "1" } else { "0" })
+    }
+
+    /// Sets the `manufacturer` attribute as is.
+    pub fn manufacturer<T: Into<String>>(self, value: T) -> FakePowerSupply {
+        self.attribute("manufacturer", value)
+    }
+
+    /// Sets the `model_name` attribute as is.
+    pub fn model_name<T: Into<String>>(self, value: T) -> FakePowerSupply {
+        self.attribute("model_name", value)
+    }
+
+    /// Sets the `serial_number` attribute as is.
+    pub fn serial_number<T: Into<String>>(self, value: T) -> FakePowerSupply {
+        self.attribute("serial_number", value)
+    }
+
+    /// Sets the `energy_now` attribute, in `µWh`.
+    pub fn energy_now(self, value: Energy) -> FakePowerSupply {
+        self.micro("energy_now", value.get::<watt_hour>())
+    }
+
+    /// Sets the `energy_full` attribute, in `µWh`.
+    pub fn energy_full(self, value: Energy) -> FakePowerSupply {
+        self.micro("energy_full", value.get::<watt_hour>())
+    }
+
+    /// Sets the `energy_full_design` attribute, in `µWh`.
+    pub fn energy_full_design(self, value: Energy) -> FakePowerSupply {
+        self.micro("energy_full_design", value.get::<watt_hour>())
+    }
+
+    /// Sets the `charge_now` attribute, in `µAh`.
+    pub fn charge_now(self, value: ElectricCharge) -> FakePowerSupply {
+        self.micro("charge_now", value.get::<ampere_hour>())
+    }
+
+    /// Sets the `charge_full` attribute, in `µAh`.
+    pub fn charge_full(self, value: ElectricCharge) -> FakePowerSupply {
+        self.micro("charge_full", value.get::<ampere_hour>())
+    }
+
+    /// Sets the `charge_full_design` attribute, in `µAh`.
+    pub fn charge_full_design(self, value: ElectricCharge) -> FakePowerSupply {
+        self.micro("charge_full_design", value.get::<ampere_hour>())
+    }
+
+    /// Sets the `power_now` attribute, in `µW`.
+    pub fn power_now(self, value: Power) -> FakePowerSupply {
+        self.micro("power_now", value.get::<watt>())
+    }
+
+    /// Sets the `current_now` attribute, in `µA`; sign is kept as is.
+    pub fn current_now(self, value: ElectricCurrent) -> FakePowerSupply {
+        self.micro("current_now", value.get::<ampere>())
+    }
+
+    /// Sets the `voltage_now` attribute, in `µV`.
+    pub fn voltage_now(self, value: ElectricPotential) -> FakePowerSupply {
+        self.micro("voltage_now", value.get::<volt>())
+    }
+
+    /// Sets the `voltage_min_design` attribute, in `µV`.
+    pub fn voltage_min_design(self, value: ElectricPotential) -> FakePowerSupply {
+        self.micro("voltage_min_design", value.get::<volt>())
+    }
+
+    /// Sets the `capacity` attribute, in whole percents.
+    pub fn capacity(self, value: Ratio) -> FakePowerSupply {
+        let value = value.get::<percent>().round();
+        self.attribute("capacity", format!("{}", value))
+    }
+
+    /// Sets the `temp` attribute, in tenths of degree Celsius.
+    pub fn temp(self, value: ThermodynamicTemperature) -> FakePowerSupply {
+        let value = (value.get::<degree_celsius>() * 10.0).round();
+        self.attribute("temp", format!("{}", value))
+    }
+
+    /// Sets the `cycle_count` attribute.
+    pub fn cycle_count(self, value: u32) -> FakePowerSupply {
+        self.attribute("cycle_count", value.to_string())
+    }
+
+    fn micro(self, name: &str, value: f32) -> FakePowerSupply {
+        let value = (f64::from(value) * 1_000_000.0).round();
+        self.attribute(name, format!("{}", value))
+    }
+}
+
+/// Temporary `/sys/class/power_supply` directory.
+///
+/// Directory is removed when `FakeSysfs` is dropped.
+#[derive(Debug)]
+pub struct FakeSysfs {
+    root: PathBuf,
+    devices: BTreeMap<String, FakePowerSupply>,
+}
+
+impl FakeSysfs {
+    /// Creates empty directory.
+    pub fn new() -> Result<FakeSysfs> {
+        Ok(FakeSysfs {
+            root: archive::temp_dir()?,
+            devices: BTreeMap::new(),
+        })
+    }
+
+    /// Directory path.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Creates manager, which reads devices from this directory.
+    pub fn manager(&self) -> Result<Manager> {
+        Manager::with_sysfs_root(self.root.clone())
+    }
+
+    /// Adds device, replacing the existing one with the same name.
+    pub fn add(&mut self, device: FakePowerSupply) -> Result<()> {
+        self.devices.insert(device.name.clone(), device);
+        self.write()
+    }
+
+    /// Changes the `name` device.
+    pub fn update<F>(&mut self, name: &str, f: F) -> Result<()>
+    where
+        F: FnOnce(FakePowerSupply) -> FakePowerSupply,
+    {
+        let device = self.devices.remove(name).ok_or_else(|| Self::missing(name))?;
+        let device = f(device);
+        self.devices.insert(device.name.clone(), device);
+        self.write()
+    }
+
+    /// Removes the `name` device, as if it was unplugged.
+    pub fn remove(&mut self, name: &str) -> Result<FakePowerSupply> {
+        let device = self.devices.remove(name).ok_or_else(|| Self::missing(name))?;
+        self.write()?;
+
+        Ok(device)
+    }
+
+    fn write(&self) -> Result<()> {
+        let mut frame = Frame::new(Duration::default());
+        for (name, device) in &self.devices {
+            for (attribute, value) in &device.attributes {
+                frame.set(name.as_str(), attribute.as_str(), value.as_str())?;
+            }
+        }
+
+        frame.write_into(&self.root)
+    }
+
+    fn missing(name: &str) -> Error {
+        Error::not_found("Fake power supply does not exist").with_device(name)
+    }
+}
+
+impl Drop for FakeSysfs {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
@@ -0,0 +1,142 @@
+use std::fmt;
+
+use crate::diagnostics::{Adjustment, Diagnostics, Quantity, Trace};
+use crate::units::energy::watt_hour;
+use crate::units::power::watt;
+use crate::units::ratio::percent;
+use crate::units::{Energy, Power, Ratio};
+use crate::{Readings, State};
+
+// Gauges are reporting full energy a little above the design one for new batteries,
+// but anything above that factor is a broken gauge
+const FULL_TO_DESIGN_LIMIT: f32 = 1.5;
+
+/// Suspicious battery reading, which usually means a broken fuel gauge or driver.
+///
+/// See the [Battery::anomalies](struct.Battery.html#method.anomalies) method.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Anomaly {
+    /// Current energy is greater than the full energy.
+    EnergyAboveFull { energy: Energy, energy_full: Energy },
+    /// Full energy is more than 1.5 times greater than the design energy.
+    EnergyFullAboveDesign { energy_full: Energy, energy_full_design: Energy },
+    /// Device reported the ACPI "unknown" (`0xFFFF`) energy rate value.
+    UnknownRateSentinel,
+    /// Device reported energy rate above the sane limit, which was discarded.
+    RateTooHigh { energy_rate: Power },
+    /// Battery is charging, but the current (or power) is reported as a negative value.
+    ChargingWithNegativeCurrent,
+    /// Device reported zero battery voltage.
+    ZeroVoltage,
+    /// Ratio value was outside of the `0..=100 %` range and was clamped.
+    RatioOutOfRange { quantity: Quantity, value: Ratio },
+
+    // Awaiting for https://github.com/rust-lang/rust/issues/44109
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+impl Anomaly {
+    /// Checks battery values for the suspicious readings.
+    pub(crate) fn detect(readings: &Readings, state: State, diagnostics: &Diagnostics) -> Vec<Anomaly> {
+        let mut anomalies = Vec::new();
+
+        if let (Some(energy), Some(energy_full)) = (readings.energy().value(), readings.energy_full().value()) {
+            if energy > energy_full {
+                anomalies.push(Anomaly::EnergyAboveFull {
+                    energy,
+                    energy_full,
+                });
+            }
+        }
+
+        let energy_full_design = readings.energy_full_design().value();
+        if let (Some(energy_full), Some(energy_full_design)) = (readings.energy_full().value(), energy_full_design) {
+            if energy_full_design.value > 0.0 && energy_full > energy_full_design * FULL_TO_DESIGN_LIMIT {
+                anomalies.push(Anomaly::EnergyFullAboveDesign {
+                    energy_full,
+                    energy_full_design,
+                });
+            }
+        }
+
+        for trace in diagnostics.traces() {
+            for adjustment in trace.adjustments() {
+                match adjustment {
+                    Adjustment::RateUnknownSentinel => anomalies.push(Anomaly::UnknownRateSentinel),
+                    Adjustment::RateTooHigh { raw } => anomalies.push(Anomaly::RateTooHigh {
+                        energy_rate: Power::new::<watt>(*raw),
+                    }),
+                    Adjustment::Clamped { raw } => anomalies.push(Anomaly::RatioOutOfRange {
+                        quantity: trace.quantity(),
+                        value: Ratio::new::<percent>(*raw * 100.0),
+                    }),
+                    _ => {}
+                }
+            }
+        }
+
+        if state == State::Charging {
+            let negative = diagnostics
+                .trace(Quantity::EnergyRate)
+                .map_or(false, |trace| Self::raw_values(trace).any(|value| value < 0.0));
+            if negative {
+                anomalies.push(Anomaly::ChargingWithNegativeCurrent);
+            }
+        }
+
+        let zero_voltage = match readings.voltage().value() {
+            Some(voltage) => voltage.value <= 0.0,
+            // Zero values are rejected while reading, so they are not making it to the readings
+            None => diagnostics
+                .trace(Quantity::Voltage)
+                .map_or(false, |trace| Self::raw_values(trace).any(|value| value == 0.0)),
+        };
+        if zero_voltage {
+            anomalies.push(Anomaly::ZeroVoltage);
+        }
+
+        anomalies
+    }
+
+    /// Numeric raw values of the trace, including the ignored ones.
+    fn raw_values(trace: &Trace) -> impl Iterator<Item = f32> + '_ {
+        let sources = trace.sources().iter().map(|source| source.raw());
+        let ignored = trace.adjustments().iter().filter_map(|adjustment| match adjustment {
+            Adjustment::Ignored { raw, .. } => Some(raw.as_str()),
+            _ => None,
+        });
+
+        sources.chain(ignored).filter_map(|raw| raw.trim().parse::<f32>().ok())
+    }
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Anomaly::EnergyAboveFull { energy, energy_full } => write!(
+                f,
+                "energy {:.2} Wh is above the full energy {:.2} Wh",
+                energy.get::<watt_hour>(),
+                energy_full.get::<watt_hour>()
+            ),
+            Anomaly::EnergyFullAboveDesign { energy_full, energy_full_design } => write!(
+                f,
+                "full energy {:.2} Wh is more than {} times above the design energy {:.2} Wh",
+                energy_full.get::<watt_hour>(),
+                FULL_TO_DESIGN_LIMIT,
+                energy_full_design.get::<watt_hour>()
+            ),
+            Anomaly::UnknownRateSentinel => write!(f, "energy rate is the ACPI unknown value"),
+            Anomaly::RateTooHigh { energy_rate } => {
+                write!(f, "energy rate {:.2} W is too high", energy_rate.get::<watt>())
+            }
+            Anomaly::ChargingWithNegativeCurrent => write!(f, "battery is charging with a negative current"),
+            Anomaly::ZeroVoltage => write!(f, "battery voltage is zero"),
+            Anomaly::RatioOutOfRange { quantity, value } => {
+                write!(f, "{} {:.1} % is out of range", quantity, value.get::<percent>())
+            }
+            _ => write!(f, "unknown anomaly"),
+        }
+    }
+}
@@ -4,7 +4,7 @@ use std::ops::{Deref, DerefMut};
 use crate::platform::traits::*;
 use crate::platform::Device;
 use crate::units::{ElectricPotential, Energy, Power, Ratio, ThermodynamicTemperature, Time};
-use crate::{State, Technology};
+use crate::{Anomaly, BatteryId, Diagnostics, Fields, Readings, State, Technology, Warning};
 
 /// Battery instant information representation.
 ///
@@ -122,6 +122,105 @@ impl Battery {
         self.0.serial_number()
     }
 
+    /// Device name used by the OS, for example, `BAT0` on Linux.
+    ///
+    /// Name is not stable and might change after the battery re-plug or reboot,
+    /// see [Battery::id](#method.id) for the stable identity.
+    /// Only Linux implementation provides it for now, `None` is returned on other platforms.
+    pub fn name(&self) -> Option<&str> {
+        self.0.name()
+    }
+
+    /// Battery values, which are telling apart the reported, derived and unavailable ones.
+    ///
+    /// Methods above are substituting values which device driver does not report
+    /// (for example, zero is returned by [Battery::energy_full_design](#method.energy_full_design)
+    /// and `100 %` by [Battery::state_of_health](#method.state_of_health) if battery
+    /// design capacity is unknown), which is convenient for displaying but misleading
+    /// for any kind of analysis. Returned [readings] are not doing that.
+    ///
+    /// Only Linux implementation tracks the values origin precisely,
+    /// on other platforms all values fetched from the OS are considered to be reported ones.
+    ///
+    /// # Example
+    ///
+    /// ```edition2018
+    /// # use battery::{Manager, Reading, Result};
+    /// # fn main() -> Result<()> {
+    /// for battery in Manager::new()?.batteries()? {
+    ///     match battery?.readings().state_of_health() {
+    ///         Reading::Reported(value) | Reading::Derived(value) => println!("Health: {:?}", value),
+    ///         Reading::Unavailable => println!("Health is unknown"),
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [readings]: struct.Readings.html
+    pub fn readings(&self) -> Readings {
+        self.0.readings()
+    }
+
+    /// Battery identity.
+    ///
+    /// Unlike the `Battery` itself, identity stays the same
+    /// when the battery is re-plugged or system is rebooted,
+    /// so it can be used to match batteries between the program runs.
+    pub fn id(&self) -> BatteryId {
+        BatteryId::new(self.vendor(), self.model(), self.serial_number())
+    }
+
+    /// Problems happened while loading battery values.
+    ///
+    /// Always empty for batteries fetched with the [Manager::batteries](struct.Manager.html#method.batteries) method,
+    /// see [Manager::batteries_lenient](struct.Manager.html#method.batteries_lenient) for details.
+    pub fn warnings(&self) -> &[Warning] {
+        self.0.warnings()
+    }
+
+    /// Values which failed to load and are holding placeholder or stale data.
+    ///
+    /// Always empty for batteries fetched with the [Manager::batteries](struct.Manager.html#method.batteries) method,
+    /// see [Manager::batteries_lenient](struct.Manager.html#method.batteries_lenient) for details.
+    pub fn missing_fields(&self) -> Fields {
+        self.0.missing_fields()
+    }
+
+    /// Report about where each battery value came from.
+    ///
+    /// For each value it lists device attributes it was calculated from (along with their raw values),
+    /// the conversion applied to them and the sanity checks which were triggered.
+    /// Report is useful for debugging odd values and can be pasted into bug reports as-is:
+    ///
+    /// ```rust,no_run
+    /// # fn main() -> battery::Result<()> {
+    /// let manager = battery::Manager::new()?;
+    /// for battery in manager.batteries()? {
+    ///     println!("{}", battery?.diagnostics());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Only Linux is tracking the values origin for now, report is empty for other platforms.
+    pub fn diagnostics(&self) -> Diagnostics {
+        self.0.diagnostics()
+    }
+
+    /// Checks battery values for the suspicious readings.
+    ///
+    /// Many fuel gauges and drivers are reporting nonsense from time to time
+    /// (current energy above the full one, negative current while charging and so on),
+    /// which this crate usually hides by clamping or discarding such values.
+    /// Returned [anomalies](enum.Anomaly.html) are allowing to detect broken gauges instead.
+    ///
+    /// Some checks are relying on the [diagnostics](#method.diagnostics) report,
+    /// so they are available on Linux only.
+    pub fn anomalies(&self) -> Vec<Anomaly> {
+        Anomaly::detect(&self.readings(), self.state(), &self.diagnostics())
+    }
+
     /// Remaining time till full battery.
     ///
     /// This is an instant value and may different vastly from call to call.
@@ -0,0 +1,274 @@
+//! Origins of the battery values.
+//!
+//! See the [Battery::diagnostics](../struct.Battery.html#method.diagnostics) method.
+
+use std::fmt;
+
+/// Battery value, which origin is tracked by [Diagnostics].
+///
+/// [Diagnostics]: struct.Diagnostics.html
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+pub enum Quantity {
+    State,
+    StateOfCharge,
+    StateOfHealth,
+    Energy,
+    EnergyFull,
+    EnergyFullDesign,
+    EnergyRate,
+    Voltage,
+    /// Intermediate value, used to convert charge values into energy ones.
+    DesignVoltage,
+    Temperature,
+    CycleCount,
+
+    // Awaiting for https://github.com/rust-lang/rust/issues/44109
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let display = match self {
+            Quantity::State => "state",
+            Quantity::StateOfCharge => "state_of_charge",
+            Quantity::StateOfHealth => "state_of_health",
+            Quantity::Energy => "energy",
+            Quantity::EnergyFull => "energy_full",
+            Quantity::EnergyFullDesign => "energy_full_design",
+            Quantity::EnergyRate => "energy_rate",
+            Quantity::Voltage => "voltage",
+            Quantity::DesignVoltage => "design_voltage",
+            Quantity::Temperature => "temperature",
+            Quantity::CycleCount => "cycle_count",
+            _ => "unknown",
+        };
+
+        write!(f, "{}", display)
+    }
+}
+
+/// Calculation applied to the source values in order to get the battery value.
+///
+/// Plain unit conversions (for example, `µWh` into `J`) are not listed here.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Conversion {
+    /// `charge × design_voltage`
+    ChargeByDesignVoltage,
+    /// `current × design_voltage`
+    CurrentByDesignVoltage,
+    /// `capacity × energy_full`
+    CapacityByEnergyFull,
+    /// `energy / energy_full`
+    EnergyByEnergyFull,
+    /// `energy_full / energy_full_design`
+    EnergyFullByDesign,
+    /// Value copied from the `energy_full_design`
+    FromEnergyFullDesign,
+
+    // Awaiting for https://github.com/rust-lang/rust/issues/44109
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+impl fmt::Display for Conversion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let display = match self {
+            Conversion::ChargeByDesignVoltage => "charge × design voltage",
+            Conversion::CurrentByDesignVoltage => "current × design voltage",
+            Conversion::CapacityByEnergyFull => "capacity × energy_full",
+            Conversion::EnergyByEnergyFull => "energy / energy_full",
+            Conversion::EnergyFullByDesign => "energy_full / energy_full_design",
+            Conversion::FromEnergyFullDesign => "copied from energy_full_design",
+            _ => "unknown",
+        };
+
+        write!(f, "{}", display)
+    }
+}
+
+/// Correction applied while calculating the battery value.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Adjustment {
+    /// Attribute exists, but its value was ignored (unparseable or out of the sane range).
+    Ignored { attribute: String, raw: String },
+    /// Attribute value was corrected by the [quirk](../quirks/index.html), `raw` is the value before correction.
+    Corrected { attribute: String, raw: String },
+    /// Ratio value was clamped into the `0.0..=1.0` range.
+    Clamped { raw: f32 },
+    /// Energy rate above `100 W` was discarded.
+    RateTooHigh { raw: f32 },
+    /// Energy rate below `10 µW` was discarded.
+    RateTooLow { raw: f32 },
+    /// Energy rate equal to the ACPI "Ones" (`0xFFFF`) value, which means "unknown", was discarded.
+    RateUnknownSentinel,
+
+    // Awaiting for https://github.com/rust-lang/rust/issues/44109
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+impl fmt::Display for Adjustment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Adjustment::Ignored { attribute, raw } => write!(f, "ignored {} = {:?}", attribute, raw),
+            Adjustment::Corrected { attribute, raw } => write!(f, "corrected {} = {:?} by quirk", attribute, raw),
+            Adjustment::Clamped { raw } => write!(f, "clamped {} into 0..=1", raw),
+            Adjustment::RateTooHigh { raw } => write!(f, "discarded rate {} W above 100 W", raw),
+            Adjustment::RateTooLow { raw } => write!(f, "discarded rate {} W below 10 µW", raw),
+            Adjustment::RateUnknownSentinel => write!(f, "discarded ACPI unknown rate sentinel"),
+            _ => write!(f, "unknown adjustment"),
+        }
+    }
+}
+
+/// Device attribute used for the battery value calculation.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Source {
+    attribute: String,
+    raw: String,
+}
+
+impl Source {
+    /// Attribute name, for example, `energy_now` for Linux.
+    pub fn attribute(&self) -> &str {
+        &self.attribute
+    }
+
+    /// Attribute value, as it was read from the device.
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+}
+
+/// Origin of one battery value.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Trace {
+    quantity: Quantity,
+    sources: Vec<Source>,
+    conversion: Option<Conversion>,
+    adjustments: Vec<Adjustment>,
+}
+
+impl Trace {
+    pub(crate) fn new(quantity: Quantity) -> Trace {
+        Trace {
+            quantity,
+            sources: Vec::new(),
+            conversion: None,
+            adjustments: Vec::new(),
+        }
+    }
+
+    pub(crate) fn source<T: Into<String>>(&mut self, attribute: &str, raw: T) {
+        self.sources.push(Source {
+            attribute: attribute.to_string(),
+            raw: raw.into(),
+        });
+    }
+
+    pub(crate) fn convert(&mut self, conversion: Conversion) {
+        self.conversion = Some(conversion);
+    }
+
+    pub(crate) fn adjust(&mut self, adjustment: Adjustment) {
+        self.adjustments.push(adjustment);
+    }
+
+    /// Battery value this trace is about.
+    pub fn quantity(&self) -> Quantity {
+        self.quantity
+    }
+
+    /// Device attributes used for calculation.
+    ///
+    /// Empty if value was not reported by the device and it was not possible to calculate it.
+    pub fn sources(&self) -> &[Source] {
+        &self.sources
+    }
+
+    /// Calculation applied to the sources, if any.
+    pub fn conversion(&self) -> Option<Conversion> {
+        self.conversion
+    }
+
+    /// Corrections applied while calculating the value.
+    pub fn adjustments(&self) -> &[Adjustment] {
+        &self.adjustments
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: ", self.quantity)?;
+        if self.sources.is_empty() {
+            f.write_str("no sources")?;
+        } else {
+            for (idx, source) in self.sources.iter().enumerate() {
+                if idx > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "{} = {:?}", source.attribute, source.raw)?;
+            }
+        }
+        if let Some(conversion) = self.conversion {
+            write!(f, " ({})", conversion)?;
+        }
+        for adjustment in &self.adjustments {
+            write!(f, "; {}", adjustment)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Report about how the battery values were obtained.
+///
+/// This struct is created by the [Battery::diagnostics](../struct.Battery.html#method.diagnostics) method.
+///
+/// `Display` implementation renders it as a human-readable text, one value per line,
+/// which is suitable for pasting into bug reports.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Diagnostics {
+    traces: Vec<Trace>,
+}
+
+impl Diagnostics {
+    pub(crate) fn new(traces: Vec<Trace>) -> Diagnostics {
+        Diagnostics {
+            traces,
+        }
+    }
+
+    /// Replaces traces for the same quantities with the `other` ones.
+    pub(crate) fn merge(&mut self, other: Diagnostics) {
+        for trace in other.traces {
+            match self.traces.iter_mut().find(|existing| existing.quantity == trace.quantity) {
+                Some(existing) => *existing = trace,
+                None => self.traces.push(trace),
+            }
+        }
+    }
+
+    /// Traces for all tracked values.
+    ///
+    /// Empty if platform does not track the values origin.
+    pub fn traces(&self) -> &[Trace] {
+        &self.traces
+    }
+
+    /// Trace for the specific value.
+    pub fn trace(&self, quantity: Quantity) -> Option<&Trace> {
+        self.traces.iter().find(|trace| trace.quantity == quantity)
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for trace in &self.traces {
+            writeln!(f, "{}", trace)?;
+        }
+
+        Ok(())
+    }
+}
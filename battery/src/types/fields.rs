@@ -0,0 +1,157 @@
+use std::fmt;
+use std::ops::{BitAnd, BitOr, BitOrAssign, Not, Sub};
+
+/// Set of the [Battery] values groups which should be updated during the refresh.
+///
+/// Used by the [Manager::refresh_with](struct.Manager.html#method.refresh_with) method
+/// in order to skip reading of values caller is not interested in.
+///
+/// Values can be combined with the `|` operator:
+///
+/// ```edition2018
+/// # use battery::Fields;
+/// let fields = Fields::STATE | Fields::CHARGE;
+///
+/// assert!(fields.contains(Fields::STATE));
+/// assert!(!fields.contains(Fields::VOLTAGE));
+/// ```
+///
+/// [Battery]: struct.Battery.html
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Fields(u8);
+
+impl Fields {
+    /// [Battery::state](struct.Battery.html#method.state) value.
+    pub const STATE: Fields = Fields(1);
+
+    /// [Battery::state_of_charge](struct.Battery.html#method.state_of_charge) value.
+    ///
+    /// Energy values are refreshed together with it, so they are never disagreeing
+    /// with the state of charge.
+    pub const CHARGE: Fields = Fields(1 << 1);
+
+    /// [Battery::energy](struct.Battery.html#method.energy),
+    /// [Battery::energy_full](struct.Battery.html#method.energy_full),
+    /// [Battery::energy_full_design](struct.Battery.html#method.energy_full_design)
+    /// and [Battery::state_of_health](struct.Battery.html#method.state_of_health) values.
+    pub const ENERGY: Fields = Fields(1 << 2);
+
+    /// [Battery::energy_rate](struct.Battery.html#method.energy_rate) value.
+    pub const ENERGY_RATE: Fields = Fields(1 << 3);
+
+    /// [Battery::voltage](struct.Battery.html#method.voltage) value.
+    pub const VOLTAGE: Fields = Fields(1 << 4);
+
+    /// [Battery::temperature](struct.Battery.html#method.temperature) value.
+    pub const TEMPERATURE: Fields = Fields(1 << 5);
+
+    /// [Battery::cycle_count](struct.Battery.html#method.cycle_count) value.
+    pub const CYCLE_COUNT: Fields = Fields(1 << 6);
+
+    /// All values, same as the [Manager::refresh](struct.Manager.html#method.refresh) does.
+    pub const ALL: Fields = Fields((1 << 7) - 1);
+
+    /// Returns an empty set.
+    pub fn empty() -> Fields {
+        Fields(0)
+    }
+
+    /// Returns `true` if there are no values in the set.
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns `true` if all values from the `other` set are present in this set.
+    pub fn contains(self, other: Fields) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns `true` if at least one value from the `other` set is present in this set.
+    pub fn intersects(self, other: Fields) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    /// Adds the values which should be refreshed together with the ones from this set.
+    pub(crate) fn with_implied(self) -> Fields {
+        if self.contains(Fields::CHARGE) {
+            self | Fields::ENERGY
+        } else {
+            self
+        }
+    }
+}
+
+impl Default for Fields {
+    fn default() -> Self {
+        Fields::ALL
+    }
+}
+
+impl BitOr for Fields {
+    type Output = Fields;
+
+    fn bitor(self, rhs: Fields) -> Fields {
+        Fields(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Fields {
+    fn bitor_assign(&mut self, rhs: Fields) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitAnd for Fields {
+    type Output = Fields;
+
+    fn bitand(self, rhs: Fields) -> Fields {
+        Fields(self.0 & rhs.0)
+    }
+}
+
+impl Sub for Fields {
+    type Output = Fields;
+
+    fn sub(self, rhs: Fields) -> Fields {
+        Fields(self.0 & !rhs.0)
+    }
+}
+
+impl Not for Fields {
+    type Output = Fields;
+
+    fn not(self) -> Fields {
+        Fields::ALL - self
+    }
+}
+
+impl fmt::Debug for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names = [
+            (Fields::STATE, "STATE"),
+            (Fields::CHARGE, "CHARGE"),
+            (Fields::ENERGY, "ENERGY"),
+            (Fields::ENERGY_RATE, "ENERGY_RATE"),
+            (Fields::VOLTAGE, "VOLTAGE"),
+            (Fields::TEMPERATURE, "TEMPERATURE"),
+            (Fields::CYCLE_COUNT, "CYCLE_COUNT"),
+        ];
+
+        let mut first = true;
+        for (flag, name) in names.iter() {
+            if self.contains(*flag) {
+                if !first {
+                    f.write_str(" | ")?;
+                }
+                f.write_str(name)?;
+                first = false;
+            }
+        }
+
+        if first {
+            f.write_str("(empty)")?;
+        }
+
+        Ok(())
+    }
+}
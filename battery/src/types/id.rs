@@ -0,0 +1,74 @@
+use std::fmt;
+
+/// Battery identity, which persists across the device re-plugging and system reboots.
+///
+/// Built from the battery vendor, model and serial number,
+/// see the [Battery::id](struct.Battery.html#method.id) method.
+///
+/// Note that identity is [unique](#method.is_unique) only if the serial number is known,
+/// otherwise two batteries of the same model are indistinguishable.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct BatteryId {
+    vendor: Option<String>,
+    model: Option<String>,
+    serial_number: Option<String>,
+}
+
+impl BatteryId {
+    /// Creates identity from the battery vendor, model and serial number.
+    pub fn new<T: Into<String>>(vendor: Option<T>, model: Option<T>, serial_number: Option<T>) -> BatteryId {
+        BatteryId {
+            vendor: vendor.map(Into::into),
+            model: model.map(Into::into),
+            serial_number: serial_number.map(Into::into),
+        }
+    }
+
+    /// Battery vendor.
+    pub fn vendor(&self) -> Option<&str> {
+        self.vendor.as_ref().map(AsRef::as_ref)
+    }
+
+    /// Battery model.
+    pub fn model(&self) -> Option<&str> {
+        self.model.as_ref().map(AsRef::as_ref)
+    }
+
+    /// Battery serial number.
+    pub fn serial_number(&self) -> Option<&str> {
+        self.serial_number.as_ref().map(AsRef::as_ref)
+    }
+
+    /// Returns `true` if identity is able to distinguish this battery from any other one.
+    pub fn is_unique(&self) -> bool {
+        self.serial_number.is_some()
+    }
+}
+
+/// Formats identity as a `vendor-model-serial` string, suitable to be used as a file name.
+///
+/// Unknown parts are replaced with the `unknown` word and characters
+/// other than ASCII alphanumerics, `.` and `_` are replaced with `_`.
+impl fmt::Display for BatteryId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parts = [&self.vendor, &self.model, &self.serial_number];
+        for (idx, part) in parts.iter().enumerate() {
+            if idx > 0 {
+                f.write_str("-")?;
+            }
+            match part {
+                Some(value) if !value.trim().is_empty() => {
+                    for chr in value.trim().chars() {
+                        match chr {
+                            c if c.is_ascii_alphanumeric() || c == '.' || c == '_' => write!(f, "{}", c)?,
+                            _ => f.write_str("_")?,
+                        }
+                    }
+                }
+                _ => f.write_str("unknown")?,
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,10 +1,14 @@
 use std::fmt;
+#[cfg(target_os = "linux")]
+use std::path::PathBuf;
 use std::rc::Rc;
+use std::time::Duration;
 
 use crate::platform::traits::*;
 use crate::platform::Iterator as PlatformIterator;
 use crate::platform::Manager as PlatformManager;
-use crate::{Batteries, Battery, Result};
+use crate::quirks::Quirks;
+use crate::{Batteries, Battery, Fields, RefreshReport, Result};
 
 /// Manager for batteries available in system.
 ///
@@ -37,6 +41,32 @@ impl Manager {
         })
     }
 
+    /// Creates new manager value, which applies additional hardware `quirks`.
+    ///
+    /// `quirks` are used along with the [builtin](quirks/struct.Quirks.html#method.builtin) ones,
+    /// see the [quirks](quirks/index.html) module for details.
+    pub fn with_quirks(quirks: Quirks) -> Result<Manager> {
+        let inner = PlatformManager::with_quirks(quirks)?;
+
+        Ok(Manager {
+            inner: Rc::new(inner),
+        })
+    }
+
+    /// Creates new manager value, which reads batteries from the `root` directory
+    /// instead of the `/sys/class/power_supply`.
+    ///
+    /// Directory should have the same layout, one sub-directory per device.
+    /// Useful for the replayed [archives](archive/index.html) and tests.
+    #[cfg(target_os = "linux")]
+    pub fn with_sysfs_root<T: Into<PathBuf>>(root: T) -> Result<Manager> {
+        let inner = PlatformManager::with_root(root.into());
+
+        Ok(Manager {
+            inner: Rc::new(inner),
+        })
+    }
+
     /// Returns an iterator over available batteries.
     ///
     /// There are no guarantees provided for [batteries] ordering,
@@ -50,10 +80,165 @@ impl Manager {
         Ok(Batteries::from(inner))
     }
 
+    /// Returns an iterator over available batteries, which tolerates broken devices.
+    ///
+    /// Unlike the [Manager::batteries](#method.batteries) method, batteries which values
+    /// can't be loaded are not yielded as an error; all readable values are loaded
+    /// and problems are reported by the [Battery::warnings](struct.Battery.html#method.warnings)
+    /// and [Battery::missing_fields](struct.Battery.html#method.missing_fields) methods.
+    /// Refreshing such batteries is lenient too: values which failed to load are keeping
+    /// their previous values.
+    ///
+    /// Only Linux implementation is loading battery values one by one,
+    /// on other platforms this method is the same as `Manager::batteries`.
+    ///
+    /// # Example
+    ///
+    /// ```edition2018
+    /// # use battery::{Manager, Result};
+    /// # fn main() -> Result<()> {
+    /// for battery in Manager::new()?.batteries_lenient()? {
+    ///     let battery = battery?;
+    ///     for warning in battery.warnings() {
+    ///         eprintln!("{:?}: {}", battery.model(), warning);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn batteries_lenient(&self) -> Result<Batteries> {
+        let inner = PlatformIterator::new_lenient(self.inner.clone())?;
+
+        Ok(Batteries::from(inner))
+    }
+
     /// Refresh battery information in-place.
+    ///
+    /// If the battery was removed and added back (for example, hot-swappable or docking station batteries),
+    /// `battery` is transparently re-bound to it, as long as its [identity] is the same
+    /// and contains the serial number.
+    /// If it is not possible to find the battery anymore, returned error
+    /// [is marked](struct.Error.html#method.is_removed) as the "battery removed" one.
+    ///
+    /// [identity]: struct.BatteryId.html
     pub fn refresh(&self, battery: &mut Battery) -> Result<()> {
         self.inner.refresh(battery)
     }
+
+    /// Refresh only the requested battery values in-place.
+    ///
+    /// Values which are not included into `fields` are not read from the device
+    /// and are keeping their previous values.
+    /// This is useful for frequent polling, for example, status bars are usually
+    /// interested in the battery state and state of charge only.
+    ///
+    /// Note that some platforms (MacOS, Windows and FreeBSD) are fetching all values
+    /// with one system call, so on them this method is not cheaper than the
+    /// [Manager::refresh](#method.refresh) method.
+    ///
+    /// # Example
+    ///
+    /// ```edition2018
+    /// # use battery::{Fields, Manager, Result};
+    /// # fn main() -> Result<()> {
+    /// let manager = Manager::new()?;
+    /// for battery in manager.batteries()? {
+    ///     let mut battery = battery?;
+    ///     manager.refresh_with(&mut battery, Fields::STATE | Fields::CHARGE)?;
+    ///     println!("{}: {:?}", battery.state(), battery.state_of_charge());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn refresh_with(&self, battery: &mut Battery, fields: Fields) -> Result<()> {
+        self.inner.refresh_with(battery, fields)
+    }
+
+    /// Refresh battery information in-place, waiting for at most `timeout` for the device.
+    ///
+    /// Some device drivers (usually the SMBus or embedded controller backed ones) might block
+    /// for seconds while reading the battery attributes; this method allows to protect
+    /// the caller (for example, an UI thread) from such drivers.
+    ///
+    /// Values are read in a separate thread, and if it does not finish in time,
+    /// error with the [`Timeout`] kind is returned, mentioning the attribute which hangs.
+    /// `battery` keeps its last known good values in that case.
+    ///
+    /// Only Linux implementation is able to enforce the `timeout`,
+    /// on other platforms this method is the same as [Manager::refresh](#method.refresh).
+    ///
+    /// [`Timeout`]: enum.ErrorKind.html#variant.Timeout
+    pub fn refresh_timeout(&self, battery: &mut Battery, timeout: Duration) -> Result<()> {
+        self.inner.refresh_timeout(battery, timeout)
+    }
+
+    /// Refresh information for all passed batteries in-place.
+    ///
+    /// Returned [report] contains refresh results for each battery,
+    /// indexes of batteries which are not available in system anymore
+    /// and batteries which appeared since the `batteries` were fetched.
+    ///
+    /// Batteries which failed to load during the enumeration are not reported as added ones.
+    ///
+    /// # Example
+    ///
+    /// ```edition2018
+    /// # use battery::{Manager, Result};
+    /// # fn main() -> Result<()> {
+    /// let manager = Manager::new()?;
+    /// let mut batteries = manager.batteries()?.collect::<Result<Vec<_>>>()?;
+    ///
+    /// let report = manager.refresh_all(&mut batteries)?;
+    /// for idx in report.removed().iter().rev() {
+    ///     batteries.remove(*idx);
+    /// }
+    /// batteries.extend(report.into_added());
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [report]: struct.RefreshReport.html
+    pub fn refresh_all(&self, batteries: &mut [Battery]) -> Result<RefreshReport> {
+        self.refresh_batch(batteries, false)
+    }
+
+    /// Same as [Manager::refresh_all](#method.refresh_all), but reads the devices concurrently.
+    ///
+    /// Only Linux implementation is able to read devices in parallel,
+    /// on other platforms this method is the same as `Manager::refresh_all`.
+    pub fn refresh_all_concurrently(&self, batteries: &mut [Battery]) -> Result<RefreshReport> {
+        self.refresh_batch(batteries, true)
+    }
+
+    fn refresh_batch(&self, batteries: &mut [Battery], concurrently: bool) -> Result<RefreshReport> {
+        let devices = batteries.iter_mut().map(|battery| &mut **battery).collect();
+        let results = self.inner.refresh_all(devices, concurrently);
+
+        let devices = batteries.iter().map(|battery| &**battery).collect::<Vec<_>>();
+        let (present, added) = PlatformIterator::presence(self.inner.clone(), &devices, &results)?;
+        let added = added.into_iter().map(Battery::from).collect();
+
+        // Battery is considered to be removed only if it is not possible
+        // to refresh it and it is missing from the enumeration too
+        let removed = results
+            .iter()
+            .zip(present)
+            .enumerate()
+            .filter(|(_, (result, present))| result.is_err() && !present)
+            .map(|(idx, _)| idx)
+            .collect();
+
+        Ok(RefreshReport::new(results, removed, added))
+    }
+}
+
+#[cfg(test)]
+impl From<PlatformManager> for Manager {
+    fn from(inner: PlatformManager) -> Manager {
+        Manager {
+            inner: Rc::new(inner),
+        }
+    }
 }
 
 impl fmt::Debug for Manager {
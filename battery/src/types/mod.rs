@@ -1,11 +1,25 @@
+mod anomaly;
 mod battery;
+pub mod diagnostics;
+mod fields;
+mod id;
 mod iterator;
 mod manager;
+mod reading;
+mod report;
 mod state;
 mod technology;
+mod warning;
 
+pub use self::anomaly::Anomaly;
 pub use self::battery::Battery;
+pub use self::diagnostics::{Adjustment, Conversion, Diagnostics, Quantity, Trace};
+pub use self::fields::Fields;
+pub use self::id::BatteryId;
 pub use self::iterator::Batteries;
 pub use self::manager::Manager;
+pub use self::reading::{Reading, Readings};
+pub use self::report::RefreshReport;
 pub use self::state::State;
 pub use self::technology::Technology;
+pub use self::warning::Warning;
@@ -0,0 +1,174 @@
+use crate::units::{ElectricPotential, Energy, Power, Ratio, ThermodynamicTemperature, Time};
+
+/// Battery value along with the information about where it came from.
+///
+/// Many device drivers are not reporting all the values, and [Battery] methods
+/// are substituting the missing ones with zeroes (or calculating them from other values)
+/// to keep the API simple. `Reading` allows to distinguish these cases.
+///
+/// See the [Battery::readings](struct.Battery.html#method.readings) method.
+///
+/// [Battery]: struct.Battery.html
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Reading<T> {
+    /// Value is reported by the device as-is (unit conversions aside).
+    Reported(T),
+    /// Value is not reported by the device and was calculated from other values.
+    Derived(T),
+    /// Device is not providing this value and it is not possible to calculate it.
+    Unavailable,
+}
+
+impl<T> Reading<T> {
+    /// Returns the value, if it is available.
+    pub fn value(self) -> Option<T> {
+        match self {
+            Reading::Reported(value) | Reading::Derived(value) => Some(value),
+            Reading::Unavailable => None,
+        }
+    }
+
+    /// Returns `true` if value is reported by the device.
+    pub fn is_reported(&self) -> bool {
+        match self {
+            Reading::Reported(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if value was calculated from other values.
+    pub fn is_derived(&self) -> bool {
+        match self {
+            Reading::Derived(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if value is either reported or derived.
+    pub fn is_available(&self) -> bool {
+        match self {
+            Reading::Unavailable => false,
+            _ => true,
+        }
+    }
+
+    /// Returns the value or the `default` one, if it is not available.
+    pub fn unwrap_or(self, default: T) -> T {
+        self.value().unwrap_or(default)
+    }
+
+    /// Maps value with `f`, keeping its origin.
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Reading<U> {
+        match self {
+            Reading::Reported(value) => Reading::Reported(f(value)),
+            Reading::Derived(value) => Reading::Derived(f(value)),
+            Reading::Unavailable => Reading::Unavailable,
+        }
+    }
+
+    /// Marks available value as the derived one.
+    pub(crate) fn derived(self) -> Reading<T> {
+        match self {
+            Reading::Reported(value) | Reading::Derived(value) => Reading::Derived(value),
+            Reading::Unavailable => Reading::Unavailable,
+        }
+    }
+}
+
+impl<T> From<Option<T>> for Reading<T> {
+    /// Converts the optional value reported by the device.
+    fn from(value: Option<T>) -> Reading<T> {
+        match value {
+            Some(value) => Reading::Reported(value),
+            None => Reading::Unavailable,
+        }
+    }
+}
+
+impl<T> Default for Reading<T> {
+    fn default() -> Self {
+        Reading::Unavailable
+    }
+}
+
+/// Set of the battery values, which are not substituting missing ones with zeroes.
+///
+/// This struct is created by the [Battery::readings](struct.Battery.html#method.readings) method.
+/// Each method is a counterpart of the same [Battery] method, see their documentation for details.
+///
+/// [Battery]: struct.Battery.html
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Readings {
+    pub(crate) state_of_charge: Reading<Ratio>,
+    pub(crate) state_of_health: Reading<Ratio>,
+    pub(crate) energy: Reading<Energy>,
+    pub(crate) energy_full: Reading<Energy>,
+    pub(crate) energy_full_design: Reading<Energy>,
+    pub(crate) energy_rate: Reading<Power>,
+    pub(crate) voltage: Reading<ElectricPotential>,
+    pub(crate) temperature: Reading<ThermodynamicTemperature>,
+    pub(crate) cycle_count: Reading<u32>,
+    pub(crate) time_to_full: Reading<Time>,
+    pub(crate) time_to_empty: Reading<Time>,
+}
+
+impl Readings {
+    /// Battery state of charge.
+    pub fn state_of_charge(&self) -> Reading<Ratio> {
+        self.state_of_charge
+    }
+
+    /// Battery state of health.
+    ///
+    /// Unavailable if either full or design energy is unknown.
+    pub fn state_of_health(&self) -> Reading<Ratio> {
+        self.state_of_health
+    }
+
+    /// Amount of energy currently available in the battery.
+    pub fn energy(&self) -> Reading<Energy> {
+        self.energy
+    }
+
+    /// Amount of energy in the battery when it's considered full.
+    pub fn energy_full(&self) -> Reading<Energy> {
+        self.energy_full
+    }
+
+    /// Amount of energy the battery is designed to hold when it's considered full.
+    pub fn energy_full_design(&self) -> Reading<Energy> {
+        self.energy_full_design
+    }
+
+    /// Amount of energy being drained from the battery.
+    ///
+    /// Values rejected by sanity checks are considered to be unavailable.
+    pub fn energy_rate(&self) -> Reading<Power> {
+        self.energy_rate
+    }
+
+    /// Battery voltage.
+    pub fn voltage(&self) -> Reading<ElectricPotential> {
+        self.voltage
+    }
+
+    /// Battery temperature.
+    pub fn temperature(&self) -> Reading<ThermodynamicTemperature> {
+        self.temperature
+    }
+
+    /// Number of charge/discharge cycles.
+    pub fn cycle_count(&self) -> Reading<u32> {
+        self.cycle_count
+    }
+
+    /// Remaining time till full battery.
+    pub fn time_to_full(&self) -> Reading<Time> {
+        self.time_to_full
+    }
+
+    /// Remaining time till empty battery.
+    pub fn time_to_empty(&self) -> Reading<Time> {
+        self.time_to_empty
+    }
+}
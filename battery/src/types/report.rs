@@ -0,0 +1,62 @@
+use std::fmt;
+
+use crate::{Battery, Result};
+
+/// Outcome of the batch refresh.
+///
+/// This struct is created by the [Manager::refresh_all](struct.Manager.html#method.refresh_all)
+/// and [Manager::refresh_all_concurrently](struct.Manager.html#method.refresh_all_concurrently) methods.
+/// See their documentation for more.
+pub struct RefreshReport {
+    results: Vec<Result<()>>,
+    removed: Vec<usize>,
+    added: Vec<Battery>,
+}
+
+impl RefreshReport {
+    pub(crate) fn new(results: Vec<Result<()>>, removed: Vec<usize>, added: Vec<Battery>) -> RefreshReport {
+        RefreshReport {
+            results,
+            removed,
+            added,
+        }
+    }
+
+    /// Refresh results for each battery, in the same order as batteries were passed.
+    pub fn results(&self) -> &[Result<()>] {
+        &self.results
+    }
+
+    /// Indexes of the passed batteries which are not available in system anymore.
+    ///
+    /// Information stored in these batteries is stale and they should be dropped by caller.
+    pub fn removed(&self) -> &[usize] {
+        &self.removed
+    }
+
+    /// Batteries which appeared in system since the previous enumeration
+    /// and were not passed to the refresh call.
+    pub fn added(&self) -> &[Battery] {
+        &self.added
+    }
+
+    /// Consumes the report, returning the newly appeared batteries.
+    pub fn into_added(self) -> Vec<Battery> {
+        self.added
+    }
+
+    /// Returns `true` if all passed batteries were refreshed successfully.
+    pub fn is_ok(&self) -> bool {
+        self.results.iter().all(Result::is_ok)
+    }
+}
+
+impl fmt::Debug for RefreshReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RefreshReport")
+            .field("results", &self.results)
+            .field("removed", &self.removed)
+            .field("added", &self.added)
+            .finish()
+    }
+}
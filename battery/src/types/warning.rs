@@ -0,0 +1,44 @@
+use std::fmt;
+
+use crate::{Error, Fields};
+
+/// Problem which happened while loading the battery in a lenient mode.
+///
+/// See the [Manager::batteries_lenient](struct.Manager.html#method.batteries_lenient) method.
+#[derive(Debug)]
+pub struct Warning {
+    fields: Fields,
+    error: Error,
+}
+
+impl Warning {
+    pub(crate) fn new(fields: Fields, error: Error) -> Warning {
+        Warning {
+            fields,
+            error,
+        }
+    }
+
+    /// Battery values which were not loaded because of this problem.
+    ///
+    /// Set is empty if the problem happened while loading the static information,
+    /// like the battery vendor or model.
+    pub fn fields(&self) -> Fields {
+        self.fields
+    }
+
+    /// Error which happened while loading the values.
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.fields.is_empty() {
+            write!(f, "{}", self.error)
+        } else {
+            write!(f, "{:?}: {}", self.fields, self.error)
+        }
+    }
+}
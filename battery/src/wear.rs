@@ -0,0 +1,408 @@
+//! Battery capacity fade tracking.
+//!
+//! [WearTracker] persists the battery full energy and cycle count once per interval (one day by default)
+//! per each [BatteryId](../struct.BatteryId.html). [analyze] fits linear trends into the recorded
+//! [points](struct.WearPoint.html) and reports how fast the capacity fades per month and per cycle,
+//! and when it is expected to drop below the replacement threshold (80% by default).
+//!
+//! Points are kept in the `wear` file of the battery directory, see the [storage layout](../history/index.html#storage).
+//!
+//! This module requires the `history` feature.
+//!
+//! Capacity is measured relative to the design energy; if the device does not report it,
+//! the first recorded full energy is used instead.
+//!
+//! # Example
+//!
+//! ```edition2018,no_run
+//! # use battery::Result;
+//! use battery::wear::WearTracker;
+//!
+//! # fn main() -> Result<()> {
+//! let tracker = WearTracker::open("/var/lib/battery/wear")?;
+//! for battery in battery::Manager::new()?.batteries()? {
+//!     let battery = battery?;
+//!     tracker.record(&battery)?;
+//!     if let Some(report) = tracker.report(&battery.id())? {
+//!         println!("{:?}, replace at {:?}", report.fade_per_month(), report.replacement());
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [WearTracker]: struct.WearTracker.html
+//! [analyze]: fn.analyze.html
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::history::store::{self, Store};
+use crate::history::{seconds, unix_seconds};
+
+use crate::units::energy::joule;
+use crate::units::ratio::ratio;
+use crate::units::{Energy, Ratio};
+use crate::{Battery, BatteryId, Result};
+
+#[allow(clippy::redundant_static_lifetimes)]
+static HEADER: &'static str = "# battery wear v1";
+#[allow(clippy::redundant_static_lifetimes)]
+static FILE: &'static str = "wear";
+
+/// Average month duration in seconds.
+const MONTH: f64 = 30.436_875 * 24.0 * 60.0 * 60.0;
+
+/// Recorded battery capacity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WearPoint {
+    time: SystemTime,
+    energy_full: Energy,
+    energy_full_design: Option<Energy>,
+    cycle_count: Option<u32>,
+}
+
+impl WearPoint {
+    pub fn new(
+        time: SystemTime,
+        energy_full: Energy,
+        energy_full_design: Option<Energy>,
+        cycle_count: Option<u32>,
+    ) -> WearPoint {
+        WearPoint {
+            time,
+            energy_full,
+            energy_full_design,
+            cycle_count,
+        }
+    }
+
+    /// Creates point from the `battery` readings, `None` if the full energy is not available.
+    pub fn from_battery(battery: &Battery, time: SystemTime) -> Option<WearPoint> {
+        let readings = battery.readings();
+        let energy_full = readings.energy_full().value()?;
+
+        Some(WearPoint::new(
+            time,
+            energy_full,
+            readings.energy_full_design().value(),
+            readings.cycle_count().value(),
+        ))
+    }
+
+    pub fn time(&self) -> SystemTime {
+        self.time
+    }
+
+    pub fn energy_full(&self) -> Energy {
+        self.energy_full
+    }
+
+    pub fn energy_full_design(&self) -> Option<Energy> {
+        self.energy_full_design
+    }
+
+    pub fn cycle_count(&self) -> Option<u32> {
+        self.cycle_count
+    }
+
+    fn to_line(&self) -> String {
+        let optional = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+        format!(
+            "{} {} {} {}",
+            unix_seconds(self.time),
+            self.energy_full.get::<joule>(),
+            optional(self.energy_full_design.map(|value| value.get::<joule>().to_string())),
+            optional(self.cycle_count.map(|value| value.to_string())),
+        )
+    }
+
+    fn from_line(line: &str) -> Option<WearPoint> {
+        let mut parts = line.split_whitespace();
+        let time = parts.next()?.parse::<u64>().ok()?;
+        let energy_full = parts.next()?.parse::<f32>().ok()?;
+        let energy_full_design = match parts.next()? {
+            "-" => None,
+            value => Some(value.parse::<f32>().ok()?),
+        };
+        let cycle_count = match parts.next()? {
+            "-" => None,
+            value => Some(value.parse::<u32>().ok()?),
+        };
+        if parts.next().is_some() {
+            return None;
+        }
+
+        Some(WearPoint::new(
+            UNIX_EPOCH + Duration::from_secs(time),
+            Energy::new::<joule>(energy_full),
+            energy_full_design.map(Energy::new::<joule>),
+            cycle_count,
+        ))
+    }
+}
+
+/// Capacity fade analysis result.
+///
+/// Fade values are fractions of the reference (design) energy and are positive when the battery wears,
+/// for example, `0.0005` per cycle means that each cycle takes 0.05% of the capacity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WearReport {
+    points: usize,
+    state_of_health: Ratio,
+    fade_per_month: Option<Ratio>,
+    fade_per_cycle: Option<Ratio>,
+    replacement: Option<SystemTime>,
+}
+
+impl WearReport {
+    /// Number of points the report is based on.
+    pub fn points(&self) -> usize {
+        self.points
+    }
+
+    /// Latest full energy to the reference energy ratio.
+    pub fn state_of_health(&self) -> Ratio {
+        self.state_of_health
+    }
+
+    /// Capacity fade per month, `None` if the points are covering less than one day.
+    pub fn fade_per_month(&self) -> Option<Ratio> {
+        self.fade_per_month
+    }
+
+    /// Capacity fade per charge cycle, `None` if the cycle count was not changing or is not reported.
+    pub fn fade_per_cycle(&self) -> Option<Ratio> {
+        self.fade_per_cycle
+    }
+
+    /// Moment when the capacity trend crosses the replacement threshold.
+    ///
+    /// It could be in the past, if the battery already crossed it,
+    /// and it is `None`, if the capacity is not fading.
+    pub fn replacement(&self) -> Option<SystemTime> {
+        self.replacement
+    }
+}
+
+/// Fits the capacity trend into time-ordered `points`.
+///
+/// Returns `None` if there are no points.
+pub fn analyze(points: &[WearPoint], threshold: Ratio) -> Option<WearReport> {
+    let first = points.first()?;
+    let last = points.last()?;
+    let reference = points
+        .iter()
+        .rev()
+        .find_map(WearPoint::energy_full_design)
+        .filter(|design| design.get::<joule>() > 0.0)
+        .unwrap_or(first.energy_full)
+        .get::<joule>() as f64;
+    if reference <= 0.0 {
+        return None;
+    }
+    let capacity = |point: &WearPoint| point.energy_full.get::<joule>() as f64 / reference;
+    let time = |point: &WearPoint| seconds(point.time.duration_since(UNIX_EPOCH).unwrap_or_default());
+
+    let span = time(last) - time(first);
+    let by_time = if span >= 24.0 * 60.0 * 60.0 {
+        fit(points.iter().map(|point| (time(point), capacity(point))))
+    } else {
+        None
+    };
+    let by_cycle = fit(
+        points
+            .iter()
+            .filter_map(|point| point.cycle_count.map(|cycles| (f64::from(cycles), capacity(point)))),
+    );
+
+    let replacement = by_time.and_then(|(slope, intercept)| {
+        if slope >= 0.0 {
+            return None;
+        }
+        let crossing = (f64::from(threshold.get::<ratio>()) - intercept) / slope;
+        if crossing >= 0.0 {
+            Some(UNIX_EPOCH + Duration::from_secs(crossing as u64))
+        } else {
+            Some(UNIX_EPOCH)
+        }
+    });
+
+    Some(WearReport {
+        points: points.len(),
+        state_of_health: Ratio::new::<ratio>(capacity(last) as f32),
+        fade_per_month: by_time.map(|(slope, _)| Ratio::new::<ratio>((-slope * MONTH) as f32)),
+        fade_per_cycle: by_cycle.map(|(slope, _)| Ratio::new::<ratio>(-slope as f32)),
+        replacement,
+    })
+}
+
+/// Least squares linear fit, returns slope and intercept,
+/// or `None` if there are less than two distinct `x` values.
+fn fit<I: Iterator<Item = (f64, f64)>>(values: I) -> Option<(f64, f64)> {
+    let values = values.collect::<Vec<_>>();
+    if values.len() < 2 {
+        return None;
+    }
+    let len = values.len() as f64;
+    let mean_x = values.iter().map(|(x, _)| x).sum::<f64>() / len;
+    let mean_y = values.iter().map(|(_, y)| y).sum::<f64>() / len;
+    let covariance = values.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum::<f64>();
+    let variance = values.iter().map(|(x, _)| (x - mean_x).powi(2)).sum::<f64>();
+    if variance == 0.0 {
+        return None;
+    }
+
+    let slope = covariance / variance;
+    Some((slope, mean_y - slope * mean_x))
+}
+
+/// Persistent capacity fade tracker.
+///
+/// See the [module documentation](index.html) for details.
+#[derive(Debug, Clone)]
+pub struct WearTracker {
+    store: Store,
+    interval: Duration,
+    threshold: Ratio,
+}
+
+impl WearTracker {
+    /// Opens tracker in the `dir` root directory, creating it if necessary.
+    pub fn open<T: Into<PathBuf>>(dir: T) -> Result<WearTracker> {
+        Ok(WearTracker {
+            store: Store::open(dir.into())?,
+            interval: Duration::from_secs(24 * 60 * 60),
+            threshold: Ratio::new::<ratio>(0.8),
+        })
+    }
+
+    /// Sets the minimal interval between the recorded points.
+    pub fn with_interval(mut self, interval: Duration) -> WearTracker {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets the state of health, at which battery should be replaced.
+    pub fn with_threshold(mut self, threshold: Ratio) -> WearTracker {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        self.store.root()
+    }
+
+    /// Records the `battery` capacity, if the interval since the last point has passed.
+    ///
+    /// Returns `true` if the point was recorded.
+    pub fn record(&self, battery: &Battery) -> Result<bool> {
+        match WearPoint::from_battery(battery, SystemTime::now()) {
+            Some(point) => self.record_point(&battery.id(), point),
+            None => Ok(false),
+        }
+    }
+
+    /// Records the battery `id` point, if the interval since the last point has passed.
+    ///
+    /// Returns `true` if the point was recorded.
+    pub fn record_point(&self, id: &BatteryId, point: WearPoint) -> Result<bool> {
+        if let Some(last) = self.points(id)?.last() {
+            let elapsed = point.time.duration_since(last.time).unwrap_or_default();
+            if elapsed < self.interval {
+                return Ok(false);
+            }
+        }
+
+        store::append_record(&self.store.file(id, FILE)?, HEADER, &point.to_line())?;
+
+        Ok(true)
+    }
+
+    /// Loads the battery `id` points, ordered by time.
+    pub fn points(&self, id: &BatteryId) -> Result<Vec<WearPoint>> {
+        let mut points = match self.store.find_file(id, FILE)? {
+            Some(path) => store::read_records(&path, "wear", WearPoint::from_line)?,
+            None => Vec::new(),
+        };
+        points.sort_by_key(WearPoint::time);
+
+        Ok(points)
+    }
+
+    /// Analyzes the battery `id` points with the configured threshold, `None` if there are no points.
+    pub fn report(&self, id: &BatteryId) -> Result<Option<WearReport>> {
+        Ok(analyze(&self.points(id)?, self.threshold))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use approx::assert_abs_diff_eq;
+
+    use crate::units::energy::watt_hour;
+    use crate::units::ratio::{percent, ratio};
+    use crate::units::{Energy, Ratio};
+    use super::{analyze, WearPoint, WearTracker};
+    use crate::history::tests::id;
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    /// Battery losing 1 Wh of 50 Wh every 30 days and 10 cycles.
+    fn point(day: u64) -> WearPoint {
+        WearPoint::new(
+            UNIX_EPOCH + Duration::from_secs(1_600_000_000 + day * DAY),
+            Energy::new::<watt_hour>(50.0 - day as f32 / 30.0),
+            Some(Energy::new::<watt_hour>(50.0)),
+            Some(day as u32 / 3),
+        )
+    }
+
+    #[test]
+    fn test_analyze() {
+        let points = (0..=90).step_by(10).map(point).collect::<Vec<_>>();
+        let report = analyze(&points, Ratio::new::<percent>(80.0)).unwrap();
+
+        assert_eq!(report.points(), 10);
+        assert_abs_diff_eq!(report.state_of_health().get::<percent>(), 94.0, epsilon = 0.01);
+        // 2% per 30 days
+        assert_abs_diff_eq!(report.fade_per_month().unwrap().get::<percent>(), 2.029, epsilon = 0.01);
+        assert_abs_diff_eq!(report.fade_per_cycle().unwrap().get::<percent>(), 0.2, epsilon = 0.01);
+        // 80% is reached after 300 days
+        let replacement = report.replacement().unwrap().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        assert!((replacement as i64 - (1_600_000_000 + 300 * DAY) as i64).abs() < 60 * 60);
+
+        let stable = vec![point(0), WearPoint::new(point(40).time(), point(0).energy_full(), None, None)];
+        let report = analyze(&stable, Ratio::new::<ratio>(0.8)).unwrap();
+        assert_eq!(report.replacement(), None);
+        assert_eq!(report.fade_per_cycle(), None);
+        assert!(analyze(&[], Ratio::new::<ratio>(0.8)).is_none());
+    }
+
+    #[test]
+    fn test_tracker() {
+        let root = tempfile::tempdir().unwrap();
+        let tracker = WearTracker::open(root.path()).unwrap().with_threshold(Ratio::new::<percent>(90.0));
+        let id = id();
+
+        assert!(tracker.report(&id).unwrap().is_none());
+        for day in 0..60 {
+            let recorded = tracker.record_point(&id, point(day)).unwrap();
+            assert!(recorded);
+            // Same day points are skipped
+            let later = WearPoint::new(point(day).time() + Duration::from_secs(60), point(day).energy_full(), None, None);
+            assert!(!tracker.record_point(&id, later).unwrap());
+        }
+
+        assert!(root.path().join(id.to_string()).join("wear").is_file());
+        let points = tracker.points(&id).unwrap();
+        assert_eq!(points.len(), 60);
+        assert_eq!(points[59].cycle_count(), Some(19));
+        let report = tracker.report(&id).unwrap().unwrap();
+        // 90% is reached after 150 days
+        let replacement = report.replacement().unwrap().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        assert!((replacement as i64 - (1_600_000_000 + 150 * DAY) as i64).abs() < 60 * 60);
+    }
+}